@@ -69,6 +69,14 @@ fn expand_syntax(ast: DeriveInput) -> Result<TokenStream, Vec<syn::Error>> {
                 unsafe { ::std::mem::transmute::<u32, #name>(raw.0) }
             }
 
+            fn try_from_raw(raw: ::cstree::RawSyntaxKind) -> ::core::option::Option<Self> {
+                if raw.0 < #variant_count {
+                    ::core::option::Option::Some(Self::from_raw(raw))
+                } else {
+                    ::core::option::Option::None
+                }
+            }
+
             fn into_raw(self) -> ::cstree::RawSyntaxKind {
                 ::cstree::RawSyntaxKind(self as u32)
             }
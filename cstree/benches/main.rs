@@ -3,9 +3,9 @@ use cstree::{
     build::*,
     green::GreenNode,
     interning::{new_interner, Interner},
+    syntax::SyntaxNode,
     RawSyntaxKind, Syntax,
 };
-
 #[derive(Debug)]
 pub enum Element<'s> {
     Node(Vec<Element<'s>>),
@@ -54,6 +54,32 @@ where
     node
 }
 
+pub fn build_arena_recursive<I>(
+    root: &Element<'_>,
+    builder: &mut ArenaGreenNodeBuilder<'_, TestKind, I>,
+    mut from: u32,
+) -> u32
+where
+    I: Interner,
+{
+    match root {
+        Element::Node(children) => {
+            builder.start_node(TestKind::Element { n: from });
+            for child in children {
+                from = build_arena_recursive(child, builder, from + 1);
+            }
+            builder.finish_node();
+        }
+        Element::Token(text) => {
+            builder.token(TestKind::Element { n: from }, text);
+        }
+        Element::Plus => {
+            builder.static_token(TestKind::Plus);
+        }
+    }
+    from
+}
+
 pub fn build_recursive<I>(
     root: &Element<'_>,
     builder: &mut GreenNodeBuilder<'_, '_, TestKind, I>,
@@ -93,6 +119,11 @@ fn two_level_tree() -> Element<'static> {
     ])
 }
 
+fn large_tree() -> Element<'static> {
+    use Element::*;
+    Node((0..1_000).map(|_| two_level_tree()).collect())
+}
+
 pub fn create(c: &mut Criterion) {
     #[cfg(not(feature = "lasso_compat"))]
     const GROUP_NAME: &str = "two-level tree (default interner)";
@@ -123,5 +154,98 @@ pub fn create(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, create);
+pub fn arena(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large tree");
+    group.throughput(Throughput::Elements(1));
+
+    let mut interner = new_interner();
+    let tree = large_tree();
+
+    group.bench_function("standard builder", |b| {
+        b.iter(|| {
+            let mut cache = NodeCache::with_interner(&mut interner);
+            let tree = build_tree_with_cache(&tree, &mut cache, true);
+            black_box(tree);
+        })
+    });
+
+    group.bench_function("arena builder", |b| {
+        b.iter(|| {
+            let mut builder = ArenaGreenNodeBuilder::with_interner(&mut interner);
+            build_arena_recursive(&tree, &mut builder, 0);
+            let (tree, _) = builder.finish();
+            black_box(tree);
+        })
+    });
+
+    group.finish();
+}
+
+fn punctuation_tree() -> Element<'static> {
+    use Element::*;
+    Node(
+        (0..1_000)
+            .map(|i| {
+                if i % 2 == 0 {
+                    Node(vec![Plus])
+                } else {
+                    Node(vec![Token("x"), Plus, Token("y")])
+                }
+            })
+            .collect(),
+    )
+}
+
+pub fn dedup_threshold(c: &mut Criterion) {
+    let mut group = c.benchmark_group("punctuation-heavy tree");
+    group.throughput(Throughput::Elements(1));
+
+    let mut interner = new_interner();
+    let tree = punctuation_tree();
+
+    for threshold in [0, 2] {
+        group.bench_function(format!("dedup threshold {threshold}"), |b| {
+            b.iter(|| {
+                let mut cache = NodeCache::with_interner(&mut interner).with_dedup_threshold(threshold);
+                let tree = build_tree_with_cache(&tree, &mut cache, true);
+                black_box(tree);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+pub fn traversal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("downward traversal");
+    group.throughput(Throughput::Elements(1));
+
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let tree = build_tree_with_cache(&large_tree(), &mut cache, true);
+
+    group.bench_function("syntax nodes", |b| {
+        b.iter(|| {
+            let root: SyntaxNode<TestKind> = SyntaxNode::new_root(tree.clone());
+            let len: u32 = root.descendants().map(|node| u32::from(node.text_range().len())).sum();
+            black_box(len);
+        })
+    });
+
+    group.bench_function("thin nodes", |b| {
+        b.iter(|| {
+            let root: SyntaxNode<TestKind> = SyntaxNode::new_root(tree.clone());
+            let len: u32 = root
+                .thin()
+                .descendants()
+                .map(|node| u32::from(node.text_range().len()))
+                .sum();
+            black_box(len);
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, create, arena, dedup_threshold, traversal);
 criterion_main!(benches);
@@ -2,16 +2,30 @@
 //! The [`GreenNodeBuilder`](crate::build::GreenNodeBuilder) from the [`build` module](crate::build) is the main entry
 //! point to constructing [`GreenNode`]s and [`GreenToken`]s.
 
+pub(super) mod arena;
+mod binary;
 pub(super) mod builder;
 mod element;
+pub(super) mod growable;
 mod iter;
+mod mmap;
 mod node;
 mod token;
+mod validate;
 
 pub(crate) use self::element::GreenElementRef;
-use self::element::{GreenElement, PackedGreenElement};
+use self::element::PackedGreenElement;
 
-pub use self::{iter::GreenNodeChildren, node::GreenNode, token::GreenToken};
+pub use self::{
+    arena::{ArenaElementRef, ArenaGreenNode, ArenaNodeChildren, ArenaNodeRef, ArenaTokenRef, ArenaTokens},
+    binary::{DecodeError, DEFAULT_MAX_DEPTH},
+    element::GreenElement,
+    iter::GreenNodeChildren,
+    mmap::{MmapElement, MmapNode, MmapNodeChildren, MmapToken, MmapTree},
+    node::GreenNode,
+    token::GreenToken,
+    validate::ValidationError,
+};
 
 #[cfg(test)]
 mod tests {
@@ -37,9 +51,26 @@ mod tests {
 
         assert_eq!(size_of::<GreenNode>(),          size_of::<*const u8>());
         assert_eq!(size_of::<GreenToken>(),         size_of::<*const u8>());
-        assert_eq!(size_of::<GreenNodeHead>(),      size_of::<u32>() * 3);
+        assert_eq!(size_of::<GreenNodeHead>(),      size_of::<u32>() * 4);
         assert_eq!(size_of::<GreenTokenData>(),     size_of::<u32>() * 3);
         assert_eq!(size_of::<GreenElement>(),       size_of::<*const u8>() * 2);
         assert_eq!(size_of::<PackedGreenElement>(), size_of::<*const u8>());
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    #[cfg(target_pointer_width = "64")]
+    fn flags_fit_into_padding() {
+        use crate::{text::TextSize, RawSyntaxKind};
+        use std::mem::size_of;
+        use triomphe::HeaderWithLength;
+
+        // the node flags fill what would otherwise be padding before the number of children
+        #[allow(dead_code)]
+        struct HeadWithoutFlags(RawSyntaxKind, TextSize, u32);
+        assert_eq!(
+            size_of::<HeaderWithLength<GreenNodeHead>>(),
+            size_of::<HeaderWithLength<HeadWithoutFlags>>()
+        );
+    }
 }
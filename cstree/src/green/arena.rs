@@ -0,0 +1,629 @@
+//! Arena-backed green trees.
+//!
+//! A [`GreenNode`] allocates its children individually on the heap. For very large inputs, this can put a lot of
+//! pressure on the allocator. An [`ArenaGreenNode`] instead stores a tree in two contiguous buffers: one with the
+//! kind, text length and range of children of every node, and one with the children of _all_ nodes, one child list
+//! after the other. Building the tree then needs only a handful of (amortized) allocations for the two buffers, and
+//! traversal stays within the same regions of memory.
+//!
+//! Arena trees are built with an [`ArenaGreenNodeBuilder`](crate::build::ArenaGreenNodeBuilder), which offers the
+//! same interface as the regular [`GreenNodeBuilder`](crate::build::GreenNodeBuilder). They do not deduplicate
+//! nodes, as every node is stored inline, but they do intern token text.
+
+use std::{fmt, iter::FusedIterator, slice};
+
+use crate::{
+    green::{GreenNode, GreenToken},
+    interning::{new_interner, Interner, Resolver, TokenInterner, TokenKey},
+    text::TextSize,
+    util::NodeOrToken,
+    utility_types::MaybeOwned,
+    RawSyntaxKind, Syntax,
+};
+
+use super::{
+    builder::{token_text_len, BuildError, Checkpoint},
+    token::GreenTokenData,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct ArenaNodeData {
+    kind: RawSyntaxKind,
+    text_len: TextSize,
+    children_start: u32,
+    children_len: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ArenaChild {
+    Node(u32),
+    Token(GreenTokenData),
+}
+
+/// A self-contained green tree whose nodes are stored in one buffer and whose child lists are stored in another.
+///
+/// Obtain an `ArenaGreenNode` from an [`ArenaGreenNodeBuilder`](crate::build::ArenaGreenNodeBuilder). The tree can
+/// be inspected directly through [`root`](ArenaGreenNode::root), whose API mirrors that of [`GreenNode`], or be
+/// converted into a regular green tree with [`to_green`](ArenaGreenNode::to_green) when a
+/// [`SyntaxNode`](crate::syntax::SyntaxNode) is required.
+#[derive(Clone)]
+pub struct ArenaGreenNode {
+    nodes:    Vec<ArenaNodeData>,
+    children: Vec<ArenaChild>,
+}
+
+impl ArenaGreenNode {
+    /// The root node of this tree.
+    #[inline]
+    pub fn root(&self) -> ArenaNodeRef<'_> {
+        ArenaNodeRef {
+            arena: self,
+            index: self.nodes.len() as u32 - 1,
+        }
+    }
+
+    /// [`RawSyntaxKind`] of the root node.
+    #[inline]
+    pub fn kind(&self) -> RawSyntaxKind {
+        self.root().kind()
+    }
+
+    /// Returns the length of text covered by the whole tree.
+    #[inline]
+    pub fn text_len(&self) -> TextSize {
+        self.root().text_len()
+    }
+
+    /// Iterator over all children of the root node.
+    #[inline]
+    pub fn children(&self) -> ArenaNodeChildren<'_> {
+        self.root().children()
+    }
+
+    /// The number of nodes in this tree, including the root.
+    #[inline]
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns an iterator over all tokens of the tree, in document order.
+    #[inline]
+    pub fn tokens(&self) -> ArenaTokens<'_> {
+        self.root().tokens()
+    }
+
+    /// Returns the source text of the whole tree, resolved with `resolver`. See
+    /// [`ArenaNodeRef::resolve_text`].
+    #[inline]
+    pub fn resolve_text<S, R>(&self, resolver: &R) -> String
+    where
+        S: Syntax,
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        self.root().resolve_text::<S, R>(resolver)
+    }
+
+    /// Converts this tree into a regular [`GreenNode`], for example to create a
+    /// [`SyntaxNode`](crate::syntax::SyntaxNode) from it. Token text is not re-interned, so the result can be
+    /// resolved with the same interner as the arena tree.
+    pub fn to_green(&self) -> GreenNode {
+        self.root().to_green()
+    }
+}
+
+impl fmt::Debug for ArenaGreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArenaGreenNode")
+            .field("nodes", &self.nodes.len())
+            .field("children", &self.children.len())
+            .finish()
+    }
+}
+
+/// A reference to a node inside an [`ArenaGreenNode`].
+#[derive(Clone, Copy)]
+pub struct ArenaNodeRef<'a> {
+    arena: &'a ArenaGreenNode,
+    index: u32,
+}
+
+impl<'a> ArenaNodeRef<'a> {
+    #[inline]
+    fn data(&self) -> &'a ArenaNodeData {
+        &self.arena.nodes[self.index as usize]
+    }
+
+    /// [`RawSyntaxKind`] of this node.
+    #[inline]
+    pub fn kind(&self) -> RawSyntaxKind {
+        self.data().kind
+    }
+
+    /// Returns the length of text covered by this node.
+    #[inline]
+    pub fn text_len(&self) -> TextSize {
+        self.data().text_len
+    }
+
+    /// Iterator over all children of this node.
+    #[inline]
+    pub fn children(&self) -> ArenaNodeChildren<'a> {
+        let data = self.data();
+        let start = data.children_start as usize;
+        let end = start + data.children_len as usize;
+        ArenaNodeChildren {
+            arena: self.arena,
+            inner: self.arena.children[start..end].iter(),
+        }
+    }
+
+    /// Returns the child of this node at `index`, if any.
+    #[inline]
+    pub fn child(&self, index: usize) -> Option<ArenaElementRef<'a>> {
+        self.children().nth(index)
+    }
+
+    /// Returns an iterator over all tokens in the subtree of this node, in document order.
+    ///
+    /// The tree is traversed directly in the arena, without converting any part of it into [`GreenNode`]s.
+    #[inline]
+    pub fn tokens(&self) -> ArenaTokens<'a> {
+        ArenaTokens {
+            stack: vec![self.children()],
+        }
+    }
+
+    /// Returns the source text of this node, i.e. the combined text of all tokens in its subtree, resolved with
+    /// `resolver`. The text of tokens without text of their own is the [static text](Syntax::static_text) of their
+    /// kind `S`.
+    ///
+    /// ## Panics
+    /// If a token has no text of its own and its kind has no static text either.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::build::ArenaGreenNodeBuilder;
+    /// let mut builder: ArenaGreenNodeBuilder<MySyntax> = ArenaGreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// let (tree, interner) = builder.finish();
+    /// let interner = interner.unwrap();
+    /// assert_eq!(tree.root().resolve_text::<MySyntax, _>(&interner), "1+2");
+    /// assert_eq!(tree.tokens().count(), 3);
+    /// ```
+    pub fn resolve_text<S, R>(&self, resolver: &R) -> String
+    where
+        S: Syntax,
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        let mut text = String::with_capacity(u32::from(self.text_len()) as usize);
+        for token in self.tokens() {
+            match token.text(resolver) {
+                Some(token_text) => text.push_str(token_text),
+                None => {
+                    let kind = S::from_raw(token.kind());
+                    let static_text =
+                        S::static_text(kind).unwrap_or_else(|| panic!("Missing static text for '{kind:?}'"));
+                    text.push_str(static_text);
+                }
+            }
+        }
+        text
+    }
+
+    /// Converts the subtree rooted at this node into a regular [`GreenNode`].
+    pub fn to_green(&self) -> GreenNode {
+        let children: Vec<_> = self
+            .children()
+            .map(|child| match child {
+                NodeOrToken::Node(node) => NodeOrToken::Node(node.to_green()),
+                NodeOrToken::Token(token) => NodeOrToken::Token(GreenToken::new(*token.data)),
+            })
+            .collect();
+        GreenNode::new(self.kind(), children)
+    }
+}
+
+impl fmt::Debug for ArenaNodeRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArenaNodeRef")
+            .field("kind", &self.kind())
+            .field("text_len", &self.text_len())
+            .finish()
+    }
+}
+
+/// A reference to a token inside an [`ArenaGreenNode`].
+#[derive(Clone, Copy)]
+pub struct ArenaTokenRef<'a> {
+    data: &'a GreenTokenData,
+}
+
+impl<'a> ArenaTokenRef<'a> {
+    /// [`RawSyntaxKind`] of this token.
+    #[inline]
+    pub fn kind(&self) -> RawSyntaxKind {
+        self.data.kind
+    }
+
+    /// The original source text of this token.
+    #[inline]
+    pub fn text<'i, I>(&self, resolver: &'i I) -> Option<&'i str>
+    where
+        I: Resolver<TokenKey> + ?Sized,
+    {
+        self.data.text.map(|key| resolver.resolve(key))
+    }
+
+    /// Returns the length of text covered by this token.
+    #[inline]
+    pub fn text_len(&self) -> TextSize {
+        self.data.text_len
+    }
+
+    /// Returns the interned key of text covered by this token.
+    ///
+    /// See also [`GreenToken::text_key`].
+    #[inline]
+    pub fn text_key(&self) -> Option<TokenKey> {
+        self.data.text
+    }
+}
+
+impl fmt::Debug for ArenaTokenRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArenaTokenRef")
+            .field("kind", &self.data.kind)
+            .field("text", &self.data.text)
+            .finish()
+    }
+}
+
+/// A reference to an element (node or token) inside an [`ArenaGreenNode`].
+pub type ArenaElementRef<'a> = NodeOrToken<ArenaNodeRef<'a>, ArenaTokenRef<'a>>;
+
+impl ArenaElementRef<'_> {
+    /// Returns kind of this element.
+    #[inline]
+    pub fn kind(&self) -> RawSyntaxKind {
+        match self {
+            NodeOrToken::Node(it) => it.kind(),
+            NodeOrToken::Token(it) => it.kind(),
+        }
+    }
+
+    /// Returns the length of the text covered by this element.
+    #[inline]
+    pub fn text_len(&self) -> TextSize {
+        match self {
+            NodeOrToken::Node(it) => it.text_len(),
+            NodeOrToken::Token(it) => it.text_len(),
+        }
+    }
+}
+
+/// An iterator over the children of a node in an [`ArenaGreenNode`].
+#[derive(Debug, Clone)]
+pub struct ArenaNodeChildren<'a> {
+    arena: &'a ArenaGreenNode,
+    inner: slice::Iter<'a, ArenaChild>,
+}
+
+impl<'a> ArenaNodeChildren<'a> {
+    #[inline]
+    fn resolve(&self, child: &'a ArenaChild) -> ArenaElementRef<'a> {
+        match child {
+            ArenaChild::Node(index) => NodeOrToken::Node(ArenaNodeRef {
+                arena: self.arena,
+                index: *index,
+            }),
+            ArenaChild::Token(data) => NodeOrToken::Token(ArenaTokenRef { data }),
+        }
+    }
+}
+
+impl<'a> Iterator for ArenaNodeChildren<'a> {
+    type Item = ArenaElementRef<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let child = self.inner.next()?;
+        Some(self.resolve(child))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let child = self.inner.nth(n)?;
+        Some(self.resolve(child))
+    }
+}
+
+impl DoubleEndedIterator for ArenaNodeChildren<'_> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let child = self.inner.next_back()?;
+        Some(self.resolve(child))
+    }
+}
+
+impl ExactSizeIterator for ArenaNodeChildren<'_> {
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl FusedIterator for ArenaNodeChildren<'_> {}
+
+/// An iterator over the tokens in the subtree of a node in an [`ArenaGreenNode`], in document order.
+#[derive(Debug, Clone)]
+pub struct ArenaTokens<'a> {
+    /// The remaining children of every node from the starting node to the current one.
+    stack: Vec<ArenaNodeChildren<'a>>,
+}
+
+impl<'a> Iterator for ArenaTokens<'a> {
+    type Item = ArenaTokenRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let children = self.stack.last_mut()?;
+            match children.next() {
+                Some(NodeOrToken::Node(node)) => self.stack.push(node.children()),
+                Some(NodeOrToken::Token(token)) => return Some(token),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+impl FusedIterator for ArenaTokens<'_> {}
+
+/// A builder for arena-backed green trees.
+///
+/// The builder has the same interface as [`GreenNodeBuilder`](crate::build::GreenNodeBuilder), but stores all
+/// nodes of the resulting tree in one [`ArenaGreenNode`].
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// # use cstree::build::ArenaGreenNodeBuilder;
+/// let mut builder: ArenaGreenNodeBuilder<MySyntax> = ArenaGreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.token(Int, "42");
+/// builder.finish_node();
+/// let (tree, interner) = builder.finish();
+///
+/// assert_eq!(tree.kind(), MySyntax::into_raw(Root));
+/// let int = tree.children().next().unwrap();
+/// assert_eq!(int.kind(), MySyntax::into_raw(Int));
+/// assert_eq!(int.as_token().unwrap().text(&interner.unwrap()), Some("42"));
+/// ```
+#[derive(Debug)]
+pub struct ArenaGreenNodeBuilder<'interner, S: Syntax, I = TokenInterner> {
+    interner: MaybeOwned<'interner, I>,
+    parents:  Vec<(S, usize)>,
+    pending:  Vec<(ArenaChild, TextSize)>,
+    nodes:    Vec<ArenaNodeData>,
+    children: Vec<ArenaChild>,
+}
+
+impl<S: Syntax> ArenaGreenNodeBuilder<'static, S> {
+    /// Creates a new builder with a new default interner.
+    pub fn new() -> Self {
+        Self::from_interner(new_interner())
+    }
+}
+
+impl<S: Syntax> Default for ArenaGreenNodeBuilder<'static, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'interner, S, I> ArenaGreenNodeBuilder<'interner, S, I>
+where
+    S: Syntax,
+    I: Interner<TokenKey>,
+{
+    /// Constructs a builder that interns token text into an existing interner.
+    #[inline]
+    pub fn with_interner(interner: &'interner mut I) -> Self {
+        Self::with_maybe_owned(MaybeOwned::Borrowed(interner))
+    }
+
+    /// Constructs a builder that takes ownership of the given interner.
+    /// The interner is returned on [`finish`](ArenaGreenNodeBuilder::finish).
+    #[inline]
+    pub fn from_interner(interner: I) -> Self {
+        Self::with_maybe_owned(MaybeOwned::Owned(interner))
+    }
+
+    fn with_maybe_owned(interner: MaybeOwned<'interner, I>) -> Self {
+        Self {
+            interner,
+            parents: Vec::with_capacity(8),
+            pending: Vec::with_capacity(8),
+            nodes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Get a reference to the interner used to deduplicate source text (strings).
+    #[inline]
+    pub fn interner(&self) -> &I {
+        &self.interner
+    }
+
+    /// Get a mutable reference to the interner used to deduplicate source text (strings).
+    #[inline]
+    pub fn interner_mut(&mut self) -> &mut I {
+        &mut self.interner
+    }
+
+    /// Add a new token with the given `text` to the current node.
+    ///
+    /// ## Panics
+    /// If `text` is longer than [`u32::MAX`] bytes.
+    /// In debug mode, if `kind` has static text, this function will verify that `text` matches that text.
+    #[inline]
+    pub fn token(&mut self, kind: S, text: &str) {
+        let data = match S::static_text(kind) {
+            Some(static_text) => {
+                debug_assert_eq!(
+                    static_text, text,
+                    r#"Received `{kind:?}` token which should have text "{static_text}", but "{text}" was given."#
+                );
+                GreenTokenData {
+                    kind:     S::into_raw(kind),
+                    text:     None,
+                    text_len: TextSize::from(token_text_len(static_text)),
+                }
+            }
+            None => GreenTokenData {
+                kind:     S::into_raw(kind),
+                text:     Some(self.interner.get_or_intern(text)),
+                text_len: TextSize::from(token_text_len(text)),
+            },
+        };
+        self.pending.push((ArenaChild::Token(data), data.text_len));
+    }
+
+    /// Add a new token to the current node without storing an explicit section of text.
+    ///
+    /// ## Panics
+    /// If `kind` does not have static text, i.e., `S::static_text(kind)` returns `None`.
+    #[inline]
+    pub fn static_token(&mut self, kind: S) {
+        let static_text = S::static_text(kind).unwrap_or_else(|| panic!("Missing static text for '{kind:?}'"));
+        let data = GreenTokenData {
+            kind:     S::into_raw(kind),
+            text:     None,
+            text_len: TextSize::from(token_text_len(static_text)),
+        };
+        self.pending.push((ArenaChild::Token(data), data.text_len));
+    }
+
+    /// Start new node of the given `kind` and make it current.
+    #[inline]
+    pub fn start_node(&mut self, kind: S) {
+        let len = self.pending.len();
+        self.parents.push((kind, len));
+    }
+
+    /// Finish the current branch and restore the previous branch as current.
+    ///
+    /// ## Panics
+    /// If the text of the finished node is longer than [`u32::MAX`] bytes.
+    #[inline]
+    pub fn finish_node(&mut self) {
+        let (kind, first_child) = self.parents.pop().unwrap();
+        let children_start = self.children.len() as u32;
+        let mut text_len = TextSize::from(0);
+        for (child, len) in self.pending.drain(first_child..) {
+            text_len = text_len.checked_add(len).unwrap_or_else(|| {
+                let err = BuildError::SourceTooLarge {
+                    len: u64::from(u32::from(text_len)) + u64::from(u32::from(len)),
+                };
+                panic!("failed to finish a node: {err}")
+            });
+            self.children.push(child);
+        }
+        let index = self.nodes.len() as u32;
+        self.nodes.push(ArenaNodeData {
+            kind: S::into_raw(kind),
+            text_len,
+            children_start,
+            children_len: self.children.len() as u32 - children_start,
+        });
+        self.pending.push((ArenaChild::Node(index), text_len));
+    }
+
+    /// Prepare for maybe wrapping the next node with a surrounding node.
+    ///
+    /// See [`GreenNodeBuilder::checkpoint`](crate::build::GreenNodeBuilder::checkpoint).
+    #[inline]
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.pending.len())
+    }
+
+    /// Wrap the previous branch marked by [`checkpoint`](ArenaGreenNodeBuilder::checkpoint) in a new
+    /// branch and make it current.
+    #[inline]
+    pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: S) {
+        let Checkpoint(checkpoint) = checkpoint;
+        assert!(
+            checkpoint <= self.pending.len(),
+            "checkpoint no longer valid, was finish_node called early?"
+        );
+
+        if let Some(&(_, first_child)) = self.parents.last() {
+            assert!(
+                checkpoint >= first_child,
+                "checkpoint no longer valid, was an unmatched start_node_at called?"
+            );
+        }
+
+        self.parents.push((kind, checkpoint));
+    }
+
+    /// Complete building the tree.
+    ///
+    /// Make sure that calls to [`start_node`](ArenaGreenNodeBuilder::start_node) /
+    /// [`start_node_at`](ArenaGreenNodeBuilder::start_node_at) and
+    /// [`finish_node`](ArenaGreenNodeBuilder::finish_node) are balanced, i.e. that every started node has
+    /// been completed!
+    ///
+    /// If this builder was constructed with [`new`](ArenaGreenNodeBuilder::new) or
+    /// [`from_interner`](ArenaGreenNodeBuilder::from_interner), this method returns the interner as its second
+    /// return value.
+    #[inline]
+    pub fn finish(mut self) -> (ArenaGreenNode, Option<I>) {
+        assert_eq!(self.pending.len(), 1);
+        match self.pending.pop().unwrap() {
+            (ArenaChild::Node(_), _) => {}
+            (ArenaChild::Token(_), _) => {
+                panic!("called `finish` on an `ArenaGreenNodeBuilder` which only contained a token")
+            }
+        }
+        // Finished nodes are pushed in post-order, so the root is always the last node.
+        let tree = ArenaGreenNode {
+            nodes:    self.nodes,
+            children: self.children,
+        };
+        (tree, self.interner.into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    #[test]
+    #[should_panic = "too large"]
+    fn node_too_large() {
+        let mut builder: ArenaGreenNodeBuilder<TestSyntaxKind> = ArenaGreenNodeBuilder::new();
+        builder.start_node(Root);
+        builder.token(Int, "1");
+        // pretend that a lot of text was already added, without allocating it
+        let data = GreenTokenData {
+            kind:     TestSyntaxKind::into_raw(Int),
+            text:     None,
+            text_len: u32::MAX.into(),
+        };
+        builder.pending.push((ArenaChild::Token(data), data.text_len));
+        builder.finish_node();
+    }
+}
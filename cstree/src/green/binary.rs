@@ -0,0 +1,342 @@
+//! A compact binary encoding of green trees.
+//!
+//! Trees are encoded in preorder, prefixed with a magic number and a format version:
+//!  - a node is written as a `0` tag byte, followed by its kind and number of children as little-endian `u32`s, a byte
+//!    with the [flags](GreenNode::flags) that were set on the node itself and then by its children
+//!  - a token is written as a `1` tag byte, followed by its kind and text length as little-endian `u32`s, a byte that
+//!    indicates whether the token has non-static text and, if so, the text itself as UTF-8
+//!
+//! Unlike the `serde` implementations of [`SyntaxNode`](crate::syntax::SyntaxNode), this works directly on green
+//! trees and does not include any node data.
+
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    build::NodeCache,
+    interning::{Interner, Resolver, TokenKey},
+    util::NodeOrToken,
+    RawSyntaxKind, Syntax,
+};
+
+use super::{GreenElement, GreenNode, GreenNodeChildren};
+
+pub(super) const MAGIC: &[u8; 4] = b"CSTR";
+pub(super) const VERSION: u8 = 2;
+
+pub(super) const NODE_TAG: u8 = 0;
+pub(super) const TOKEN_TAG: u8 = 1;
+
+/// The maximum depth of trees that are decoded by [`GreenNode::deserialize_from`] and [`GreenNode::from_bytes`],
+/// counting the root as depth 1.
+///
+/// Decoding is not recursive, but many operations on trees (including dropping them) are, so untrusted input that
+/// is nested too deeply could otherwise overflow the stack later on. Use
+/// [`deserialize_with_max_depth`](GreenNode::deserialize_with_max_depth) to decode trees with a different limit.
+pub const DEFAULT_MAX_DEPTH: usize = 2048;
+
+/// An error that occurs when decoding a green tree from its binary encoding.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// Reading the input failed. This includes inputs that end before the tree is complete.
+    Io(io::Error),
+    /// The input does not start with the magic number of the binary format.
+    InvalidMagic,
+    /// The input was encoded with an unknown version of the binary format.
+    UnsupportedVersion(u8),
+    /// The input contains an element with an unknown tag byte.
+    InvalidTag(u8),
+    /// The text of a token is not valid UTF-8.
+    InvalidUtf8,
+    /// The encoded tree is a single token instead of a node.
+    RootIsToken,
+    /// The input continues after the end of the encoded tree.
+    TrailingData,
+    /// The encoded tree is nested more deeply than the depth limit allows.
+    TooDeep,
+    /// A token has no text of its own, but its kind does not have [static text](Syntax::static_text) either.
+    MissingText(RawSyntaxKind),
+    /// An element has a kind that is not valid for the [`Syntax`] type, as reported by [`Syntax::try_from_raw`].
+    InvalidKind(RawSyntaxKind),
+    /// A token with [static text](Syntax::static_text) claims a text length that differs from the length of that
+    /// text.
+    StaticTextLenMismatch(RawSyntaxKind),
+    /// The text of the encoded tree is longer than `u32::MAX` bytes, so its offsets cannot be represented.
+    TextTooLong,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Io(err) => write!(f, "failed to read tree: {err}"),
+            DecodeError::InvalidMagic => write!(f, "input is not an encoded tree"),
+            DecodeError::UnsupportedVersion(version) => write!(f, "unsupported format version {version}"),
+            DecodeError::InvalidTag(tag) => write!(f, "invalid element tag {tag}"),
+            DecodeError::InvalidUtf8 => write!(f, "token text is not valid UTF-8"),
+            DecodeError::RootIsToken => write!(f, "the root of the tree is a token"),
+            DecodeError::TrailingData => write!(f, "unexpected data after the end of the tree"),
+            DecodeError::TooDeep => write!(f, "the tree is nested too deeply"),
+            DecodeError::MissingText(kind) => write!(f, "token of kind {kind:?} has no text"),
+            DecodeError::InvalidKind(kind) => write!(f, "invalid syntax kind {kind:?}"),
+            DecodeError::StaticTextLenMismatch(kind) => {
+                write!(f, "token of kind {kind:?} has a different length than its static text")
+            }
+            DecodeError::TextTooLong => write!(f, "the text of the tree is longer than `u32::MAX` bytes"),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(err: io::Error) -> Self {
+        DecodeError::Io(err)
+    }
+}
+
+impl GreenNode {
+    /// Writes the binary encoding of this tree into `writer`, without buffering it in memory first.
+    ///
+    /// The written bytes are identical to the ones returned by [`to_bytes`](GreenNode::to_bytes). The `resolver`
+    /// must be the one the tree was built with and is used to resolve the text of the tree's tokens. Since the tree
+    /// is written in many small pieces, consider wrapping unbuffered writers in an [`io::BufWriter`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::build::NodeCache;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "42");
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    ///
+    /// let mut bytes = Vec::new();
+    /// tree.serialize_to(&mut bytes, &interner).unwrap();
+    ///
+    /// let mut cache = NodeCache::new();
+    /// let decoded =
+    ///     GreenNode::deserialize_from::<MySyntax, _, _>(&mut bytes.as_slice(), &mut cache).unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(decoded);
+    /// assert_eq!(root.resolve_text(cache.interner()), "42");
+    /// ```
+    pub fn serialize_to<W, R>(&self, writer: &mut W, resolver: &R) -> io::Result<()>
+    where
+        W: Write + ?Sized,
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        write_node_header(writer, self)?;
+
+        let mut stack: Vec<GreenNodeChildren<'_>> = vec![self.children()];
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                None => {
+                    stack.pop();
+                }
+                Some(NodeOrToken::Node(node)) => {
+                    write_node_header(writer, node)?;
+                    stack.push(node.children());
+                }
+                Some(NodeOrToken::Token(token)) => {
+                    writer.write_all(&[TOKEN_TAG])?;
+                    writer.write_all(&token.kind().0.to_le_bytes())?;
+                    writer.write_all(&u32::from(token.text_len()).to_le_bytes())?;
+                    match token.text(resolver) {
+                        Some(text) => {
+                            writer.write_all(&[1])?;
+                            writer.write_all(text.as_bytes())?;
+                        }
+                        None => writer.write_all(&[0])?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the binary encoding of this tree.
+    ///
+    /// See [`serialize_to`](GreenNode::serialize_to) to write the encoding to an [`io::Write`] instead.
+    pub fn to_bytes<R>(&self, resolver: &R) -> Vec<u8>
+    where
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 1 + u32::from(self.text_len()) as usize);
+        // NOTE: writing to a `Vec` never fails
+        self.serialize_to(&mut bytes, resolver).unwrap();
+        bytes
+    }
+
+    /// Reads a tree in the binary format written by [`serialize_to`](GreenNode::serialize_to) from `reader`.
+    ///
+    /// The text of the decoded tokens is interned with the interner of `cache`, and nodes and tokens are deduplicated
+    /// like when building the tree with a [`GreenNodeBuilder`](crate::build::GreenNodeBuilder) using this cache.
+    /// Reading stops at the end of the encoded tree, so further data in `reader` is left untouched. All kinds must be
+    /// valid kinds of `S` according to [`Syntax::try_from_raw`], and tokens without text of their own must be of a
+    /// kind that has [static text](Syntax::static_text) of the encoded length.
+    ///
+    /// Trees that are nested more deeply than [`DEFAULT_MAX_DEPTH`] are rejected.
+    ///
+    /// # Errors
+    /// Returns a [`DecodeError`] if reading from `reader` fails or the input is not a valid encoding of a tree.
+    #[inline]
+    pub fn deserialize_from<S, Rd, I>(reader: &mut Rd, cache: &mut NodeCache<'_, I>) -> Result<GreenNode, DecodeError>
+    where
+        S: Syntax,
+        Rd: Read + ?Sized,
+        I: Interner<TokenKey>,
+    {
+        Self::deserialize_with_max_depth::<S, Rd, I>(reader, cache, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Reads a tree like [`deserialize_from`](GreenNode::deserialize_from), but rejects trees that are nested more
+    /// deeply than `max_depth` instead of [`DEFAULT_MAX_DEPTH`]. The root of the tree is at depth 1.
+    ///
+    /// # Errors
+    /// Returns a [`DecodeError`] if reading from `reader` fails or the input is not a valid encoding of a tree, and
+    /// [`DecodeError::TooDeep`] if the tree is nested too deeply.
+    pub fn deserialize_with_max_depth<S, Rd, I>(
+        reader: &mut Rd,
+        cache: &mut NodeCache<'_, I>,
+        max_depth: usize,
+    ) -> Result<GreenNode, DecodeError>
+    where
+        S: Syntax,
+        Rd: Read + ?Sized,
+        I: Interner<TokenKey>,
+    {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(DecodeError::InvalidMagic);
+        }
+        let version = read_u8(reader)?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+
+        // The children of all currently open nodes, and for each open node its kind, its own flags, the index of its
+        // first child and the number of its children that remain to be read.
+        let mut children: Vec<GreenElement> = Vec::new();
+        let mut parents: Vec<(RawSyntaxKind, u8, usize, u32)> = Vec::new();
+        let mut text = Vec::new();
+        loop {
+            match read_u8(reader)? {
+                NODE_TAG => {
+                    let kind = RawSyntaxKind(read_u32(reader)?);
+                    if S::try_from_raw(kind).is_none() {
+                        return Err(DecodeError::InvalidKind(kind));
+                    }
+                    let n_children = read_u32(reader)?;
+                    let flags = read_u8(reader)?;
+                    if parents.len() == max_depth {
+                        return Err(DecodeError::TooDeep);
+                    }
+                    parents.push((kind, flags, children.len(), n_children));
+                }
+                TOKEN_TAG => {
+                    if parents.is_empty() {
+                        return Err(DecodeError::RootIsToken);
+                    }
+                    let kind = RawSyntaxKind(read_u32(reader)?);
+                    let Some(syntax_kind) = S::try_from_raw(kind) else {
+                        return Err(DecodeError::InvalidKind(kind));
+                    };
+                    let len = read_u32(reader)?;
+                    let key = match read_u8(reader)? {
+                        0 => match S::static_text(syntax_kind) {
+                            Some(static_text) if static_text.len() == len as usize => None,
+                            Some(_) => return Err(DecodeError::StaticTextLenMismatch(kind)),
+                            None => return Err(DecodeError::MissingText(kind)),
+                        },
+                        _ => {
+                            // don't trust `len` to allocate the buffer, the input may be much shorter
+                            text.clear();
+                            Read::take(&mut *reader, u64::from(len)).read_to_end(&mut text)?;
+                            if text.len() != len as usize {
+                                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                            }
+                            let text = std::str::from_utf8(&text).map_err(|_| DecodeError::InvalidUtf8)?;
+                            Some(cache.interner_mut().get_or_intern(text))
+                        }
+                    };
+                    children.push(cache.get_cached_token(kind, key, len).into());
+                    parents.last_mut().unwrap().3 -= 1;
+                }
+                tag => return Err(DecodeError::InvalidTag(tag)),
+            }
+
+            // finish all nodes whose children are complete
+            while let Some(&(kind, flags, first_child, 0)) = parents.last() {
+                parents.pop();
+                let text_len: u64 = children[first_child..]
+                    .iter()
+                    .map(|child| u64::from(u32::from(child.text_len())))
+                    .sum();
+                if text_len > u64::from(u32::MAX) {
+                    return Err(DecodeError::TextTooLong);
+                }
+                let node = cache.build_node_with_flags(kind, flags, &mut children, first_child);
+                match parents.last_mut() {
+                    Some(parent) => {
+                        children.push(node.into());
+                        parent.3 -= 1;
+                    }
+                    None => return Ok(node),
+                }
+            }
+        }
+    }
+
+    /// Decodes a tree from its binary encoding, as returned by [`to_bytes`](GreenNode::to_bytes).
+    ///
+    /// See [`deserialize_from`](GreenNode::deserialize_from) for details.
+    ///
+    /// # Errors
+    /// Returns a [`DecodeError`] if `bytes` are not a valid encoding of a tree, including if there are any bytes left
+    /// after the end of the tree.
+    pub fn from_bytes<S, I>(bytes: &[u8], cache: &mut NodeCache<'_, I>) -> Result<GreenNode, DecodeError>
+    where
+        S: Syntax,
+        I: Interner<TokenKey>,
+    {
+        let mut reader = bytes;
+        let node = Self::deserialize_from::<S, _, I>(&mut reader, cache)?;
+        if !reader.is_empty() {
+            return Err(DecodeError::TrailingData);
+        }
+        Ok(node)
+    }
+}
+
+fn write_node_header<W: Write + ?Sized>(writer: &mut W, node: &GreenNode) -> io::Result<()> {
+    writer.write_all(&[NODE_TAG])?;
+    writer.write_all(&node.kind().0.to_le_bytes())?;
+    writer.write_all(&(node.children().len() as u32).to_le_bytes())?;
+    writer.write_all(&[node.own_flags()])
+}
+
+pub(super) fn read_u8<R: Read + ?Sized>(reader: &mut R) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+pub(super) fn read_u32<R: Read + ?Sized>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
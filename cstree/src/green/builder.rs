@@ -1,11 +1,16 @@
-use std::hash::{Hash, Hasher};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    hash::{Hash, Hasher},
+};
 
 use fxhash::{FxHashMap, FxHasher32};
 use text_size::TextSize;
 
 use crate::{
     green::{GreenElement, GreenNode, GreenToken},
-    interning::{new_interner, Interner, TokenInterner, TokenKey},
+    interning::{new_interner, Interner, Resolver, TokenInterner, TokenKey},
     util::NodeOrToken,
     utility_types::MaybeOwned,
     RawSyntaxKind, Syntax,
@@ -21,11 +26,20 @@ const CHILDREN_CACHE_THRESHOLD: usize = 3;
 
 /// A `NodeCache` deduplicates identical tokens and small nodes during tree construction.
 /// You can re-use the same cache for multiple similar trees with [`GreenNodeBuilder::with_cache`].
+///
+/// Tokens are identical if they have the same kind and text. All occurrences of such tokens share a single
+/// [`GreenToken`] allocation, regardless of how many nodes they appear in (see [`GreenToken::ptr_eq`]).
+///
+/// The text of every token is hashed only once, by the interner when it is interned. Tokens are then deduplicated
+/// by their kind and the resulting [`TokenKey`], and nodes are deduplicated by their kind and their already
+/// deduplicated children, so neither lookup hashes any text again.
 #[derive(Debug)]
 pub struct NodeCache<'i, I = TokenInterner> {
-    nodes:    FxHashMap<GreenNodeHead, GreenNode>,
-    tokens:   FxHashMap<GreenTokenData, GreenToken>,
+    nodes: FxHashMap<GreenNodeHead, GreenNode>,
+    tokens: FxHashMap<GreenTokenData, GreenToken>,
     interner: MaybeOwned<'i, I>,
+    /// Nodes with fewer children than this are not deduplicated.
+    min_dedup_children: usize,
 }
 
 impl NodeCache<'static> {
@@ -54,9 +68,10 @@ impl NodeCache<'static> {
     /// ```
     pub fn new() -> Self {
         Self {
-            nodes:    FxHashMap::default(),
-            tokens:   FxHashMap::default(),
+            nodes: FxHashMap::default(),
+            tokens: FxHashMap::default(),
             interner: MaybeOwned::Owned(new_interner()),
+            min_dedup_children: 0,
         }
     }
 }
@@ -101,9 +116,10 @@ where
     #[inline]
     pub fn with_interner(interner: &'i mut I) -> Self {
         Self {
-            nodes:    FxHashMap::default(),
-            tokens:   FxHashMap::default(),
+            nodes: FxHashMap::default(),
+            tokens: FxHashMap::default(),
             interner: MaybeOwned::Borrowed(interner),
+            min_dedup_children: 0,
         }
     }
 
@@ -138,12 +154,39 @@ where
     #[inline]
     pub fn from_interner(interner: I) -> Self {
         Self {
-            nodes:    FxHashMap::default(),
-            tokens:   FxHashMap::default(),
+            nodes: FxHashMap::default(),
+            tokens: FxHashMap::default(),
             interner: MaybeOwned::Owned(interner),
+            min_dedup_children: 0,
         }
     }
 
+    /// Only deduplicate nodes with at least `min_children` children, including tokens.
+    ///
+    /// By default, the cache deduplicates all small nodes, including nodes with a single child such as a wrapper
+    /// around a punctuation token. For trees with many such nodes that are not actually repeated, looking them up
+    /// in the cache can cost more than the sharing saves. Nodes with fewer than `min_children` children are created
+    /// without consulting the cache. Tokens are always deduplicated, and trees built with any threshold have the
+    /// same structure and text.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::build::NodeCache;
+    ///
+    /// let mut cache = NodeCache::new().with_dedup_threshold(2);
+    /// let int = cache.token(Int, "1");
+    /// let first = cache.node(Operation, vec![int.clone().into()]);
+    /// let second = cache.node(Operation, vec![int.into()]);
+    /// assert_eq!(first, second);
+    /// assert!(!first.ptr_eq(&second));
+    /// ```
+    #[inline]
+    pub fn with_dedup_threshold(mut self, min_children: usize) -> Self {
+        self.min_dedup_children = min_children;
+        self
+    }
+
     /// Get a reference to the interner used to deduplicate source text (strings).
     ///
     /// See also [`interner_mut`](NodeCache::interner_mut).
@@ -153,6 +196,12 @@ where
     }
 
     /// Get a mutable reference to the interner used to deduplicate source text (strings).
+    ///
+    /// This can be used to intern additional strings that are not the text of any token, such as for node data or
+    /// diagnostics, so that they can be resolved with the same interner as the tree. If the cache was constructed
+    /// with [`with_interner`](NodeCache::with_interner), this is the interner borrowed by the cache, which remains
+    /// accessible through the original reference once the cache is dropped.
+    ///
     /// # Examples
     /// ```
     /// # use cstree::*;
@@ -177,13 +226,218 @@ where
         self.interner.into_owned()
     }
 
-    fn node<S: Syntax>(&mut self, kind: S, all_children: &mut Vec<GreenElement>, offset: usize) -> GreenNode {
+    /// Creates a node of the given `kind` from `children`, reusing a cached node if possible.
+    ///
+    /// Together with [`token`](NodeCache::token), this allows assembling green trees manually, outside of a
+    /// [`GreenNodeBuilder`]. Nodes created this way are deduplicated in the same way as if they had been built
+    /// with a builder using this cache, so they share structure with other trees built from the same cache.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::build::NodeCache;
+    ///
+    /// let mut cache = NodeCache::new();
+    /// let int = cache.token(Int, "1");
+    /// let first = cache.node(Operation, vec![int.clone().into()]);
+    /// let second = cache.node(Operation, vec![int.into()]);
+    /// assert!(first.ptr_eq(&second));
+    /// ```
+    #[inline]
+    pub fn node<S: Syntax>(&mut self, kind: S, mut children: Vec<GreenElement>) -> GreenNode {
+        self.build_node(S::into_raw(kind), &mut children, 0)
+    }
+
+    /// Creates a token of the given `kind` with the given `text`, interning the text and reusing a cached token if
+    /// possible.
+    ///
+    /// See [`node`](NodeCache::node) to create nodes from tokens.
+    ///
+    /// ## Panics
+    /// If `text` is longer than [`u32::MAX`] bytes.
+    /// In debug mode, if `kind` has static text, this function will verify that `text` matches that text.
+    #[inline]
+    pub fn token<S: Syntax>(&mut self, kind: S, text: &str) -> GreenToken {
+        match S::static_text(kind) {
+            Some(static_text) => {
+                debug_assert_eq!(
+                    static_text, text,
+                    r#"Received `{kind:?}` token which should have text "{static_text}", but "{text}" was given."#
+                );
+                self.get_cached_token(S::into_raw(kind), None, token_text_len(static_text))
+            }
+            None => {
+                let len = token_text_len(text);
+                let text = self.intern(text);
+                self.get_cached_token(S::into_raw(kind), Some(text), len)
+            }
+        }
+    }
+
+    /// Reports groups of distinct nodes in this cache that look alike, but were not deduplicated because they differ
+    /// somewhere in their subtrees. This is a diagnostic to help find out why trees share less structure than
+    /// expected, such as because of tokens that have the same text but a different kind.
+    ///
+    /// Nodes are grouped by their [`StructuralKey`]. For every group that contains more than one distinct node, the
+    /// key is returned together with the number of distinct nodes in the group. The groups are sorted by this number,
+    /// starting with the largest.
+    ///
+    /// Note that only small nodes are deduplicated, so large nodes will not be reported even if they are identical.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::build::{NodeCache, StructuralKey};
+    ///
+    /// let mut cache = NodeCache::new();
+    /// let int = cache.token(Int, "1");
+    /// let float = cache.token(Float, "1");
+    /// cache.node(Operation, vec![int.into()]);
+    /// cache.node(Operation, vec![float.into()]);
+    ///
+    /// let candidates = cache.duplicate_candidates();
+    /// let key = StructuralKey {
+    ///     kind:     MySyntax::into_raw(Operation),
+    ///     text_len: 1.into(),
+    ///     children: 1,
+    /// };
+    /// assert_eq!(candidates, [(key, 2)]);
+    /// ```
+    pub fn duplicate_candidates(&self) -> Vec<(StructuralKey, usize)> {
+        let mut groups: FxHashMap<StructuralKey, usize> = FxHashMap::default();
+        for node in self.nodes.values() {
+            let key = StructuralKey {
+                kind:     node.kind(),
+                text_len: node.text_len(),
+                children: node.children().len(),
+            };
+            *groups.entry(key).or_default() += 1;
+        }
+        let mut candidates: Vec<_> = groups.into_iter().filter(|&(_, count)| count > 1).collect();
+        candidates.sort_unstable_by(|(key1, count1), (key2, count2)| count2.cmp(count1).then(key1.cmp(key2)));
+        candidates
+    }
+
+    /// Rebuilds the trees with the given `roots`, whose text is resolved with `resolver`, using this cache and its
+    /// interner. Returns the new roots, in the same order, together with a map from every key of the original trees
+    /// to the key of the same text in this cache's interner.
+    ///
+    /// Interners never forget strings, so an interner that is shared between many trees over a long time keeps the
+    /// text of all trees that were ever built with it. To reclaim the memory of strings that are no longer used,
+    /// rebuild the trees that are still alive with a new, empty cache and drop the old interner together with the
+    /// old trees. The new interner then only contains the text of the rebuilt trees.
+    ///
+    /// The rebuilt trees are equal in structure and text to the original ones, and are deduplicated by this cache
+    /// like trees built with a [`GreenNodeBuilder`]. Since their keys differ, the rebuilt trees must only be resolved
+    /// with the new interner.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::build::NodeCache;
+    ///
+    /// let mut cache = NodeCache::new();
+    /// let closed = cache.token(Identifier, "closed");
+    /// let closed = cache.node(Root, vec![closed.into()]);
+    /// let open = cache.token(Identifier, "open");
+    /// let open = cache.node(Root, vec![open.into()]);
+    /// drop(closed);
+    ///
+    /// let mut compacted = NodeCache::new();
+    /// let (roots, keys) = compacted.reintern(&[open], cache.interner());
+    /// drop(cache);
+    /// assert_eq!(keys.len(), 1);
+    /// let open: SyntaxNode<MySyntax> = SyntaxNode::new_root(roots[0].clone());
+    /// assert_eq!(open.resolve_text(compacted.interner()), "open");
+    /// ```
+    pub fn reintern<R>(&mut self, roots: &[GreenNode], resolver: &R) -> (Vec<GreenNode>, HashMap<TokenKey, TokenKey>)
+    where
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        let mut keys = HashMap::new();
+        let roots = roots
+            .iter()
+            .map(|root| self.reintern_node(root, resolver, &mut keys))
+            .collect();
+        (roots, keys)
+    }
+
+    fn reintern_node<R>(&mut self, node: &GreenNode, resolver: &R, keys: &mut HashMap<TokenKey, TokenKey>) -> GreenNode
+    where
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        let mut children: Vec<GreenElement> = node
+            .children()
+            .map(|child| match child {
+                NodeOrToken::Node(node) => self.reintern_node(node, resolver, keys).into(),
+                NodeOrToken::Token(token) => {
+                    let text = token.text_key().map(|key| {
+                        *keys
+                            .entry(key)
+                            .or_insert_with(|| self.interner.get_or_intern(resolver.resolve(key)))
+                    });
+                    self.get_cached_token(token.kind(), text, token.text_len().into())
+                        .into()
+                }
+            })
+            .collect();
+        self.build_node_with_flags(node.kind(), node.own_flags(), &mut children, 0)
+    }
+
+    pub(super) fn map_node_kinds<F>(&mut self, node: &GreenNode, f: &mut F) -> GreenNode
+    where
+        F: FnMut(RawSyntaxKind) -> RawSyntaxKind,
+    {
+        let mut children: Vec<GreenElement> = node
+            .children()
+            .map(|child| match child {
+                NodeOrToken::Node(node) => self.map_node_kinds(node, f).into(),
+                NodeOrToken::Token(token) => self
+                    .get_cached_token(f(token.kind()), token.text_key(), token.text_len().into())
+                    .into(),
+            })
+            .collect();
+        self.build_node_with_flags(f(node.kind()), node.own_flags(), &mut children, 0)
+    }
+
+    #[inline]
+    pub(super) fn build_node(
+        &mut self,
+        kind: RawSyntaxKind,
+        all_children: &mut Vec<GreenElement>,
+        offset: usize,
+    ) -> GreenNode {
+        self.build_node_with_flags(kind, 0, all_children, offset)
+    }
+
+    /// Creates a node like [`node`](NodeCache::node), with `own_flags` set on the node in addition to the flags of
+    /// its children.
+    #[inline]
+    pub(crate) fn node_with_flags(
+        &mut self,
+        kind: RawSyntaxKind,
+        own_flags: u8,
+        mut children: Vec<GreenElement>,
+    ) -> GreenNode {
+        self.build_node_with_flags(kind, own_flags, &mut children, 0)
+    }
+
+    /// Builds a node like [`build_node`](NodeCache::build_node), with `own_flags` set in addition to the flags of its
+    /// children.
+    pub(super) fn build_node_with_flags(
+        &mut self,
+        kind: RawSyntaxKind,
+        own_flags: u8,
+        all_children: &mut Vec<GreenElement>,
+        offset: usize,
+    ) -> GreenNode {
         // NOTE: this fn must remove all children starting at `first_child` from `all_children` before returning
-        let kind = S::into_raw(kind);
         let mut hasher = FxHasher32::default();
         let mut text_len: TextSize = 0.into();
+        let mut flags = own_flags;
         for child in &all_children[offset..] {
             text_len += child.text_len();
+            flags |= child.flags();
             child.hash(&mut hasher);
         }
         let child_hash = hasher.finish() as u32;
@@ -196,10 +450,10 @@ where
         // For `libsyntax/parse/parser.rs`, measurements show that deduping saves
         // 17% of the memory for green nodes!
         let children = all_children.drain(offset..);
-        if children.len() <= CHILDREN_CACHE_THRESHOLD {
-            self.get_cached_node(kind, children, text_len, child_hash)
+        if (self.min_dedup_children..=CHILDREN_CACHE_THRESHOLD).contains(&children.len()) {
+            self.get_cached_node(kind, children, text_len, child_hash, flags, own_flags)
         } else {
-            GreenNode::new_with_len_and_hash(kind, children, text_len, child_hash)
+            GreenNode::new_with_len_and_hash(kind, children, text_len, child_hash, flags, own_flags)
         }
     }
 
@@ -217,11 +471,15 @@ where
         children: std::vec::Drain<'_, GreenElement>,
         text_len: TextSize,
         child_hash: u32,
+        flags: u8,
+        own_flags: u8,
     ) -> GreenNode {
         let head = GreenNodeHead {
             kind,
             text_len,
             child_hash,
+            flags,
+            own_flags,
         };
         self.nodes
             .entry(head)
@@ -229,9 +487,8 @@ where
             .clone()
     }
 
-    fn token<S: Syntax>(&mut self, kind: S, text: Option<TokenKey>, len: u32) -> GreenToken {
+    pub(super) fn get_cached_token(&mut self, kind: RawSyntaxKind, text: Option<TokenKey>, len: u32) -> GreenToken {
         let text_len = TextSize::from(len);
-        let kind = S::into_raw(kind);
         let data = GreenTokenData { kind, text, text_len };
         self.tokens
             .entry(data)
@@ -240,9 +497,63 @@ where
     }
 }
 
+/// The properties by which [`NodeCache::duplicate_candidates`] groups similar nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StructuralKey {
+    /// The kind of the nodes.
+    pub kind:     RawSyntaxKind,
+    /// The length of the text covered by the nodes.
+    pub text_len: TextSize,
+    /// The number of children of the nodes, including tokens.
+    pub children: usize,
+}
+
+/// An error that occurs when finishing a tree with a [`GreenNodeBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// The total length of the source text exceeds [`u32::MAX`] bytes, so offsets into it cannot be represented as
+    /// [`TextSize`]s.
+    SourceTooLarge {
+        /// The total length of the source text given to the builder, in bytes.
+        len: u64,
+    },
+    /// A tree described by nesting depths, as given to [`GreenNode::from_nested`], is not properly nested.
+    InvalidNesting {
+        /// The index of the first item with an invalid depth, or the number of items if there are no items.
+        index: usize,
+    },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::SourceTooLarge { len } => write!(
+                f,
+                "source text of {len} bytes is too large, at most {} bytes are supported",
+                u32::MAX
+            ),
+            BuildError::InvalidNesting { index } => write!(f, "item {index} of the nested tree has an invalid depth"),
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+/// The length of the text of a token, which must fit into a [`TextSize`].
+///
+/// ## Panics
+/// If `text` is longer than [`u32::MAX`] bytes.
+#[inline]
+pub(super) fn token_text_len(text: &str) -> u32 {
+    u32::try_from(text.len()).unwrap_or_else(|_| {
+        let err = BuildError::SourceTooLarge { len: text.len() as u64 };
+        panic!("failed to create a token: {err}")
+    })
+}
+
 /// A checkpoint for maybe wrapping a node. See [`GreenNodeBuilder::checkpoint`] for details.
 #[derive(Clone, Copy, Debug)]
-pub struct Checkpoint(usize);
+pub struct Checkpoint(pub(super) usize);
 
 /// A builder for green trees.
 /// Construct with [`new`](GreenNodeBuilder::new), [`with_cache`](GreenNodeBuilder::with_cache), or
@@ -270,18 +581,71 @@ pub struct Checkpoint(usize);
 /// ```
 #[derive(Debug)]
 pub struct GreenNodeBuilder<'cache, 'interner, S: Syntax, I = TokenInterner> {
-    cache:    MaybeOwned<'cache, NodeCache<'interner, I>>,
-    parents:  Vec<(S, usize)>,
-    children: Vec<GreenElement>,
+    cache:     MaybeOwned<'cache, NodeCache<'interner, I>>,
+    /// The kind, first child and flags of every node that has been started, but not yet finished.
+    parents:   Vec<(S, usize, u8)>,
+    /// The depth and declared text length of every unfinished node that was started with a length hint.
+    len_hints: Vec<(usize, TextSize)>,
+    children:  Vec<GreenElement>,
+    /// The summed length of the text of all tokens added so far, which may exceed what a `TextSize` can represent.
+    text_len:  u64,
+    stats:     BuildStats,
+}
+
+/// Statistics about a tree built with a [`GreenNodeBuilder`], as returned by
+/// [`finish_with_stats`](GreenNodeBuilder::finish_with_stats).
+///
+/// The counts include every node and token that was added to the tree, including ones that were deduplicated with
+/// identical nodes or tokens built before.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BuildStats {
+    /// The number of nodes in the tree, including the root.
+    pub node_count:      usize,
+    /// The number of tokens in the tree.
+    pub token_count:     usize,
+    /// The number of tokens in the tree whose text is interned, i.e. which do not have
+    /// [static text](crate::Syntax::static_text). Tokens with the same text share a single interned string, so this
+    /// is an upper bound on the number of strings the tree added to the interner.
+    pub interned_tokens: usize,
+}
+
+impl BuildStats {
+    #[inline(always)]
+    fn count_token(&mut self, token: &GreenToken) {
+        self.token_count += 1;
+        if token.text_key().is_some() {
+            self.interned_tokens += 1;
+        }
+    }
+
+    /// Counts all nodes and tokens in the subtree of `node`, excluding `node` itself.
+    fn count_descendants(&mut self, node: &GreenNode) {
+        let mut stack = vec![node.children()];
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                None => {
+                    stack.pop();
+                }
+                Some(NodeOrToken::Node(node)) => {
+                    self.node_count += 1;
+                    stack.push(node.children());
+                }
+                Some(NodeOrToken::Token(token)) => self.count_token(token),
+            }
+        }
+    }
 }
 
 impl<S: Syntax> GreenNodeBuilder<'static, 'static, S> {
     /// Creates new builder with an empty [`NodeCache`].
     pub fn new() -> Self {
         Self {
-            cache:    MaybeOwned::Owned(NodeCache::new()),
-            parents:  Vec::with_capacity(8),
-            children: Vec::with_capacity(8),
+            cache:     MaybeOwned::Owned(NodeCache::new()),
+            parents:   Vec::with_capacity(8),
+            len_hints: Vec::new(),
+            children:  Vec::with_capacity(8),
+            text_len:  0,
+            stats:     BuildStats::default(),
         }
     }
 }
@@ -301,9 +665,12 @@ where
     /// share underlying trees.
     pub fn with_cache(cache: &'cache mut NodeCache<'interner, I>) -> Self {
         Self {
-            cache:    MaybeOwned::Borrowed(cache),
-            parents:  Vec::with_capacity(8),
-            children: Vec::with_capacity(8),
+            cache:     MaybeOwned::Borrowed(cache),
+            parents:   Vec::with_capacity(8),
+            len_hints: Vec::new(),
+            children:  Vec::with_capacity(8),
+            text_len:  0,
+            stats:     BuildStats::default(),
         }
     }
 
@@ -334,12 +701,52 @@ where
     /// ```
     pub fn from_cache(cache: NodeCache<'interner, I>) -> Self {
         Self {
-            cache:    MaybeOwned::Owned(cache),
-            parents:  Vec::with_capacity(8),
-            children: Vec::with_capacity(8),
+            cache:     MaybeOwned::Owned(cache),
+            parents:   Vec::with_capacity(8),
+            len_hints: Vec::new(),
+            children:  Vec::with_capacity(8),
+            text_len:  0,
+            stats:     BuildStats::default(),
         }
     }
 
+    /// Creates a builder that continues building the tree rooted at `node`, for appending children to a finished
+    /// tree.
+    ///
+    /// The root of `node` is started as the current node, with its children already added, so further elements are
+    /// appended after them. Finishing the root without adding anything results in a tree that is equal to `node`.
+    /// `cache` should use the same interner that `node` was built with, so that the text of existing and added
+    /// tokens can be resolved together. The elements of `node` are included in the
+    /// [`stats`](GreenNodeBuilder::stats) of the builder, so that they describe the finished tree.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::build::*;
+    /// let mut cache = NodeCache::new();
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::with_cache(&mut cache);
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.finish_node();
+    /// let (tree, _) = builder.finish();
+    ///
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::from_green(tree, &mut cache);
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// let (tree, _) = builder.finish();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    /// assert_eq!(root.resolve_text(cache.interner()), "1+2");
+    /// ```
+    pub fn from_green(node: GreenNode, cache: &'cache mut NodeCache<'interner, I>) -> Self {
+        let mut builder = Self::with_cache(cache);
+        builder.children.extend(node.children().map(|child| child.cloned()));
+        builder.parents.push((S::from_raw(node.kind()), 0, node.own_flags()));
+        builder.text_len = u64::from(u32::from(node.text_len()));
+        builder.stats.count_descendants(&node);
+        builder
+    }
+
     /// Shortcut to construct a builder that uses an existing interner.
     ///
     /// This is equivalent to using [`from_cache`](GreenNodeBuilder::from_cache) with a node cache
@@ -393,21 +800,64 @@ where
     /// In debug mode, if `kind` has static text, this function will verify that `text` matches that text.
     #[inline]
     pub fn token(&mut self, kind: S, text: &str) {
-        let token = match S::static_text(kind) {
-            Some(static_text) => {
-                debug_assert_eq!(
+        if !self.reserve_text(text.len()) {
+            return;
+        }
+        let token = self.cache.token(kind, text);
+        self.push_token(token);
+    }
+
+    /// Add a new token to the current node, the text of which has already been interned with the builder's
+    /// interner as `key`, and is `len` bytes long.
+    ///
+    /// This avoids interning the text again, for example for keywords that were interned up front, but still
+    /// deduplicates the token itself.
+    ///
+    /// ## Panics
+    /// In debug mode, if `key` cannot be resolved by the builder's interner or the text it resolves to is not `len`
+    /// bytes long. Also in debug mode, if `kind` has static text, this function will verify that the text of `key`
+    /// matches that text.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::interning::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// let key = builder.interner_mut().get_or_intern("let");
+    /// builder.start_node(Root);
+    /// builder.token_interned(Identifier, key, 3.into());
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    /// assert_eq!(root.resolve_text(&interner), "let");
+    /// ```
+    #[inline]
+    pub fn token_interned(&mut self, kind: S, key: TokenKey, len: TextSize) {
+        if cfg!(debug_assertions) {
+            let text = self.interner().try_resolve(key).unwrap_or_else(|| {
+                panic!("Key {key:?} for `{kind:?}` token does not belong to the builder's interner")
+            });
+            assert_eq!(
+                TextSize::of(text),
+                len,
+                r#"Received `{kind:?}` token with text "{text}", but a length of {len:?} was given."#
+            );
+            if let Some(static_text) = S::static_text(kind) {
+                assert_eq!(
                     static_text, text,
                     r#"Received `{kind:?}` token which should have text "{static_text}", but "{text}" was given."#
                 );
-                self.cache.token::<S>(kind, None, static_text.len() as u32)
-            }
-            None => {
-                let len = text.len() as u32;
-                let text = self.cache.intern(text);
-                self.cache.token::<S>(kind, Some(text), len)
             }
+        }
+        if !self.reserve_text(u32::from(len) as usize) {
+            return;
+        }
+        let token = match S::static_text(kind) {
+            Some(_) => self.cache.get_cached_token(S::into_raw(kind), None, len.into()),
+            None => self.cache.get_cached_token(S::into_raw(kind), Some(key), len.into()),
         };
-        self.children.push(token.into());
+        self.push_token(token);
     }
 
     /// Add a new token to the current node without storing an explicit section of text.
@@ -422,26 +872,163 @@ where
     #[inline]
     pub fn static_token(&mut self, kind: S) {
         let static_text = S::static_text(kind).unwrap_or_else(|| panic!("Missing static text for '{kind:?}'"));
-        let token = self.cache.token::<S>(kind, None, static_text.len() as u32);
+        if !self.reserve_text(static_text.len()) {
+            return;
+        }
+        let token = self
+            .cache
+            .get_cached_token(S::into_raw(kind), None, token_text_len(static_text));
+        self.push_token(token);
+    }
+
+    #[inline(always)]
+    fn push_token(&mut self, token: GreenToken) {
+        self.stats.count_token(&token);
         self.children.push(token.into());
     }
 
+    /// Accounts for `len` more bytes of source text and returns whether the total still fits into a `TextSize`.
+    /// Once the source text is too large, no more tokens are added and finishing the tree will fail.
+    #[inline(always)]
+    fn reserve_text(&mut self, len: usize) -> bool {
+        self.text_len = self.text_len.saturating_add(len as u64);
+        self.text_len <= u64::from(u32::MAX)
+    }
+
     /// Start new node of the given `kind` and make it current.
     #[inline]
     pub fn start_node(&mut self, kind: S) {
         let len = self.children.len();
-        self.parents.push((kind, len));
+        self.parents.push((kind, len, 0));
+    }
+
+    /// Start new node of the given `kind` and make it current, declaring ahead of time that the text of the node will
+    /// have a length of `len`.
+    ///
+    /// This is meant for parsers that already know the extent of a node before they have built its children, for
+    /// example from an earlier pass over the input. Apart from the hint, this is the same as
+    /// [`start_node`](GreenNodeBuilder::start_node). The length of a node is always computed from its children when
+    /// it is finished, so the hint does not change the resulting tree.
+    ///
+    /// ## Panics
+    /// In debug mode, [`finish_node`](GreenNodeBuilder::finish_node) will verify that the text of the node actually
+    /// has length `len`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.start_node_with_len_hint(Operation, 3.into());
+    /// builder.token(Int, "1");
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// let (tree, _) = builder.finish();
+    /// assert_eq!(tree.text_len(), 3.into());
+    /// ```
+    #[inline]
+    pub fn start_node_with_len_hint(&mut self, kind: S, len: TextSize) {
+        self.start_node(kind);
+        self.len_hints.push((self.parents.len(), len));
     }
 
     /// Finish the current branch and restore the previous branch as current.
     #[inline]
     pub fn finish_node(&mut self) {
-        let (kind, first_child) = self.parents.pop().unwrap();
+        let depth = self.parents.len();
+        let (kind, first_child, flags) = self.parents.pop().unwrap();
         // NOTE: we rely on the node cache to remove all children starting at `first_child` from `self.children`
-        let node = self.cache.node::<S>(kind, &mut self.children, first_child);
+        let node = self
+            .cache
+            .build_node_with_flags(S::into_raw(kind), flags, &mut self.children, first_child);
+        if let Some(&(hint_depth, len)) = self.len_hints.last() {
+            if hint_depth == depth {
+                self.len_hints.pop();
+                debug_assert_eq!(
+                    node.text_len(),
+                    len,
+                    "Finished `{kind:?}` node which was started with a length hint of {len:?}, but its text has a \
+                     different length."
+                );
+            }
+        }
+        self.stats.node_count += 1;
         self.children.push(node.into());
     }
 
+    /// Sets `flags` on the current node, in addition to any flags that were set on it before.
+    ///
+    /// Flags are an arbitrary set of bits that are stored with every green node, for example to mark nodes that
+    /// contain a syntax error. When a node is finished, its flags are combined with the flags of all of its children,
+    /// so the [flags](GreenNode::flags) of every node include those of its descendants. This allows skipping whole
+    /// subtrees that do not have a particular flag set. Nodes are only deduplicated with nodes that have the same
+    /// flags.
+    ///
+    /// The flags set on a node itself are kept when the node is rebuilt, such as when editing a tree, with
+    /// [`NodeCache::reintern`], or when encoding and decoding it in the binary format or with `serde`. Only nodes
+    /// that are created directly with [`NodeCache::node`] or [`GreenNode::new`] have no flags of their own, just
+    /// those of their children.
+    ///
+    /// ## Panics
+    /// If there is no current node, i.e. if no node has been started or all started nodes have been finished.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// const HAS_ERROR: u8 = 1;
+    ///
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.start_node(Operation);
+    /// builder.token(Int, "1");
+    /// builder.static_token(Plus);
+    /// builder.set_flags(HAS_ERROR);
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// let (tree, _) = builder.finish();
+    /// assert_eq!(tree.flags() & HAS_ERROR, HAS_ERROR);
+    /// ```
+    #[inline]
+    pub fn set_flags(&mut self, flags: u8) {
+        let (_, _, current) = self
+            .parents
+            .last_mut()
+            .expect("no current node to set flags on, call `start_node` first");
+        *current |= flags;
+    }
+
+    /// The number of nodes that have been started, but not yet finished.
+    ///
+    /// This is `0` before the root node is started and after it is finished.
+    #[inline]
+    pub fn current_depth(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Returns an iterator over the kinds of all nodes that have been started, but not yet finished, from the
+    /// outermost node (usually the root) to the innermost one, which is the node that would be finished by the next
+    /// call to [`finish_node`](GreenNodeBuilder::finish_node).
+    ///
+    /// Use `.next_back()` or `.last()` on the iterator to get the kind of the current node.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.start_node(Operation);
+    /// assert_eq!(builder.current_depth(), 2);
+    /// assert!(builder.open_kinds().eq([Root, Operation]));
+    /// builder.finish_node();
+    /// assert_eq!(builder.open_kinds().next_back(), Some(Root));
+    /// ```
+    #[inline]
+    pub fn open_kinds(&self) -> impl DoubleEndedIterator<Item = S> + ExactSizeIterator + '_ {
+        self.parents.iter().map(|&(kind, _, _)| kind)
+    }
+
     /// Prepare for maybe wrapping the next node with a surrounding node.
     ///
     /// The way wrapping works is that you first get a checkpoint, then you add nodes and tokens as
@@ -482,14 +1069,54 @@ where
             "checkpoint no longer valid, was finish_node called early?"
         );
 
-        if let Some(&(_, first_child)) = self.parents.last() {
+        if let Some(&(_, first_child, _)) = self.parents.last() {
             assert!(
                 checkpoint >= first_child,
                 "checkpoint no longer valid, was an unmatched start_node_at called?"
             );
         }
 
-        self.parents.push((kind, checkpoint));
+        self.parents.push((kind, checkpoint, 0));
+    }
+
+    /// Wrap the previous branch marked by [`checkpoint`](GreenNodeBuilder::checkpoint) in a new branch and make it
+    /// current, like [`start_node_at`](GreenNodeBuilder::start_node_at), but leave any trivia tokens at the start of
+    /// the branch outside of the new node.
+    ///
+    /// All tokens directly following the checkpoint for whose kind `is_trivia` returns `true` remain children of the
+    /// current node and become preceding siblings of the new node, which starts at the first other element. Trivia
+    /// inside of the wrapped elements or at their end is not affected.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// let checkpoint = builder.checkpoint();
+    /// builder.token(Whitespace, " ");
+    /// builder.token(Int, "1");
+    /// builder.start_node_at_trimmed(checkpoint, Operation, |kind| kind == Whitespace);
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    ///
+    /// let kinds: Vec<_> = root
+    ///     .children_with_tokens()
+    ///     .map(|child| child.kind())
+    ///     .collect();
+    /// assert_eq!(kinds, [Whitespace, Operation]);
+    /// ```
+    pub fn start_node_at_trimmed(&mut self, checkpoint: Checkpoint, kind: S, is_trivia: impl Fn(S) -> bool) {
+        let Checkpoint(mut start) = checkpoint;
+        while let Some(NodeOrToken::Token(token)) = self.children.get(start) {
+            if !is_trivia(S::from_raw(token.kind())) {
+                break;
+            }
+            start += 1;
+        }
+        self.start_node_at(Checkpoint(start), kind);
     }
 
     /// Complete building the tree.
@@ -503,13 +1130,287 @@ where
     /// [`from_cache`](GreenNodeBuilder::from_cache), this method returns the cache used to deduplicate tree nodes
     ///  as its second return value to allow re-using the cache or extracting the underlying string
     ///  [`Interner`]. See also [`NodeCache::into_interner`].
+    ///
+    /// ## Panics
+    /// If the total length of the source text exceeds [`u32::MAX`] bytes, which is the maximum that offsets into the
+    /// tree can represent. Use [`try_finish`](GreenNodeBuilder::try_finish) to handle this case gracefully.
+    ///
+    /// # Examples
+    /// Finishing consumes the builder, so it is not possible to accidentally keep adding to a tree that was already
+    /// finished, or to finish it twice:
+    /// ```compile_fail,E0382
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.finish_node();
+    /// let (tree, _) = builder.finish();
+    /// builder.token(Int, "1"); // error: `builder` was moved by the call to `finish`
+    /// ```
     #[inline]
-    pub fn finish(mut self) -> (GreenNode, Option<NodeCache<'interner, I>>) {
+    pub fn finish(self) -> (GreenNode, Option<NodeCache<'interner, I>>) {
+        match self.try_finish() {
+            Ok(res) => res,
+            Err(err) => panic!("failed to finish building the tree: {err}"),
+        }
+    }
+
+    /// Complete building the tree, like [`finish`](GreenNodeBuilder::finish), and also return statistics about the
+    /// built tree.
+    ///
+    /// The statistics are counted while building the tree, so this does not need to walk the tree again. See also
+    /// [`stats`](GreenNodeBuilder::stats).
+    ///
+    /// ## Panics
+    /// In the same cases as [`finish`](GreenNodeBuilder::finish).
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "1");
+    /// builder.finish_node();
+    /// let (_, _, stats) = builder.finish_with_stats();
+    /// assert_eq!(stats.node_count, 1);
+    /// assert_eq!(stats.token_count, 3);
+    /// assert_eq!(stats.interned_tokens, 2);
+    /// ```
+    #[inline]
+    pub fn finish_with_stats(self) -> (GreenNode, Option<NodeCache<'interner, I>>, BuildStats) {
+        let stats = self.stats;
+        let (node, cache) = self.finish();
+        (node, cache, stats)
+    }
+
+    /// Statistics about the nodes and tokens that have been added to the current tree so far.
+    ///
+    /// Nodes are only counted once they are finished. The statistics start over when a new tree is started with
+    /// [`finish_and_reset`](GreenNodeBuilder::finish_and_reset) or [`reset`](GreenNodeBuilder::reset).
+    #[inline]
+    pub fn stats(&self) -> BuildStats {
+        self.stats
+    }
+
+    /// Complete building the tree, like [`finish`](GreenNodeBuilder::finish), but return an error instead of
+    /// panicking if the source text is too large for the tree.
+    ///
+    /// # Errors
+    /// Returns [`BuildError::SourceTooLarge`] if the total length of the text of all tokens exceeds [`u32::MAX`]
+    /// bytes. Tokens that were added after this limit was exceeded have not been added to the tree.
+    #[inline]
+    pub fn try_finish(mut self) -> Result<(GreenNode, Option<NodeCache<'interner, I>>), BuildError> {
+        if self.text_len > u64::from(u32::MAX) {
+            return Err(BuildError::SourceTooLarge { len: self.text_len });
+        }
         assert_eq!(self.children.len(), 1);
         let cache = self.cache.into_owned();
         match self.children.pop().unwrap() {
-            NodeOrToken::Node(node) => (node, cache),
+            NodeOrToken::Node(node) => Ok((node, cache)),
+            NodeOrToken::Token(_) => panic!("called `finish` on a `GreenNodeBuilder` which only contained a token"),
+        }
+    }
+
+    /// Complete building the tree and return only the resulting [`GreenNode`].
+    ///
+    /// This is intended for builders that borrow their cache, i.e. that were constructed with
+    /// [`with_cache`](GreenNodeBuilder::with_cache), for which [`finish`](GreenNodeBuilder::finish) never returns
+    /// a cache. If the builder owns its cache, the cache is dropped, which is only allowed if the cache borrows its
+    /// interner. Otherwise, the text of the tree's tokens could never be resolved.
+    ///
+    /// The same rules as for [`finish`](GreenNodeBuilder::finish) apply regarding balanced calls to
+    /// [`start_node`](GreenNodeBuilder::start_node) and [`finish_node`](GreenNodeBuilder::finish_node).
+    ///
+    /// ## Panics
+    /// In the same cases as [`finish`](GreenNodeBuilder::finish), and if the builder owns its cache and the cache owns
+    /// its interner, such as for a builder created with [`new`](GreenNodeBuilder::new). Use
+    /// [`finish`](GreenNodeBuilder::finish) to keep the interner in that case.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::build::NodeCache;
+    /// let mut cache = NodeCache::new();
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::with_cache(&mut cache);
+    /// builder.start_node(Root);
+    /// builder.token(Int, "42");
+    /// builder.finish_node();
+    /// let green = builder.finish_node_tree();
+    /// assert_eq!(green.kind(), MySyntax::into_raw(Root));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn finish_node_tree(self) -> GreenNode {
+        let (node, cache) = self.finish();
+        assert!(
+            cache.map_or(true, |cache| cache.into_interner().is_none()),
+            "called `finish_node_tree` on a `GreenNodeBuilder` that owns its interner, use `finish` to keep it"
+        );
+        node
+    }
+
+    /// Complete building the current tree and return its root, leaving the builder ready to build a new tree.
+    ///
+    /// Unlike [`finish`](GreenNodeBuilder::finish), this does not consume the builder. Its cache and interner are
+    /// kept, so later trees share nodes and tokens with earlier ones, and its internal buffers keep their capacity,
+    /// so building many trees with the same builder does not allocate them anew for each tree. See also
+    /// [`reset`](GreenNodeBuilder::reset).
+    ///
+    /// ## Panics
+    /// If the total length of the source text exceeds [`u32::MAX`] bytes, like [`finish`](GreenNodeBuilder::finish).
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// let mut trees = Vec::new();
+    /// for text in ["1", "2"] {
+    ///     builder.start_node(Root);
+    ///     builder.token(Int, text);
+    ///     builder.finish_node();
+    ///     trees.push(builder.finish_and_reset());
+    /// }
+    /// let second: SyntaxNode<MySyntax> = SyntaxNode::new_root(trees[1].clone());
+    /// assert_eq!(second.resolve_text(builder.interner()), "2");
+    /// ```
+    pub fn finish_and_reset(&mut self) -> GreenNode {
+        if self.text_len > u64::from(u32::MAX) {
+            let err = BuildError::SourceTooLarge { len: self.text_len };
+            panic!("failed to finish building the tree: {err}");
+        }
+        assert_eq!(self.children.len(), 1);
+        let root = self.children.pop().unwrap();
+        self.reset();
+        match root {
+            NodeOrToken::Node(node) => node,
             NodeOrToken::Token(_) => panic!("called `finish` on a `GreenNodeBuilder` which only contained a token"),
         }
     }
+
+    /// Discard the tree that is currently being built, including any nodes that have been started but not finished,
+    /// so that the builder can be used to build a new tree.
+    ///
+    /// The builder's cache and interner are kept, as is the capacity of its internal buffers.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.parents.clear();
+        self.len_hints.clear();
+        self.children.clear();
+        self.text_len = 0;
+        self.stats = BuildStats::default();
+    }
+}
+
+impl GreenNode {
+    /// Builds a tree from a flat list of `items`, each of which is a node or token together with its depth in the
+    /// tree. This is a compact way of describing small trees, for example in tests.
+    ///
+    /// An item `(depth, kind, None)` starts a new node of the given `kind`, and an item `(depth, kind, Some(text))`
+    /// is a token with the given `text`. Every item after the first is a child of the closest preceding node with a
+    /// smaller depth, which must be exactly one smaller than the depth of the item. The first item is the root and
+    /// must be a node at depth `0`, and no other item has depth `0`.
+    ///
+    /// # Errors
+    /// Returns [`BuildError::InvalidNesting`] if the items do not describe a tree with a single root node according
+    /// to the above rules, for example because the depth of an item is more than one larger than the depth of the
+    /// node before it. Like [`GreenNodeBuilder::try_finish`], returns [`BuildError::SourceTooLarge`] if the text of
+    /// the tree is too large.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::build::NodeCache;
+    ///
+    /// let mut cache = NodeCache::new();
+    /// let tree = GreenNode::from_nested(
+    ///     &[
+    ///         (0, Root, None),
+    ///         (1, Operation, None),
+    ///         (2, Int, Some("1")),
+    ///         (2, Plus, Some("+")),
+    ///         (2, Int, Some("2")),
+    ///     ],
+    ///     &mut cache,
+    /// )
+    /// .unwrap();
+    /// let tree: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    /// assert_eq!(tree.first_child().unwrap().kind(), Operation);
+    /// assert_eq!(tree.resolve_text(cache.interner()), "1+2");
+    /// ```
+    pub fn from_nested<S, I>(
+        items: &[(usize, S, Option<&str>)],
+        cache: &mut NodeCache<'_, I>,
+    ) -> Result<Self, BuildError>
+    where
+        S: Syntax,
+        I: Interner<TokenKey>,
+    {
+        let mut builder: GreenNodeBuilder<'_, '_, S, I> = GreenNodeBuilder::with_cache(cache);
+        for (index, &(depth, kind, text)) in items.iter().enumerate() {
+            let is_root = index == 0;
+            let open = builder.current_depth();
+            // every item other than the root must be a child of one of the open nodes
+            if (depth == 0) != is_root || depth > open || (is_root && text.is_some()) {
+                return Err(BuildError::InvalidNesting { index });
+            }
+            for _ in depth..open {
+                builder.finish_node();
+            }
+            match text {
+                None => builder.start_node(kind),
+                Some(text) => builder.token(kind, text),
+            }
+        }
+        if items.is_empty() {
+            return Err(BuildError::InvalidNesting { index: 0 });
+        }
+        while builder.current_depth() > 0 {
+            builder.finish_node();
+        }
+        builder.try_finish().map(|(node, _)| node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    #[test]
+    fn source_too_large() {
+        let mut builder: GreenNodeBuilder<TestSyntaxKind> = GreenNodeBuilder::new();
+        builder.start_node(Root);
+        builder.token(Int, "1");
+        // pretend that a lot of text was already added, without allocating it
+        builder.text_len = u64::from(u32::MAX) - 2;
+        builder.token(Int, "23");
+        builder.finish_node();
+        assert!(builder.try_finish().is_ok());
+
+        let mut builder: GreenNodeBuilder<TestSyntaxKind> = GreenNodeBuilder::new();
+        builder.start_node(Root);
+        builder.text_len = u64::from(u32::MAX) - 2;
+        builder.token(Int, "123");
+        builder.static_token(Plus);
+        builder.finish_node();
+        let err = builder.try_finish().unwrap_err();
+        assert_eq!(
+            err,
+            BuildError::SourceTooLarge {
+                len: u64::from(u32::MAX) + 2,
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "too large")]
+    fn finish_panics_if_source_too_large() {
+        let mut builder: GreenNodeBuilder<TestSyntaxKind> = GreenNodeBuilder::new();
+        builder.start_node(Root);
+        builder.text_len = u64::from(u32::MAX);
+        builder.token(Int, "1");
+        builder.finish_node();
+        builder.finish();
+    }
 }
@@ -13,7 +13,8 @@ use crate::{
     RawSyntaxKind,
 };
 
-pub(super) type GreenElement = NodeOrToken<GreenNode, GreenToken>;
+/// An owned element of a green tree, which is either a [`GreenNode`] or a [`GreenToken`].
+pub type GreenElement = NodeOrToken<GreenNode, GreenToken>;
 pub(crate) type GreenElementRef<'a> = NodeOrToken<&'a GreenNode, &'a GreenToken>;
 
 #[repr(transparent)]
@@ -75,6 +76,12 @@ impl GreenElement {
     pub fn text_len(&self) -> TextSize {
         self.as_ref().text_len()
     }
+
+    /// Returns the [flags](GreenNode::flags) of this element, which are always empty for tokens.
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.as_ref().flags()
+    }
 }
 
 impl GreenElementRef<'_> {
@@ -95,6 +102,15 @@ impl GreenElementRef<'_> {
             NodeOrToken::Token(it) => it.text_len(),
         }
     }
+
+    /// Returns the [flags](GreenNode::flags) of this element, which are always empty for tokens.
+    #[inline]
+    pub fn flags(self) -> u8 {
+        match self {
+            NodeOrToken::Node(it) => it.flags(),
+            NodeOrToken::Token(_) => 0,
+        }
+    }
 }
 
 impl From<GreenElement> for PackedGreenElement {
@@ -118,7 +134,7 @@ impl From<PackedGreenElement> for GreenElement {
 
 impl PackedGreenElement {
     pub(crate) fn is_node(&self) -> bool {
-        self.ptr.addr() & super::token::IS_TOKEN_TAG == 0
+        Strict::addr(self.ptr) & super::token::IS_TOKEN_TAG == 0
     }
 
     pub(crate) fn as_node(&self) -> Option<&GreenNode> {
@@ -131,7 +147,7 @@ impl PackedGreenElement {
 
     pub(crate) fn into_node(self) -> Option<GreenNode> {
         if self.is_node() {
-            unsafe { Some(mem::transmute(self)) }
+            unsafe { Some(mem::transmute::<PackedGreenElement, GreenNode>(self)) }
         } else {
             None
         }
@@ -147,7 +163,7 @@ impl PackedGreenElement {
 
     pub(crate) fn into_token(self) -> Option<GreenToken> {
         if !self.is_node() {
-            unsafe { Some(mem::transmute(self)) }
+            unsafe { Some(mem::transmute::<PackedGreenElement, GreenToken>(self)) }
         } else {
             None
         }
@@ -0,0 +1,160 @@
+use std::marker::PhantomData;
+
+use text_size::TextSize;
+
+use crate::{
+    build::NodeCache,
+    green::{GreenElement, GreenNode},
+    interning::{Interner, TokenInterner, TokenKey},
+    util::NodeOrToken,
+    RawSyntaxKind, Syntax,
+};
+
+/// A node on the path from the root of a [`GrowableTree`] to its open node, with the children it had when the tree
+/// was opened plus all children appended since.
+#[derive(Debug)]
+struct OpenNode {
+    kind:     RawSyntaxKind,
+    children: Vec<GreenElement>,
+    /// The index of the next open node on the path among `children`. Unused for the open node itself.
+    index:    usize,
+}
+
+/// An already finished tree that can be extended by appending to one of its nodes, for example to add newly
+/// entered input in a REPL without rebuilding the tree from scratch.
+///
+/// Appended tokens and nodes are added as the last children of the _open node_, which is the root by default or
+/// can be chosen with [`with_open_node`](GrowableTree::with_open_node). Since the rest of the tree is not affected,
+/// appending only requires the nodes on the path from the root to the open node to be rebuilt, which happens once
+/// when calling [`green`](GrowableTree::green) or [`finish`](GrowableTree::finish).
+///
+/// Like with a [`GreenNodeBuilder`](crate::build::GreenNodeBuilder), the `cache` must use the same interner as the
+/// original tree, so that the text of all tokens can be resolved by the same interner.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::build::{GrowableTree, NodeCache};
+///
+/// let mut cache = NodeCache::new();
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::with_cache(&mut cache);
+/// builder.start_node(Root);
+/// builder.token(Int, "1");
+/// builder.finish_node();
+/// let (tree, _) = builder.finish();
+///
+/// let mut tree: GrowableTree<MySyntax> = GrowableTree::new(tree, &mut cache);
+/// tree.append_static_token(Plus);
+/// tree.append_token(Int, "2");
+/// let tree: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree.finish());
+/// assert_eq!(tree.resolve_text(cache.interner()), "1+2");
+/// ```
+#[derive(Debug)]
+pub struct GrowableTree<'c, 'i, S: Syntax, I = TokenInterner> {
+    cache:    &'c mut NodeCache<'i, I>,
+    /// The nodes from the root to the open node.
+    path:     Vec<OpenNode>,
+    text_len: TextSize,
+    _syntax:  PhantomData<S>,
+}
+
+impl<'c, 'i, S: Syntax, I> GrowableTree<'c, 'i, S, I>
+where
+    I: Interner<TokenKey>,
+{
+    /// Makes the tree with the given `root` growable, with the root as its open node.
+    #[inline]
+    pub fn new(root: GreenNode, cache: &'c mut NodeCache<'i, I>) -> Self {
+        // NOTE: the empty path always exists
+        Self::with_open_node(root, &[], cache).unwrap()
+    }
+
+    /// Makes the tree with the given `root` growable, with the node at `path` as its open node.
+    ///
+    /// The `path` gives the index of the child to descend into at each level, starting at the root. These are the
+    /// indices reported by [`SyntaxNode::ancestors_with_index`](crate::syntax::SyntaxNode::ancestors_with_index),
+    /// in reverse order. Returns `None` if there is no node at `path`.
+    pub fn with_open_node(root: GreenNode, path: &[usize], cache: &'c mut NodeCache<'i, I>) -> Option<Self> {
+        let text_len = root.text_len();
+        let mut open = Vec::with_capacity(path.len() + 1);
+        let mut node = root;
+        for &index in path {
+            let child = node.children().nth(index)?.into_node()?.clone();
+            open.push(OpenNode {
+                kind: node.kind(),
+                children: node.children().map(|child| child.cloned()).collect(),
+                index,
+            });
+            node = child;
+        }
+        open.push(OpenNode {
+            kind:     node.kind(),
+            children: node.children().map(|child| child.cloned()).collect(),
+            index:    0,
+        });
+        Some(Self {
+            cache,
+            path: open,
+            text_len,
+            _syntax: PhantomData,
+        })
+    }
+
+    /// The length of the text of the whole tree, including all appended elements. This is the offset at which the
+    /// text of the next appended element will start if the open node is the last node in the tree.
+    #[inline]
+    pub fn text_len(&self) -> TextSize {
+        self.text_len
+    }
+
+    /// Appends a new token with the given `kind` and `text` to the open node.
+    ///
+    /// ## Panics
+    /// In debug mode, if `kind` has static text, this function will verify that `text` matches that text.
+    #[inline]
+    pub fn append_token(&mut self, kind: S, text: &str) {
+        let token = self.cache.token(kind, text);
+        self.append(token.into());
+    }
+
+    /// Appends a new token to the open node, the text of which is the [static text](Syntax::static_text) of `kind`.
+    ///
+    /// ## Panics
+    /// If `kind` does not have static text, i.e., `S::static_text(kind)` returns `None`.
+    #[inline]
+    pub fn append_static_token(&mut self, kind: S) {
+        let static_text = S::static_text(kind).unwrap_or_else(|| panic!("Missing static text for '{kind:?}'"));
+        self.append_token(kind, static_text);
+    }
+
+    /// Appends an existing green `node`, for example one created with [`NodeCache::node`], to the open node.
+    #[inline]
+    pub fn append_node(&mut self, node: GreenNode) {
+        self.append(node.into());
+    }
+
+    fn append(&mut self, element: GreenElement) {
+        self.text_len += element.text_len();
+        // NOTE: the path always contains at least the root
+        self.path.last_mut().unwrap().children.push(element);
+    }
+
+    /// Builds the green tree with all elements appended so far, without consuming the growable tree.
+    pub fn green(&mut self) -> GreenNode {
+        let mut node: Option<GreenNode> = None;
+        for open in self.path.iter().rev() {
+            let mut children = open.children.clone();
+            if let Some(child) = node {
+                children[open.index] = NodeOrToken::Node(child);
+            }
+            node = Some(self.cache.build_node(open.kind, &mut children, 0));
+        }
+        node.unwrap()
+    }
+
+    /// Completes growing the tree and returns its final green root.
+    #[inline]
+    pub fn finish(mut self) -> GreenNode {
+        self.green()
+    }
+}
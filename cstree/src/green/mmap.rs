@@ -0,0 +1,343 @@
+use std::{fmt, io, iter::FusedIterator, str};
+
+use text_size::{TextRange, TextSize};
+
+use crate::{util::NodeOrToken, RawSyntaxKind};
+
+use super::binary::{read_u32, read_u8, DecodeError, MAGIC, NODE_TAG, TOKEN_TAG, VERSION};
+
+/// The length of the magic number and format version that precede the root node.
+const PREFIX_LEN: usize = MAGIC.len() + 1;
+/// The encoded length of a node without its children: tag, kind, number of children and flags.
+const NODE_HEADER_LEN: usize = 1 + 4 + 4 + 1;
+/// The encoded length of a token without its text: tag, kind, text length and whether there is text.
+const TOKEN_HEADER_LEN: usize = 1 + 4 + 4 + 1;
+
+/// A read-only view of a tree in the binary format written by [`GreenNode::to_bytes`](super::GreenNode::to_bytes),
+/// which reads all information directly from the encoded bytes.
+///
+/// Unlike [`GreenNode::from_bytes`](super::GreenNode::from_bytes), creating the view does not allocate any nodes or
+/// intern any text, so it is suitable for traversing large trees that are loaded from disk, for example from a
+/// memory-mapped file. All values are read byte-wise in little-endian order, so the bytes do not have to be aligned
+/// in any way.
+///
+/// The encoding does not store the text length of nodes, so computing it requires scanning the node's subtree.
+/// Iterating over the children of a node computes the range of each child and thus scans all of them once.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::{green::MmapTree, text::TextRange};
+///
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.token(Int, "1");
+/// builder.static_token(Plus);
+/// builder.token(Int, "2");
+/// builder.finish_node();
+/// let (tree, cache) = builder.finish();
+/// let interner = cache.unwrap().into_interner().unwrap();
+/// let bytes = tree.to_bytes(&interner);
+///
+/// let view = MmapTree::new(&bytes).unwrap();
+/// let root = view.root();
+/// assert_eq!(root.kind(), MySyntax::into_raw(Root));
+/// assert_eq!(root.text_range(), TextRange::up_to(3.into()));
+/// let texts: Vec<_> = root
+///     .children()
+///     .filter_map(|child| child.into_token())
+///     .map(|token| token.text())
+///     .collect();
+/// assert_eq!(texts, [Some("1"), None, Some("2")]);
+/// ```
+#[derive(Clone, Copy)]
+pub struct MmapTree<'b> {
+    bytes: &'b [u8],
+}
+
+impl<'b> MmapTree<'b> {
+    /// Creates a view of the tree encoded in `bytes`.
+    ///
+    /// The whole input is validated once, so that accessing the tree afterwards cannot fail.
+    ///
+    /// # Errors
+    /// Returns a [`DecodeError`] if `bytes` are not a valid encoding of a tree, including if there are any bytes left
+    /// after the end of the tree.
+    pub fn new(bytes: &'b [u8]) -> Result<Self, DecodeError> {
+        validate(bytes)?;
+        Ok(Self { bytes })
+    }
+
+    /// The root node of the tree.
+    #[inline]
+    pub fn root(&self) -> MmapNode<'b> {
+        MmapNode {
+            bytes:  self.bytes,
+            pos:    PREFIX_LEN,
+            offset: 0.into(),
+        }
+    }
+
+    /// The encoded bytes this view reads from.
+    #[inline]
+    pub fn as_bytes(&self) -> &'b [u8] {
+        self.bytes
+    }
+}
+
+impl fmt::Debug for MmapTree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MmapTree").field("root", &self.root()).finish()
+    }
+}
+
+/// A node inside an [`MmapTree`].
+#[derive(Clone, Copy)]
+pub struct MmapNode<'b> {
+    bytes:  &'b [u8],
+    /// The position of the node's header in `bytes`.
+    pos:    usize,
+    /// The offset of the node's text in the text of the tree.
+    offset: TextSize,
+}
+
+impl<'b> MmapNode<'b> {
+    /// [`RawSyntaxKind`] of this node.
+    #[inline]
+    pub fn kind(&self) -> RawSyntaxKind {
+        RawSyntaxKind(u32_at(self.bytes, self.pos + 1))
+    }
+
+    /// The range this node covers in the text of the tree.
+    #[inline]
+    pub fn text_range(&self) -> TextRange {
+        let (_, len) = skip_element(self.bytes, self.pos);
+        TextRange::at(self.offset, len)
+    }
+
+    /// Iterator over all children of this node.
+    #[inline]
+    pub fn children(&self) -> MmapNodeChildren<'b> {
+        MmapNodeChildren {
+            bytes:     self.bytes,
+            pos:       self.pos + NODE_HEADER_LEN,
+            offset:    self.offset,
+            remaining: u32_at(self.bytes, self.pos + 5) as usize,
+        }
+    }
+}
+
+impl fmt::Debug for MmapNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MmapNode")
+            .field("kind", &self.kind())
+            .field("text_range", &self.text_range())
+            .finish()
+    }
+}
+
+/// A token inside an [`MmapTree`].
+#[derive(Clone, Copy)]
+pub struct MmapToken<'b> {
+    bytes:  &'b [u8],
+    /// The position of the token's header in `bytes`.
+    pos:    usize,
+    /// The offset of the token's text in the text of the tree.
+    offset: TextSize,
+}
+
+impl<'b> MmapToken<'b> {
+    /// [`RawSyntaxKind`] of this token.
+    #[inline]
+    pub fn kind(&self) -> RawSyntaxKind {
+        RawSyntaxKind(u32_at(self.bytes, self.pos + 1))
+    }
+
+    /// The range this token covers in the text of the tree.
+    #[inline]
+    pub fn text_range(&self) -> TextRange {
+        TextRange::at(self.offset, u32_at(self.bytes, self.pos + 5).into())
+    }
+
+    /// The text of this token, borrowed from the encoded bytes.
+    ///
+    /// Returns `None` for tokens whose kind has [static text](crate::Syntax::static_text), which is not included in
+    /// the encoding.
+    #[inline]
+    pub fn text(&self) -> Option<&'b str> {
+        if self.bytes[self.pos + 9] == 0 {
+            return None;
+        }
+        let start = self.pos + TOKEN_HEADER_LEN;
+        let len = u32_at(self.bytes, self.pos + 5) as usize;
+        // SAFETY: the text of all tokens was validated to be UTF-8 when the `MmapTree` was created
+        Some(unsafe { str::from_utf8_unchecked(&self.bytes[start..start + len]) })
+    }
+}
+
+impl fmt::Debug for MmapToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MmapToken")
+            .field("kind", &self.kind())
+            .field("text_range", &self.text_range())
+            .field("text", &self.text())
+            .finish()
+    }
+}
+
+/// An element (node or token) inside an [`MmapTree`].
+pub type MmapElement<'b> = NodeOrToken<MmapNode<'b>, MmapToken<'b>>;
+
+impl MmapElement<'_> {
+    /// Returns kind of this element.
+    #[inline]
+    pub fn kind(&self) -> RawSyntaxKind {
+        match self {
+            NodeOrToken::Node(it) => it.kind(),
+            NodeOrToken::Token(it) => it.kind(),
+        }
+    }
+
+    /// The range this element covers in the text of the tree.
+    #[inline]
+    pub fn text_range(&self) -> TextRange {
+        match self {
+            NodeOrToken::Node(it) => it.text_range(),
+            NodeOrToken::Token(it) => it.text_range(),
+        }
+    }
+}
+
+/// An iterator over the children of a node in an [`MmapTree`].
+#[derive(Debug, Clone)]
+pub struct MmapNodeChildren<'b> {
+    bytes:     &'b [u8],
+    /// The position of the next child in `bytes`.
+    pos:       usize,
+    /// The offset of the next child's text in the text of the tree.
+    offset:    TextSize,
+    remaining: usize,
+}
+
+impl<'b> Iterator for MmapNodeChildren<'b> {
+    type Item = MmapElement<'b>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let (pos, offset) = (self.pos, self.offset);
+        let (end, len) = skip_element(self.bytes, pos);
+        self.pos = end;
+        self.offset += len;
+        let element = match self.bytes[pos] {
+            NODE_TAG => NodeOrToken::Node(MmapNode {
+                bytes: self.bytes,
+                pos,
+                offset,
+            }),
+            _ => NodeOrToken::Token(MmapToken {
+                bytes: self.bytes,
+                pos,
+                offset,
+            }),
+        };
+        Some(element)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for MmapNodeChildren<'_> {
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl FusedIterator for MmapNodeChildren<'_> {}
+
+#[inline]
+fn u32_at(bytes: &[u8], pos: usize) -> u32 {
+    // NOTE: the length of the slice is always 4
+    u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap())
+}
+
+/// Returns the position just past the (validated) element at `pos`, together with the length of its text.
+fn skip_element(bytes: &[u8], mut pos: usize) -> (usize, TextSize) {
+    let mut len = TextSize::from(0);
+    // the number of elements that remain to be skipped
+    let mut remaining = 1_u64;
+    while remaining > 0 {
+        remaining -= 1;
+        if bytes[pos] == NODE_TAG {
+            remaining += u64::from(u32_at(bytes, pos + 5));
+            pos += NODE_HEADER_LEN;
+        } else {
+            let text_len = u32_at(bytes, pos + 5);
+            len += TextSize::from(text_len);
+            pos += TOKEN_HEADER_LEN;
+            if bytes[pos - 1] != 0 {
+                pos += text_len as usize;
+            }
+        }
+    }
+    (pos, len)
+}
+
+fn validate(bytes: &[u8]) -> Result<(), DecodeError> {
+    let mut reader = bytes;
+    let mut magic = [0; 4];
+    io::Read::read_exact(&mut reader, &mut magic)?;
+    if &magic != MAGIC {
+        return Err(DecodeError::InvalidMagic);
+    }
+    let version = read_u8(&mut reader)?;
+    if version != VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+
+    // the number of elements that remain to be read, including the root
+    let mut remaining = 1_u64;
+    let mut text_len = 0_u64;
+    let mut is_root = true;
+    while remaining > 0 {
+        remaining -= 1;
+        match read_u8(&mut reader)? {
+            NODE_TAG => {
+                read_u32(&mut reader)?;
+                remaining += u64::from(read_u32(&mut reader)?);
+                read_u8(&mut reader)?;
+            }
+            TOKEN_TAG => {
+                if is_root {
+                    return Err(DecodeError::RootIsToken);
+                }
+                read_u32(&mut reader)?;
+                let len = read_u32(&mut reader)?;
+                text_len += u64::from(len);
+                if read_u8(&mut reader)? != 0 {
+                    if reader.len() < len as usize {
+                        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                    }
+                    let (text, rest) = reader.split_at(len as usize);
+                    str::from_utf8(text).map_err(|_| DecodeError::InvalidUtf8)?;
+                    reader = rest;
+                }
+            }
+            tag => return Err(DecodeError::InvalidTag(tag)),
+        }
+        is_root = false;
+    }
+    if text_len > u64::from(u32::MAX) {
+        return Err(DecodeError::TextTooLong);
+    }
+    if !reader.is_empty() {
+        return Err(DecodeError::TrailingData);
+    }
+    Ok(())
+}
@@ -1,13 +1,16 @@
 use std::{
     hash::{Hash, Hasher},
-    slice,
+    mem, slice,
 };
 
 use fxhash::FxHasher32;
 
 use crate::{
-    green::{iter::GreenNodeChildren, GreenElement, PackedGreenElement},
+    build::NodeCache,
+    green::{iter::GreenNodeChildren, GreenElement, GreenElementRef, PackedGreenElement},
+    interning::{Interner, TokenKey},
     text::TextSize,
+    util::NodeOrToken,
     RawSyntaxKind,
 };
 use triomphe::{Arc, HeaderWithLength, ThinArc};
@@ -18,10 +21,20 @@ pub(super) struct GreenNodeHead {
     pub(super) kind:       RawSyntaxKind,
     pub(super) text_len:   TextSize,
     pub(super) child_hash: u32,
+    /// The flags of this node, including all flags of its descendants.
+    pub(super) flags:      u8,
+    /// The flags that were set on this node itself, which are kept when the node is rebuilt with other children.
+    pub(super) own_flags:  u8,
 }
 
 /// Internal node in the immutable "green" tree.
 /// It contains other nodes and tokens as its children.
+///
+/// Green nodes implement [`Hash`] and [`Eq`] based on their structure, not on their identity: two nodes are equal if
+/// they have the same kind and their children are equal, recursively. This is the same notion of equality that is used
+/// by the [`NodeCache`](crate::build::NodeCache) to deduplicate nodes, so green nodes can be used as keys to memoize
+/// computations on subtrees. Tokens compare their text by its interned key, so nodes from trees that were built with
+/// different interners should not be compared.
 #[derive(Clone)]
 pub struct GreenNode {
     pub(super) data: ThinArc<GreenNodeHead, PackedGreenElement>,
@@ -35,18 +48,45 @@ impl std::fmt::Debug for GreenNode {
 
 impl GreenNode {
     /// Creates a new Node.
+    ///
+    /// Nodes created with this function are not deduplicated. Use [`NodeCache::node`](crate::build::NodeCache::node)
+    /// to create nodes that share structure with other trees.
     #[inline]
     pub fn new<I>(kind: RawSyntaxKind, children: I) -> GreenNode
+    where
+        I: IntoIterator<Item = GreenElement>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        Self::new_with_flags(kind, 0, children)
+    }
+
+    /// Creates a node with the same kind and [own flags](GreenNode::own_flags) as this node, but with different
+    /// `children`.
+    ///
+    /// Like [`new`](GreenNode::new), this does not deduplicate the new node.
+    #[inline]
+    pub(crate) fn with_children<I>(&self, children: I) -> GreenNode
+    where
+        I: IntoIterator<Item = GreenElement>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        Self::new_with_flags(self.kind(), self.own_flags(), children)
+    }
+
+    #[inline]
+    fn new_with_flags<I>(kind: RawSyntaxKind, own_flags: u8, children: I) -> GreenNode
     where
         I: IntoIterator<Item = GreenElement>,
         I::IntoIter: ExactSizeIterator,
     {
         let mut hasher = FxHasher32::default();
         let mut text_len: TextSize = 0.into();
+        let mut flags = own_flags;
         let children = children
             .into_iter()
             .inspect(|it| {
                 text_len += it.text_len();
+                flags |= it.flags();
                 it.hash(&mut hasher);
             })
             .map(PackedGreenElement::from);
@@ -55,16 +95,19 @@ impl GreenNode {
                 kind,
                 text_len: 0.into(),
                 child_hash: 0,
+                flags: 0,
+                own_flags,
             },
             children.len(),
         );
         let mut data = Arc::from_header_and_iter(header, children);
 
-        // XXX: fixup `text_len` and `child_hash` after construction, because
+        // XXX: fixup `text_len`, `child_hash` and `flags` after construction, because
         // we can't iterate `children` twice.
         let header = &mut Arc::get_mut(&mut data).unwrap().header.header;
         header.text_len = text_len;
         header.child_hash = hasher.finish() as u32;
+        header.flags = flags;
         GreenNode {
             data: Arc::into_thin(data),
         }
@@ -77,30 +120,23 @@ impl GreenNode {
         children: I,
         text_len: TextSize,
         child_hash: u32,
+        flags: u8,
+        own_flags: u8,
     ) -> GreenNode
     where
         I: IntoIterator<Item = GreenElement>,
         I::IntoIter: ExactSizeIterator,
     {
-        let children = children.into_iter().map(PackedGreenElement::from);
-        let header = HeaderWithLength::new(
+        Self::from_head_and_children(
             GreenNodeHead {
                 kind,
-                text_len: 0.into(),
-                child_hash: 0,
+                text_len,
+                child_hash,
+                flags,
+                own_flags,
             },
-            children.len(),
-        );
-        let mut data = Arc::from_header_and_iter(header, children);
-
-        // XXX: fixup `text_len` and `child_hash` after construction, because
-        // we can't iterate `children` twice.
-        let header = &mut Arc::get_mut(&mut data).unwrap().header.header;
-        header.text_len = text_len;
-        header.child_hash = child_hash;
-        GreenNode {
-            data: Arc::into_thin(data),
-        }
+            children,
+        )
     }
 
     #[inline]
@@ -128,6 +164,21 @@ impl GreenNode {
         self.data.header.header.text_len
     }
 
+    /// The flags of this node, which are the flags that were set on this node while building it, combined with the
+    /// flags of all its descendants.
+    ///
+    /// See [`GreenNodeBuilder::set_flags`](crate::build::GreenNodeBuilder::set_flags).
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.data.header.header.flags
+    }
+
+    /// The flags that were set on this node itself, without the flags of its descendants.
+    #[inline]
+    pub(crate) fn own_flags(&self) -> u8 {
+        self.data.header.header.own_flags
+    }
+
     #[inline]
     pub(crate) fn iter(&self) -> slice::Iter<'_, PackedGreenElement> {
         self.data.slice.iter()
@@ -140,6 +191,112 @@ impl GreenNode {
             inner: self.data.slice.iter(),
         }
     }
+
+    /// The child of this node at the given `index` among all its children, including tokens, or `None` if this node
+    /// has fewer children.
+    ///
+    /// Children are stored contiguously, so this takes constant time.
+    #[inline]
+    pub fn child(&self, index: usize) -> Option<GreenElementRef<'_>> {
+        self.data.slice.get(index).map(PackedGreenElement::as_ref)
+    }
+
+    /// The element reached by starting at this node and descending into the child at each index in `path` in turn,
+    /// counting tokens as children. An empty `path` refers to this node itself.
+    ///
+    /// Returns `None` if an index is out of range or would descend into a token.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::build::NodeCache;
+    ///
+    /// let mut cache = NodeCache::new();
+    /// let int = cache.token(Int, "1");
+    /// let operation = cache.node(Operation, vec![int.into()]);
+    /// let tree = cache.node(Root, vec![operation.into()]);
+    /// let int = tree.child_at_path(&[0, 0]).unwrap();
+    /// assert_eq!(int.kind(), MySyntax::into_raw(Int));
+    /// assert!(tree.child_at_path(&[0, 1]).is_none());
+    /// assert!(tree.child_at_path(&[0, 0, 0]).is_none());
+    /// ```
+    pub fn child_at_path(&self, path: &[usize]) -> Option<GreenElementRef<'_>> {
+        let mut element = GreenElementRef::from(self);
+        for &index in path {
+            element = element.into_node()?.child(index)?;
+        }
+        Some(element)
+    }
+
+    /// Returns `true` if `self` and `other` are the same allocation, i.e. one is a clone of the other or both were
+    /// deduplicated into the same node by a [`NodeCache`](crate::build::NodeCache).
+    ///
+    /// Unlike `==`, which compares nodes structurally, this never looks at the children of the nodes.
+    #[inline]
+    pub fn ptr_eq(&self, other: &GreenNode) -> bool {
+        std::ptr::eq(self.data.ptr(), other.data.ptr())
+    }
+
+    /// The number of heap bytes allocated for the nodes and tokens in the subtree rooted at this node.
+    ///
+    /// This counts the allocation of every node (its header and its list of children) and every token in the
+    /// subtree, ignoring padding and allocator overhead. The text of tokens is not included, since it is owned by the
+    /// interner and shared between all tokens with the same text.
+    ///
+    /// Nodes and tokens that are shared, for example because they were deduplicated by a
+    /// [`NodeCache`](crate::build::NodeCache), are counted once for every occurrence in the subtree. The result is
+    /// therefore the size the subtree would have without any deduplication, and comparing it between subtrees with
+    /// repeated content can help to decide whether deduplicating them is worth it.
+    pub fn heap_size(&self) -> usize {
+        // the reference count, followed by the header and the children
+        let own = mem::size_of::<usize>()
+            + mem::size_of::<HeaderWithLength<GreenNodeHead>>()
+            + mem::size_of_val(&self.data.slice);
+        own + self
+            .children()
+            .map(|child| match child {
+                NodeOrToken::Node(node) => node.heap_size(),
+                NodeOrToken::Token(token) => token.heap_size(),
+            })
+            .sum::<usize>()
+    }
+
+    /// Rebuilds the tree rooted at this node with the kind of every node and token replaced by the result of `f`.
+    ///
+    /// The text and structure of the tree stay the same, so all elements keep their text ranges. Tokens keep their
+    /// interned text, which means that the new tree can be resolved with the same interner as the original one. The
+    /// new tree is built with `cache`, so it may share nodes and tokens with other trees built from the same cache.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::build::NodeCache;
+    ///
+    /// let mut cache = NodeCache::new();
+    /// let int = cache.token(Int, "1");
+    /// let tree = cache.node(Root, vec![int.into()]);
+    /// let floats = tree.map_kinds(
+    ///     |kind| match MySyntax::from_raw(kind) {
+    ///         Int => MySyntax::into_raw(Float),
+    ///         _ => kind,
+    ///     },
+    ///     &mut cache,
+    /// );
+    /// let floats: SyntaxNode<MySyntax> = SyntaxNode::new_root(floats);
+    /// let token = floats.first_token().unwrap();
+    /// assert_eq!(token.kind(), Float);
+    /// assert_eq!(token.resolve_text(cache.interner()), "1");
+    /// ```
+    pub fn map_kinds<I>(
+        &self,
+        mut f: impl FnMut(RawSyntaxKind) -> RawSyntaxKind,
+        cache: &mut NodeCache<'_, I>,
+    ) -> GreenNode
+    where
+        I: Interner<TokenKey>,
+    {
+        cache.map_node_kinds(self, &mut f)
+    }
 }
 
 impl Hash for GreenNode {
@@ -1,4 +1,8 @@
-use std::{fmt, hash, mem::ManuallyDrop, ptr::NonNull};
+use std::{
+    fmt, hash,
+    mem::{self, ManuallyDrop},
+    ptr::NonNull,
+};
 
 use crate::{
     interning::{Resolver, TokenKey},
@@ -17,6 +21,9 @@ pub(super) struct GreenTokenData {
 }
 
 /// Leaf node in the immutable "green" tree.
+///
+/// Like [`GreenNode`](crate::green::GreenNode)s, green tokens are compared and hashed structurally by their kind and
+/// text (as an interned key).
 pub struct GreenToken {
     ptr: NonNull<GreenTokenData>,
 }
@@ -28,14 +35,14 @@ pub(super) const IS_TOKEN_TAG: usize = 0x1;
 impl GreenToken {
     fn add_tag(ptr: NonNull<GreenTokenData>) -> NonNull<GreenTokenData> {
         unsafe {
-            let ptr = ptr.as_ptr().map_addr(|addr| addr | IS_TOKEN_TAG);
+            let ptr = Strict::map_addr(ptr.as_ptr(), |addr| addr | IS_TOKEN_TAG);
             NonNull::new_unchecked(ptr)
         }
     }
 
     fn remove_tag(ptr: NonNull<GreenTokenData>) -> NonNull<GreenTokenData> {
         unsafe {
-            let ptr = ptr.as_ptr().map_addr(|addr| addr & !IS_TOKEN_TAG);
+            let ptr = Strict::map_addr(ptr.as_ptr(), |addr| addr & !IS_TOKEN_TAG);
             NonNull::new_unchecked(ptr)
         }
     }
@@ -83,6 +90,22 @@ impl GreenToken {
     pub fn text_key(&self) -> Option<TokenKey> {
         self.data().text
     }
+
+    /// Returns `true` if `self` and `other` are the same allocation, i.e. one is a clone of the other or both were
+    /// deduplicated into the same token by a [`NodeCache`](crate::build::NodeCache).
+    #[inline]
+    pub fn ptr_eq(&self, other: &GreenToken) -> bool {
+        self.ptr == other.ptr
+    }
+
+    /// The number of heap bytes allocated for this token, not counting its text, which is owned by the interner.
+    ///
+    /// See [`GreenNode::heap_size`](crate::green::GreenNode::heap_size) for details.
+    #[inline]
+    pub fn heap_size(&self) -> usize {
+        // the reference count, followed by the token data
+        mem::size_of::<usize>() + mem::size_of::<GreenTokenData>()
+    }
 }
 
 impl fmt::Debug for GreenToken {
@@ -0,0 +1,165 @@
+use std::{error::Error, fmt};
+
+use text_size::TextSize;
+
+use crate::{
+    interning::{Resolver, TokenKey},
+    util::NodeOrToken,
+    Syntax,
+};
+
+use super::{GreenNode, GreenNodeChildren};
+
+/// An inconsistency in a green tree that was found by [`GreenNode::validate`].
+///
+/// Every variant contains the path to the offending element, given as the index of the child to descend into at each
+/// level, starting at the node that was validated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The stored text length of a node is not the sum of the lengths of its children.
+    NodeLength {
+        /// The path to the node.
+        path:     Vec<usize>,
+        /// The text length stored in the node.
+        stored:   TextSize,
+        /// The summed text length of the node's children.
+        children: TextSize,
+    },
+    /// The stored length of a token does not match the length of its text.
+    TokenLength {
+        /// The path to the token.
+        path:   Vec<usize>,
+        /// The text length stored in the token.
+        stored: TextSize,
+        /// The length of the token's resolved or static text.
+        text:   TextSize,
+    },
+    /// The text of a token cannot be resolved with the given resolver.
+    UnresolvedText {
+        /// The path to the token.
+        path: Vec<usize>,
+    },
+    /// A token has no text of its own, but its kind does not have [static text](Syntax::static_text) either.
+    MissingText {
+        /// The path to the token.
+        path: Vec<usize>,
+    },
+}
+
+impl ValidationError {
+    /// The path to the element at which the inconsistency was found.
+    pub fn path(&self) -> &[usize] {
+        match self {
+            ValidationError::NodeLength { path, .. }
+            | ValidationError::TokenLength { path, .. }
+            | ValidationError::UnresolvedText { path }
+            | ValidationError::MissingText { path } => path,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::NodeLength { path, stored, children } => write!(
+                f,
+                "node at {path:?} has a length of {stored:?}, but its children have a length of {children:?}"
+            ),
+            ValidationError::TokenLength { path, stored, text } => write!(
+                f,
+                "token at {path:?} has a length of {stored:?}, but its text has a length of {text:?}"
+            ),
+            ValidationError::UnresolvedText { path } => write!(f, "the text of the token at {path:?} is unknown"),
+            ValidationError::MissingText { path } => write!(f, "token at {path:?} has no text"),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+impl GreenNode {
+    /// Checks that the tree rooted at this node is consistent, for example before using a tree that was decoded from
+    /// untrusted input.
+    ///
+    /// The text length of every node must be the sum of the lengths of its children, and the length of every token
+    /// must match the length of its text. The text of tokens is resolved with `resolver`, or taken from the
+    /// [static text](Syntax::static_text) of their kind `S` if they have no text of their own.
+    ///
+    /// # Errors
+    /// Returns a [`ValidationError`] describing the first inconsistency in preorder.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.static_token(Plus);
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// assert_eq!(tree.validate::<MySyntax, _>(&interner), Ok(()));
+    /// ```
+    pub fn validate<S, R>(&self, resolver: &R) -> Result<(), ValidationError>
+    where
+        S: Syntax,
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        // the index of the next child to check in every node from the validated node to the current node, together
+        // with the children of these nodes that remain to be checked
+        let mut path = Vec::new();
+        let mut stack: Vec<GreenNodeChildren<'_>> = Vec::new();
+        let mut node = self;
+        loop {
+            let children = node.children().map(|child| child.text_len()).sum::<TextSize>();
+            if children != node.text_len() {
+                return Err(ValidationError::NodeLength {
+                    path,
+                    stored: node.text_len(),
+                    children,
+                });
+            }
+            stack.push(node.children());
+            path.push(0);
+
+            // find the next node to check, checking all tokens on the way
+            node = loop {
+                let Some(children) = stack.last_mut() else {
+                    return Ok(());
+                };
+                let Some(child) = children.next() else {
+                    stack.pop();
+                    path.pop();
+                    if let Some(index) = path.last_mut() {
+                        *index += 1;
+                    }
+                    continue;
+                };
+                match child {
+                    NodeOrToken::Node(node) => break node,
+                    NodeOrToken::Token(token) => {
+                        let text = match token.text_key() {
+                            Some(key) => match resolver.try_resolve(key) {
+                                Some(text) => text,
+                                None => return Err(ValidationError::UnresolvedText { path }),
+                            },
+                            None => match S::static_text(S::from_raw(token.kind())) {
+                                Some(text) => text,
+                                None => return Err(ValidationError::MissingText { path }),
+                            },
+                        };
+                        let text = TextSize::of(text);
+                        if text != token.text_len() {
+                            return Err(ValidationError::TokenLength {
+                                path,
+                                stored: token.text_len(),
+                                text,
+                            });
+                        }
+                        *path.last_mut().unwrap() += 1;
+                    }
+                }
+            };
+        }
+    }
+}
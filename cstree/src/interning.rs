@@ -45,7 +45,10 @@
 //! [`new_interner`], or bring your own by implementing the [`Resolver`] and [`Interner`] traits defined in this module.
 //! Most methods in `cstree` require that you support interning [`TokenKey`]s. `TokenKey` implements [`InternKey`], so
 //! your implementation can use that to convert to whatever types it uses for its internal representation. Note that
-//! there is no way to change the size of the internal representation.
+//! there is no way to change the size of the internal representation: green tokens always store a 32-bit
+//! `TokenKey` next to their 32-bit kind and text length, so a smaller key would only be padded to the same size.
+//! What you can choose is the key type of your interner itself, which determines how much memory the interner needs
+//! for its own lookup tables and how many strings it can hold.
 //!
 //! ### `lasso`
 //! Using features, you can enable support for some third-party interners. The primary one is [`lasso`], a crate focused
@@ -95,6 +98,32 @@ Here, we use `from_interner`, but pass it only a shared reference to "own". Take
 of the `GreenNodeBuilder` appropriately.
 "###
 )]
+#![cfg_attr(
+    feature = "lasso_compat",
+    doc = r###"
+## Smaller interner keys
+
+`lasso`'s interners are generic over the key type they use internally, and all of them can be used with `cstree`
+regardless of that type. For small inputs, using 16-bit keys (`MiniSpur`) instead of the default 32-bit ones halves
+the size of the keys in the interner's tables. Such an interner can only hold up to `u16::MAX` strings, and
+[`try_get_or_intern`](Interner::try_get_or_intern) returns an error once it is full:
+
+```
+# use cstree::testing::*;
+# use cstree::interning::*;
+use cstree::interning::lasso::{MiniSpur, Rodeo};
+
+let mut interner = Rodeo::<MiniSpur>::new();
+let mut builder: GreenNodeBuilder<MySyntax, Rodeo<MiniSpur>> = GreenNodeBuilder::with_interner(&mut interner);
+builder.start_node(Root);
+builder.token(Int, "42");
+builder.finish_node();
+let (tree, _) = builder.finish();
+let int = tree.children().next().unwrap();
+assert_eq!(int.as_token().unwrap().text(&interner), Some("42"));
+```
+"###
+)]
 //!
 //! [crate documentation]: crate
 //! [`Syntax::static_text`]: crate::Syntax::static_text
@@ -111,6 +140,26 @@ pub use self::traits::*;
 
 mod default_interner;
 
+/// The error returned by the [`ContiguousInterner`] and the built-in [`TokenInterner`] when interning fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InternerError {
+    /// All possible [`TokenKey`]s have already been used.
+    KeySpaceExhausted,
+}
+
+impl core::fmt::Display for InternerError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InternerError::KeySpaceExhausted => write!(f, "key space exhausted"),
+        }
+    }
+}
+
+impl std::error::Error for InternerError {}
+
+mod contiguous;
+pub use contiguous::ContiguousInterner;
+
 #[cfg(not(feature = "lasso_compat"))]
 #[doc(inline)]
 pub use default_interner::TokenInterner;
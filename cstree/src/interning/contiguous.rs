@@ -0,0 +1,147 @@
+use core::fmt;
+
+use super::{InternKey, Interner, InternerError, Resolver, TokenKey};
+
+/// Marks free slots in the lookup table of a [`ContiguousInterner`].
+const EMPTY: u32 = u32::MAX;
+
+/// An [`Interner`] that stores all interned strings back-to-back in a single buffer.
+///
+/// Compared to the default [`TokenInterner`](crate::interning::TokenInterner), which allocates every string
+/// individually, this improves memory locality when resolving many keys, at the cost of never being able to free
+/// individual strings. Keys are indices into a table of string offsets, so they remain valid (and the strings they
+/// resolve to remain unchanged) while more strings are added.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::interning::ContiguousInterner;
+///
+/// let mut interner = ContiguousInterner::new();
+/// let mut builder: GreenNodeBuilder<MySyntax, ContiguousInterner> =
+///     GreenNodeBuilder::with_interner(&mut interner);
+/// builder.start_node(Root);
+/// builder.token(Int, "42");
+/// builder.finish_node();
+/// let (tree, _) = builder.finish();
+///
+/// let tree: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+/// assert_eq!(tree.resolve_text(&interner), "42");
+/// ```
+#[derive(Default, Clone)]
+pub struct ContiguousInterner {
+    /// All interned strings, in the order in which they were interned.
+    text:  String,
+    /// The end of each string in `text`. Every string starts where the previous one ends.
+    ends:  Vec<usize>,
+    /// An open-addressing hash table of indices into `ends`, with `EMPTY` marking free slots.
+    /// Its length is always zero or a power of two.
+    table: Vec<u32>,
+}
+
+impl ContiguousInterner {
+    /// Creates a new, empty interner.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new, empty interner with space for at least `strings` strings with a total size of `bytes`.
+    pub fn with_capacity(strings: usize, bytes: usize) -> Self {
+        let mut interner = Self {
+            text:  String::with_capacity(bytes),
+            ends:  Vec::with_capacity(strings),
+            table: Vec::new(),
+        };
+        interner.grow_table((strings * 2).next_power_of_two());
+        interner
+    }
+
+    /// The number of distinct strings in this interner.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Returns `true` if no strings have been interned.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> &str {
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+        &self.text[start..self.ends[index]]
+    }
+
+    /// Returns the table slot that contains the index of `text`, or the free slot where it should be inserted.
+    fn find_slot(&self, text: &str, hash: u64) -> usize {
+        let mask = self.table.len() - 1;
+        let mut slot = hash as usize & mask;
+        loop {
+            match self.table[slot] {
+                EMPTY => return slot,
+                index if self.get(index as usize) == text => return slot,
+                _ => slot = (slot + 1) & mask,
+            }
+        }
+    }
+
+    fn grow_table(&mut self, capacity: usize) {
+        let capacity = capacity.max(16);
+        if capacity <= self.table.len() {
+            return;
+        }
+        self.table = vec![EMPTY; capacity];
+        for index in 0..self.ends.len() {
+            let slot = self.find_slot(self.get(index), fxhash::hash64(self.get(index)));
+            self.table[slot] = index as u32;
+        }
+    }
+}
+
+impl fmt::Debug for ContiguousInterner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContiguousInterner")
+            .field("len", &self.len())
+            .field("bytes", &self.text.len())
+            .finish()
+    }
+}
+
+impl Resolver<TokenKey> for ContiguousInterner {
+    #[inline]
+    fn try_resolve(&self, key: TokenKey) -> Option<&str> {
+        let index = key.into_u32() as usize;
+        (index < self.ends.len()).then(|| self.get(index))
+    }
+}
+
+impl Interner<TokenKey> for ContiguousInterner {
+    type Error = InternerError;
+
+    fn try_get_or_intern(&mut self, text: &str) -> Result<TokenKey, Self::Error> {
+        // keep the table at most half full
+        if (self.ends.len() + 1) * 2 > self.table.len() {
+            self.grow_table(self.table.len() * 2);
+        }
+
+        let hash = fxhash::hash64(text);
+        let slot = self.find_slot(text, hash);
+        if self.table[slot] != EMPTY {
+            return Ok(TokenKey::try_from_u32(self.table[slot]).unwrap());
+        }
+
+        // `EMPTY` itself cannot be used as an index, and is also not a valid `TokenKey`
+        let index = u32::try_from(self.ends.len())
+            .ok()
+            .filter(|&index| index != EMPTY)
+            .ok_or(InternerError::KeySpaceExhausted)?;
+        let key = TokenKey::try_from_u32(index).ok_or(InternerError::KeySpaceExhausted)?;
+        self.text.push_str(text);
+        self.ends.push(self.text.len());
+        self.table[slot] = index;
+        Ok(key)
+    }
+}
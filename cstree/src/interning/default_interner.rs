@@ -1,11 +1,9 @@
 #![cfg(not(feature = "lasso_compat"))]
 
-use core::fmt;
-
 use fxhash::FxBuildHasher as Hasher;
 use indexmap::IndexSet;
 
-use super::{InternKey, Interner, Resolver, TokenKey};
+use super::{InternKey, Interner, InternerError, Resolver, TokenKey};
 
 /// The default [`Interner`] used to deduplicate green token strings.
 #[derive(Debug)]
@@ -21,21 +19,6 @@ impl TokenInterner {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum InternerError {
-    KeySpaceExhausted,
-}
-
-impl fmt::Display for InternerError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            InternerError::KeySpaceExhausted => write!(f, "key space exhausted"),
-        }
-    }
-}
-
-impl std::error::Error for InternerError {}
-
 impl Resolver<TokenKey> for TokenInterner {
     fn try_resolve(&self, key: TokenKey) -> Option<&str> {
         let index = key.into_u32() as usize;
@@ -0,0 +1,256 @@
+//! Sets and ranges of syntax kinds for fast membership tests.
+
+use std::{fmt, marker::PhantomData, ops::Range};
+
+use fxhash::FxHashSet;
+
+use crate::{RawSyntaxKind, Syntax};
+
+/// The number of kinds that are stored in the bit mask of a [`KindSet`].
+const MASK_BITS: u32 = u128::BITS;
+
+/// A set of syntax kinds, for testing whether an element is of one of several kinds, for example whether a token
+/// is a binary operator.
+///
+/// Kinds whose [raw representation](Syntax::into_raw) is smaller than 128 are stored in a bit mask, so testing them
+/// for membership is a single bit operation. Larger kinds are stored in a hash set.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::util::KindSet;
+///
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.token(Int, "1");
+/// builder.static_token(Plus);
+/// builder.token(Float, "2.0");
+/// builder.finish_node();
+/// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+///
+/// let literals = KindSet::from([Int, Float]);
+/// assert!(literals.contains(Int));
+/// assert!(!literals.contains(Plus));
+/// let count = root
+///     .children_with_tokens()
+///     .filter(|child| literals.contains(child.kind()))
+///     .count();
+/// assert_eq!(count, 2);
+/// ```
+pub struct KindSet<S: Syntax> {
+    mask:    u128,
+    rest:    FxHashSet<u32>,
+    _syntax: PhantomData<S>,
+}
+
+impl<S: Syntax> KindSet<S> {
+    /// Creates a new, empty set.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            mask:    0,
+            rest:    FxHashSet::default(),
+            _syntax: PhantomData,
+        }
+    }
+
+    /// Adds `kind` to the set. Returns `true` if the set did not already contain `kind`.
+    pub fn insert(&mut self, kind: S) -> bool {
+        let RawSyntaxKind(raw) = S::into_raw(kind);
+        if raw < MASK_BITS {
+            let bit = 1 << raw;
+            let is_new = self.mask & bit == 0;
+            self.mask |= bit;
+            is_new
+        } else {
+            self.rest.insert(raw)
+        }
+    }
+
+    /// Returns `true` if the set contains `kind`.
+    #[inline]
+    pub fn contains(&self, kind: S) -> bool {
+        self.contains_raw(S::into_raw(kind))
+    }
+
+    /// Returns `true` if the set contains the kind with the given raw representation.
+    #[inline]
+    pub fn contains_raw(&self, kind: RawSyntaxKind) -> bool {
+        let RawSyntaxKind(raw) = kind;
+        if raw < MASK_BITS {
+            self.mask & (1 << raw) != 0
+        } else {
+            self.rest.contains(&raw)
+        }
+    }
+
+    /// The number of kinds in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.mask.count_ones() as usize + self.rest.len()
+    }
+
+    /// Returns `true` if the set contains no kinds.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0 && self.rest.is_empty()
+    }
+}
+
+impl<S: Syntax> Default for KindSet<S> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Syntax> Clone for KindSet<S> {
+    fn clone(&self) -> Self {
+        Self {
+            mask:    self.mask,
+            rest:    self.rest.clone(),
+            _syntax: PhantomData,
+        }
+    }
+}
+
+impl<S: Syntax> PartialEq for KindSet<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.mask == other.mask && self.rest == other.rest
+    }
+}
+
+impl<S: Syntax> Eq for KindSet<S> {}
+
+impl<S: Syntax> fmt::Debug for KindSet<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let masked = (0..MASK_BITS).filter(|raw| self.mask & (1 << raw) != 0);
+        let mut rest: Vec<_> = self.rest.iter().copied().collect();
+        rest.sort_unstable();
+        f.debug_set()
+            .entries(masked.chain(rest).map(|raw| S::from_raw(RawSyntaxKind(raw))))
+            .finish()
+    }
+}
+
+impl<S: Syntax> Extend<S> for KindSet<S> {
+    fn extend<T: IntoIterator<Item = S>>(&mut self, iter: T) {
+        for kind in iter {
+            self.insert(kind);
+        }
+    }
+}
+
+impl<S: Syntax> FromIterator<S> for KindSet<S> {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<S: Syntax, const N: usize> From<[S; N]> for KindSet<S> {
+    #[inline]
+    fn from(kinds: [S; N]) -> Self {
+        kinds.into_iter().collect()
+    }
+}
+
+/// A contiguous range of syntax kinds, for testing whether an element belongs to a category of kinds that are
+/// numbered consecutively, for example whether a token is a keyword.
+///
+/// The range contains all kinds whose [raw representation](Syntax::into_raw) lies between that of `start`
+/// (inclusive) and that of `end` (exclusive). Testing for membership is two comparisons, so a range is cheaper to
+/// build and query than a [`KindSet`] with the same kinds.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::util::KindRange;
+///
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.token(Int, "1");
+/// builder.static_token(Plus);
+/// builder.token(Float, "2.0");
+/// builder.finish_node();
+/// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+///
+/// // `Identifier`, `Int` and `Float`
+/// let atoms = KindRange::from(Identifier..Operation);
+/// assert!(atoms.contains(Int));
+/// assert!(!atoms.contains(Plus));
+/// assert!(!atoms.contains(Operation));
+/// let count = root.children_with_tokens().in_range(atoms).count();
+/// assert_eq!(count, 2);
+/// ```
+pub struct KindRange<S: Syntax> {
+    start:   u32,
+    end:     u32,
+    _syntax: PhantomData<S>,
+}
+
+impl<S: Syntax> KindRange<S> {
+    /// Creates the range of all kinds from `start` (inclusive) to `end` (exclusive). The range is empty if `end` is
+    /// not larger than `start`.
+    #[inline]
+    pub fn new(start: S, end: S) -> Self {
+        Self {
+            start:   S::into_raw(start).0,
+            end:     S::into_raw(end).0,
+            _syntax: PhantomData,
+        }
+    }
+
+    /// Returns `true` if the range contains `kind`.
+    #[inline]
+    pub fn contains(&self, kind: S) -> bool {
+        self.contains_raw(S::into_raw(kind))
+    }
+
+    /// Returns `true` if the range contains the kind with the given raw representation.
+    #[inline]
+    pub fn contains_raw(&self, kind: RawSyntaxKind) -> bool {
+        let RawSyntaxKind(raw) = kind;
+        self.start <= raw && raw < self.end
+    }
+
+    /// Returns `true` if the range contains no kinds.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.end <= self.start
+    }
+}
+
+impl<S: Syntax> Clone for KindRange<S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: Syntax> Copy for KindRange<S> {}
+
+impl<S: Syntax> PartialEq for KindRange<S> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.start, self.end) == (other.start, other.end)
+    }
+}
+
+impl<S: Syntax> Eq for KindRange<S> {}
+
+impl<S: Syntax> fmt::Debug for KindRange<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // NOTE: the end of the range may not be a valid kind, so print the raw representations
+        f.debug_tuple("KindRange")
+            .field(&(RawSyntaxKind(self.start)..RawSyntaxKind(self.end)))
+            .finish()
+    }
+}
+
+impl<S: Syntax> From<Range<S>> for KindRange<S> {
+    #[inline]
+    fn from(range: Range<S>) -> Self {
+        Self::new(range.start, range.end)
+    }
+}
@@ -58,15 +58,15 @@
 //! to happen to go from input text to a `cstree` syntax tree:
 //!
 //!  1. Define an enumeration of the types of tokens (like keywords) and nodes (like "an expression") that you want to
-//! have in your syntax and implement [`Syntax`]
+//!     have in your syntax and implement [`Syntax`]
 //!
 //!  2. Create a [`GreenNodeBuilder`](build::GreenNodeBuilder) and call
-//! [`start_node`](build::GreenNodeBuilder::start_node), [`token`](build::GreenNodeBuilder::token) and
-//! [`finish_node`](build::GreenNodeBuilder::finish_node) from your parser  
+//!     [`start_node`](build::GreenNodeBuilder::start_node), [`token`](build::GreenNodeBuilder::token) and
+//!     [`finish_node`](build::GreenNodeBuilder::finish_node) from your parser
 //!
 //!  3. Call [`SyntaxNode::new_root`](syntax::SyntaxNode::new_root) or
-//! [`SyntaxNode::new_root_with_resolver`](syntax::SyntaxNode::new_root_with_resolver) with the resulting
-//! [`GreenNode`](green::GreenNode) to obtain a syntax tree that you can traverse
+//!     [`SyntaxNode::new_root_with_resolver`](syntax::SyntaxNode::new_root_with_resolver) with the resulting
+//!     [`GreenNode`](green::GreenNode) to obtain a syntax tree that you can traverse
 //!
 //! There's a full [getting started guide] that walks through each of the above steps in detail in the documentation for
 //! the `getting_started` module. The walkthrough goes through the necessary steps bit by bit and skips the lexer, but
@@ -102,8 +102,13 @@ pub mod syntax;
 #[allow(unsafe_code)]
 pub mod interning;
 
+mod kinds;
+mod line_index;
 #[cfg(feature = "serialize")]
 mod serde_impls;
+#[cfg(feature = "testing")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "testing")))]
+pub mod test_utils;
 #[allow(missing_docs)]
 mod utility_types;
 
@@ -115,7 +120,10 @@ pub struct RawSyntaxKind(pub u32);
 
 /// Typesafe representations of text ranges and sizes.
 pub mod text {
-    pub use crate::syntax::SyntaxText;
+    pub use crate::{
+        line_index::{text_in_range, LineCol, LineIndex},
+        syntax::SyntaxText,
+    };
     pub use text_size::{TextLen, TextRange, TextSize};
 }
 
@@ -125,7 +133,11 @@ pub mod text {
 /// section](../index.html#getting-started) from the top-level documentation for an introduction to how to build a
 /// syntax tree.
 pub mod build {
-    pub use crate::green::builder::{Checkpoint, GreenNodeBuilder, NodeCache};
+    pub use crate::green::{
+        arena::ArenaGreenNodeBuilder,
+        builder::{BuildError, BuildStats, Checkpoint, GreenNodeBuilder, NodeCache, StructuralKey},
+        growable::GrowableTree,
+    };
 }
 
 /// A convenient collection of the most used parts of `cstree`.
@@ -140,13 +152,16 @@ pub mod prelude {
 
 /// Types for syntax tree traversal / moving through trees.
 pub mod traversal {
-    pub use crate::utility_types::{Direction, WalkEvent};
+    pub use crate::utility_types::{Direction, VisitControl, WalkEvent};
 }
 
 /// Utility types. It shouldn't be needed to reference these directly, but they are returned in several places in
 /// `cstree` and may come in handy.
 pub mod util {
-    pub use crate::utility_types::{NodeOrToken, TokenAtOffset};
+    pub use crate::{
+        kinds::{KindRange, KindSet},
+        utility_types::{NodeOrToken, TokenAtOffset},
+    };
 }
 
 /// Synchronization primitives.
@@ -189,6 +204,18 @@ pub trait Syntax: Sized + Copy + fmt::Debug + Eq {
     /// Construct a semantic item kind from the compact representation.
     fn from_raw(raw: RawSyntaxKind) -> Self;
 
+    /// Construct a semantic item kind from the compact representation, or return `None` if `raw` does not represent
+    /// a valid kind.
+    ///
+    /// This is used when decoding trees from untrusted input, such as with
+    /// [`GreenNode::from_bytes`](crate::green::GreenNode::from_bytes), to reject unknown kinds instead of passing them
+    /// to [`from_raw`](Syntax::from_raw). By default, this calls `from_raw`, so implementations whose `from_raw` panics
+    /// on invalid kinds should also implement this. The `Syntax` derive does so automatically.
+    #[inline]
+    fn try_from_raw(raw: RawSyntaxKind) -> Option<Self> {
+        Some(Self::from_raw(raw))
+    }
+
     /// Convert a semantic item kind into a more compact representation.
     fn into_raw(self) -> RawSyntaxKind;
 
@@ -200,6 +227,83 @@ pub trait Syntax: Sized + Copy + fmt::Debug + Eq {
     /// it faster to add them to a syntax tree and to look up their text. Since there can often be many occurrences
     /// of these tokens inside a file, doing so will improve the performance of using `cstree`.
     fn static_text(self) -> Option<&'static str>;
+
+    /// A human-readable name for a particular syntax kind, used in the [`Debug`](fmt::Debug) representations of
+    /// syntax nodes and tokens, such as [`SyntaxNode::debug`](crate::syntax::SyntaxNode::debug).
+    ///
+    /// By default, this returns `None` for all kinds, in which case the kind's own `Debug` representation is used
+    /// instead. Implement this if that representation is not readable, for example because your kinds are a newtype
+    /// around a number.
+    #[inline]
+    fn kind_name(self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// Generates associated constants for the kinds of a language that represents its kinds as a newtype over `u32`
+/// instead of an `enum`, so you can write `SyntaxKind::IDENT` instead of `SyntaxKind(7)`.
+///
+/// Constants are numbered consecutively from `0`, in the order in which they are given. Like `enum` discriminants,
+/// a constant may be assigned an explicit value with `= value`, after which numbering continues from that value.
+///
+/// By default, the constants are generated on the kind type itself, which must be a tuple struct around a `u32`. To
+/// generate them on a different type, such as a type representing your language, name the kind type after a `:`.
+///
+/// # Examples
+/// ```
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// pub struct SyntaxKind(u32);
+///
+/// cstree::syntax_kind_constants! {
+///     impl SyntaxKind {
+///         /// The root of every tree.
+///         ROOT,
+///         IDENT,
+///         INT = 10,
+///         FLOAT,
+///     }
+/// }
+///
+/// assert_eq!(SyntaxKind::ROOT, SyntaxKind(0));
+/// assert_eq!(SyntaxKind::IDENT, SyntaxKind(1));
+/// assert_eq!(SyntaxKind::FLOAT, SyntaxKind(11));
+///
+/// mod kinds {
+///     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///     pub struct SyntaxKind(pub u32);
+/// }
+///
+/// pub struct MyLanguage;
+/// cstree::syntax_kind_constants! {
+///     impl MyLanguage: kinds::SyntaxKind {
+///         ROOT,
+///         IDENT,
+///     }
+/// }
+///
+/// assert_eq!(MyLanguage::IDENT, kinds::SyntaxKind(1));
+/// ```
+#[macro_export]
+macro_rules! syntax_kind_constants {
+    (impl $target:ty { $($(#[$attr:meta])* $name:ident $(= $value:expr)?),* $(,)? }) => {
+        $crate::syntax_kind_constants!(impl $target: Self { $($(#[$attr])* $name $(= $value)?),* });
+    };
+    (impl $target:ty: $kind:path { $($(#[$attr:meta])* $name:ident $(= $value:expr)?),* $(,)? }) => {
+        const _: () = {
+            #[allow(non_camel_case_types, dead_code)]
+            #[repr(u32)]
+            enum Kinds {
+                $($name $(= $value)?),*
+            }
+
+            impl $target {
+                $(
+                    $(#[$attr])*
+                    pub const $name: $kind = $kind(Kinds::$name as u32);
+                )*
+            }
+        };
+    };
 }
 
 #[cfg(feature = "derive")]
@@ -239,6 +343,10 @@ pub mod testing {
             unsafe { std::mem::transmute::<u32, Self>(raw.0) }
         }
 
+        fn try_from_raw(raw: RawSyntaxKind) -> Option<Self> {
+            (raw.0 <= TestSyntaxKind::__LAST as u32).then(|| Self::from_raw(raw))
+        }
+
         fn into_raw(self) -> RawSyntaxKind {
             RawSyntaxKind(self as u32)
         }
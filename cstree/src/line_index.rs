@@ -0,0 +1,178 @@
+//! Conversion between text offsets and line/column positions.
+
+use text_size::{TextRange, TextSize};
+
+/// A position in a text, given as a zero-based line number and a zero-based column within that line.
+///
+/// The column counts bytes from the start of the line, like [`TextSize`] offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    /// The zero-based line number.
+    pub line: u32,
+    /// The zero-based column within the line, in bytes.
+    pub col:  u32,
+}
+
+/// An index of the line breaks in a text, for converting between [`TextSize`] offsets and [`LineCol`] positions.
+///
+/// Lines are separated by `\n`. A `\r` preceding a line break is treated as part of the line it ends.
+///
+/// The index only depends on the positions of line breaks, so it can be built from any representation of the text
+/// that can be split into chunks with [`from_chunks`](LineIndex::from_chunks). In particular, this includes ropes
+/// such as `ropey::Rope`, whose `chunks()` can be indexed directly without first collecting the whole text into a
+/// `String`. The resulting index is identical to the one created by [`new`](LineIndex::new) from the full text.
+///
+/// # Examples
+/// ```
+/// # use cstree::text::{LineCol, LineIndex, TextSize};
+/// let index = LineIndex::new("foo\nbar\n");
+/// assert_eq!(
+///     index.line_col(TextSize::from(5)),
+///     LineCol { line: 1, col: 1 }
+/// );
+/// assert_eq!(
+///     index.offset(LineCol { line: 1, col: 1 }),
+///     Some(TextSize::from(5))
+/// );
+///
+/// let chunked = LineIndex::from_chunks(["fo", "o\nb", "ar\n"]);
+/// assert_eq!(chunked, index);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    /// The offset at which each line starts. The first line always starts at `0`.
+    line_starts: Vec<TextSize>,
+    /// The length of the indexed text.
+    len:         TextSize,
+}
+
+impl LineIndex {
+    /// Creates an index of the line breaks in `text`.
+    #[inline]
+    pub fn new(text: &str) -> Self {
+        Self::from_chunks([text])
+    }
+
+    /// Creates an index of the line breaks in the text formed by concatenating all `chunks`.
+    ///
+    /// ## Panics
+    /// If the total length of the chunks does not fit into a [`TextSize`].
+    pub fn from_chunks<'t>(chunks: impl IntoIterator<Item = &'t str>) -> Self {
+        let mut line_starts = vec![TextSize::from(0)];
+        let mut offset = 0_usize;
+        for chunk in chunks {
+            line_starts.extend(
+                chunk
+                    .bytes()
+                    .enumerate()
+                    .filter(|&(_, byte)| byte == b'\n')
+                    .map(|(index, _)| to_text_size(offset + index + 1)),
+            );
+            offset += chunk.len();
+        }
+        Self {
+            line_starts,
+            len: to_text_size(offset),
+        }
+    }
+
+    /// The length of the indexed text.
+    #[inline]
+    pub fn len(&self) -> TextSize {
+        self.len
+    }
+
+    /// Returns `true` if the indexed text is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0.into()
+    }
+
+    /// The number of lines in the indexed text. Text that ends with a line break ends with an empty line, so this is
+    /// always at least one.
+    #[inline]
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Converts an `offset` into the indexed text into a line/column position.
+    ///
+    /// ## Panics
+    /// If `offset` is past the end of the indexed text.
+    pub fn line_col(&self, offset: TextSize) -> LineCol {
+        assert!(
+            offset <= self.len,
+            "Bad offset: text length {:?} offset {:?}",
+            self.len,
+            offset
+        );
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        LineCol {
+            line: line as u32,
+            col:  (offset - self.line_starts[line]).into(),
+        }
+    }
+
+    /// Converts a line/column `position` into an offset into the indexed text.
+    ///
+    /// Returns `None` if the line does not exist or the column is past the end of the line, not counting the line
+    /// break.
+    pub fn offset(&self, position: LineCol) -> Option<TextSize> {
+        let range = self.line_range(position.line)?;
+        let offset = range.start().checked_add(TextSize::from(position.col))?;
+        (offset <= range.end()).then_some(offset)
+    }
+
+    /// The range of the given zero-based `line` in the indexed text, excluding its line break, or `None` if there is
+    /// no such line.
+    pub fn line_range(&self, line: u32) -> Option<TextRange> {
+        let line = line as usize;
+        let start = *self.line_starts.get(line)?;
+        let end = match self.line_starts.get(line + 1) {
+            // exclude the `\n`
+            Some(&next) => next - TextSize::from(1),
+            None => self.len,
+        };
+        Some(TextRange::new(start, end))
+    }
+}
+
+/// Collects the text in `range` out of the text formed by concatenating all `chunks`.
+///
+/// Like [`LineIndex::from_chunks`], this works with the chunks of a rope, so only the requested part of the text is
+/// copied into the returned `String`. The result is the same as slicing the full text with `range`.
+///
+/// Returns `None` if `range` extends past the end of the text or does not start and end on `char` boundaries.
+///
+/// # Examples
+/// ```
+/// # use cstree::text::{text_in_range, TextRange};
+/// let range = TextRange::new(2.into(), 6.into());
+/// assert_eq!(text_in_range(["fo", "o\nb", "ar\n"], range).as_deref(), Some("o\nba"));
+/// assert_eq!(text_in_range(["foo"], range), None);
+/// ```
+pub fn text_in_range<'t>(chunks: impl IntoIterator<Item = &'t str>, range: TextRange) -> Option<String> {
+    let start = usize::from(range.start());
+    let end = usize::from(range.end());
+    let mut text = String::with_capacity(end - start);
+    let mut offset = 0_usize;
+    for chunk in chunks {
+        if offset >= end {
+            break;
+        }
+        let chunk_end = offset + chunk.len();
+        if chunk_end > start {
+            let from = start.saturating_sub(offset);
+            let to = end.min(chunk_end) - offset;
+            text.push_str(chunk.get(from..to)?);
+        }
+        offset = chunk_end;
+    }
+    (offset >= end).then_some(text)
+}
+
+fn to_text_size(offset: usize) -> TextSize {
+    u32::try_from(offset)
+        .map(TextSize::from)
+        .expect("text is too large to be indexed")
+}
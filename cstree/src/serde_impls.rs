@@ -2,6 +2,7 @@
 
 use crate::{
     build::GreenNodeBuilder,
+    green::DEFAULT_MAX_DEPTH,
     interning::{Resolver, TokenKey},
     syntax::{ResolvedNode, SyntaxNode},
     traversal::WalkEvent,
@@ -9,7 +10,7 @@ use crate::{
     RawSyntaxKind, Syntax,
 };
 use serde::{
-    de::{Error, SeqAccess, Visitor},
+    de::{DeserializeSeed, Error, SeqAccess, Visitor},
     ser::SerializeTuple,
     Deserialize, Serialize,
 };
@@ -56,7 +57,10 @@ macro_rules! gen_serialize {
                     })
                     .unwrap_or(false);)?
 
-                Some(Event::EnterNode($l::into_raw(node.kind()), has_data))
+                match node.green().own_flags() {
+                    0 => Some(Event::EnterNode($l::into_raw(node.kind()), has_data)),
+                    flags => Some(Event::EnterFlaggedNode($l::into_raw(node.kind()), has_data, flags)),
+                }
             }
             WalkEvent::Enter(NodeOrToken::Token(tok)) => Some(Event::Token($l::into_raw(tok.kind()), tok.resolve_text($resolver))),
 
@@ -82,6 +86,9 @@ enum Event<'text> {
     /// If the boolean is true, the next element inside the data list
     /// must be attached to this node.
     EnterNode(RawSyntaxKind, bool),
+    /// Like `EnterNode`, but for a node that has flags set on it, which are given by the third parameter.
+    /// Nodes without flags use `EnterNode`, so that their encoding does not change.
+    EnterFlaggedNode(RawSyntaxKind, bool, u8),
     Token(RawSyntaxKind, &'text str),
     LeaveNode,
 }
@@ -143,11 +150,36 @@ where
     }
 }
 
-impl<'de, S, D> Deserialize<'de> for ResolvedNode<S, D>
-where
-    S: Syntax,
-    D: Deserialize<'de>,
-{
+impl<S: Syntax, D> ResolvedNode<S, D> {
+    /// Deserializes a tree like its [`Deserialize`] implementation, but rejects trees that are nested more deeply than
+    /// `max_depth` instead of [`DEFAULT_MAX_DEPTH`]. The root of the tree is at depth 1.
+    ///
+    /// # Errors
+    /// Returns an error of the `deserializer` if the input is not a valid serialized tree, including if the tree is
+    /// nested too deeply.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::syntax::ResolvedNode;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.start_node(Operation);
+    /// builder.token(Int, "1");
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let tree: ResolvedNode<MySyntax> =
+    ///     SyntaxNode::new_root_with_resolver(tree, cache.unwrap().into_interner().unwrap());
+    /// let json = serde_json::to_string(&tree).unwrap();
+    ///
+    /// let mut deserializer = serde_json::Deserializer::from_str(&json);
+    /// assert!(ResolvedNode::<MySyntax>::deserialize_with_max_depth(&mut deserializer, 1).is_err());
+    /// let mut deserializer = serde_json::Deserializer::from_str(&json);
+    /// let deserialized =
+    ///     ResolvedNode::<MySyntax>::deserialize_with_max_depth(&mut deserializer, 2).unwrap();
+    /// assert_eq!(deserialized.text(), "1");
+    /// ```
     // Deserialization is done by walking down the deserialized event stream,
     // which is the first element inside the tuple. The events
     // are then passed to a `GreenNodeBuilder` which will do all
@@ -158,66 +190,18 @@ where
     // we walk down the nodes, check if the bool at `data_list[idx]` is true,
     // and if so, pop the first element of the data list and attach the data
     // to the current node.
-    fn deserialize<De>(deserializer: De) -> Result<Self, De::Error>
+    pub fn deserialize_with_max_depth<'de, De>(deserializer: De, max_depth: usize) -> Result<Self, De::Error>
     where
         De: serde::Deserializer<'de>,
+        D: Deserialize<'de>,
     {
-        struct EventVisitor<S: Syntax, D: 'static> {
-            _marker: PhantomData<fn() -> ResolvedNode<S, D>>,
-        }
-
-        impl<'de, S, D> Visitor<'de> for EventVisitor<S, D>
-        where
-            S: Syntax,
-            D: Deserialize<'de>,
-        {
-            type Value = (ResolvedNode<S, D>, VecDeque<bool>);
-
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("a list of tree events")
-            }
-
-            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
-            where
-                A: SeqAccess<'de>,
-            {
-                let mut builder: GreenNodeBuilder<S> = GreenNodeBuilder::new();
-                let mut data_indices = VecDeque::new();
-
-                while let Some(next) = seq.next_element::<Event<'_>>()? {
-                    match next {
-                        Event::EnterNode(kind, has_data) => {
-                            builder.start_node(S::from_raw(kind));
-                            data_indices.push_back(has_data);
-                        }
-                        Event::Token(kind, text) => builder.token(S::from_raw(kind), text),
-                        Event::LeaveNode => builder.finish_node(),
-                    }
-                }
-
-                let (tree, cache) = builder.finish();
-                let tree = ResolvedNode::new_root_with_resolver(tree, cache.unwrap().into_interner().unwrap());
-                Ok((tree, data_indices))
-            }
-        }
-
-        struct ProcessedEvents<S: Syntax, D: 'static>(ResolvedNode<S, D>, VecDeque<bool>);
-        impl<'de, S, D> Deserialize<'de> for ProcessedEvents<S, D>
-        where
-            S: Syntax,
-            D: Deserialize<'de>,
-        {
-            fn deserialize<DE>(deserializer: DE) -> Result<Self, DE::Error>
-            where
-                DE: serde::Deserializer<'de>,
-            {
-                let (tree, ids) = deserializer.deserialize_seq(EventVisitor { _marker: PhantomData })?;
-                Ok(Self(tree, ids))
-            }
-        }
-
-        let (ProcessedEvents(tree, data_indices), mut data) =
-            <(ProcessedEvents<S, D>, VecDeque<D>)>::deserialize(deserializer)?;
+        let (tree, data_indices, mut data) = deserializer.deserialize_tuple(
+            2,
+            TreeVisitor {
+                max_depth,
+                _marker: PhantomData,
+            },
+        )?;
 
         tree.descendants().zip(data_indices).try_for_each(|(node, has_data)| {
             if has_data {
@@ -239,6 +223,122 @@ where
     }
 }
 
+impl<'de, S, D> Deserialize<'de> for ResolvedNode<S, D>
+where
+    S: Syntax,
+    D: Deserialize<'de>,
+{
+    fn deserialize<De>(deserializer: De) -> Result<Self, De::Error>
+    where
+        De: serde::Deserializer<'de>,
+    {
+        Self::deserialize_with_max_depth(deserializer, DEFAULT_MAX_DEPTH)
+    }
+}
+
+/// Visits the tuple of events and data that a tree is serialized as.
+struct TreeVisitor<S: Syntax, D: 'static> {
+    max_depth: usize,
+    _marker:   PhantomData<fn() -> ResolvedNode<S, D>>,
+}
+
+impl<'de, S, D> Visitor<'de> for TreeVisitor<S, D>
+where
+    S: Syntax,
+    D: Deserialize<'de>,
+{
+    type Value = (ResolvedNode<S, D>, VecDeque<bool>, VecDeque<D>);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a tuple of tree events and node data")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let events = EventVisitor {
+            max_depth: self.max_depth,
+            _marker:   PhantomData,
+        };
+        let (tree, data_indices) = seq
+            .next_element_seed(events)?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let data = seq.next_element()?.ok_or_else(|| A::Error::invalid_length(1, &self))?;
+        Ok((tree, data_indices, data))
+    }
+}
+
+/// Builds a tree from its serialized events.
+struct EventVisitor<S: Syntax, D: 'static> {
+    max_depth: usize,
+    _marker:   PhantomData<fn() -> ResolvedNode<S, D>>,
+}
+
+impl<'de, S: Syntax, D> DeserializeSeed<'de> for EventVisitor<S, D> {
+    type Value = (ResolvedNode<S, D>, VecDeque<bool>);
+
+    fn deserialize<De>(self, deserializer: De) -> Result<Self::Value, De::Error>
+    where
+        De: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, S: Syntax, D> Visitor<'de> for EventVisitor<S, D> {
+    type Value = (ResolvedNode<S, D>, VecDeque<bool>);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of tree events")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut builder: GreenNodeBuilder<S> = GreenNodeBuilder::new();
+        let mut data_indices = VecDeque::new();
+        // the number of nodes that have been entered, but not left yet
+        let mut depth = 0;
+
+        while let Some(next) = seq.next_element::<Event<'_>>()? {
+            // events outside of the root node would make the builder panic
+            if depth == 0 && !data_indices.is_empty() {
+                return Err(A::Error::custom("invalid serialized tree"));
+            }
+            match next {
+                Event::EnterNode(kind, has_data) | Event::EnterFlaggedNode(kind, has_data, _) => {
+                    if depth == self.max_depth {
+                        return Err(A::Error::custom("serialized tree is nested too deeply"));
+                    }
+                    depth += 1;
+                    builder.start_node(S::from_raw(kind));
+                    if let Event::EnterFlaggedNode(_, _, flags) = next {
+                        builder.set_flags(flags);
+                    }
+                    data_indices.push_back(has_data);
+                }
+                Event::Token(..) | Event::LeaveNode if depth == 0 => {
+                    return Err(A::Error::custom("invalid serialized tree"));
+                }
+                Event::Token(kind, text) => builder.token(S::from_raw(kind), text),
+                Event::LeaveNode => {
+                    depth -= 1;
+                    builder.finish_node();
+                }
+            }
+        }
+        if depth != 0 || data_indices.is_empty() {
+            return Err(A::Error::custom("invalid serialized tree"));
+        }
+
+        let (tree, cache) = builder.finish();
+        let tree = ResolvedNode::new_root_with_resolver(tree, cache.unwrap().into_interner().unwrap());
+        Ok((tree, data_indices))
+    }
+}
+
 impl Serialize for RawSyntaxKind {
     fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
     where
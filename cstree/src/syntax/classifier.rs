@@ -0,0 +1,113 @@
+//! Classification of syntax kinds that is registered once per tree.
+
+use std::sync::Arc as StdArc;
+
+use crate::{text::TextRange, Syntax};
+
+use super::{ResolvedNode, SyntaxNode, SyntaxToken};
+
+/// Classifies the kinds of a tree, for example to tell which tokens are trivia.
+///
+/// A classifier can be registered with a tree using [`SyntaxNode::with_classifier`], so that it does not have to be
+/// passed to every method that needs it. Any `Fn(S) -> bool` that can be shared between threads is a classifier
+/// that considers a kind to be trivia if the function returns `true` for it.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// # use cstree::text::TextRange;
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.token(Whitespace, " ");
+/// builder.token(Int, "1");
+/// builder.token(Whitespace, "\n");
+/// builder.finish_node();
+/// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+///
+/// let root = root.with_classifier(|kind| kind == Whitespace);
+/// let int = root.non_trivia_tokens().next().unwrap();
+/// assert_eq!(int.kind(), Int);
+/// assert!(!int.is_trivia());
+/// assert_eq!(
+///     root.trimmed_range_by_classifier(),
+///     TextRange::new(1.into(), 2.into())
+/// );
+/// ```
+pub trait Classifier<S: Syntax>: Send + Sync {
+    /// Returns `true` if elements of the given `kind` are trivia, such as whitespace or comments.
+    fn is_trivia(&self, kind: S) -> bool;
+}
+
+impl<S, F> Classifier<S> for F
+where
+    S: Syntax,
+    F: Fn(S) -> bool + Send + Sync,
+{
+    #[inline]
+    fn is_trivia(&self, kind: S) -> bool {
+        self(kind)
+    }
+}
+
+impl<S: Syntax, D> SyntaxNode<S, D> {
+    /// Registers `classifier` with the tree this node belongs to, replacing any classifier that was registered
+    /// before.
+    ///
+    /// The classifier is shared by all nodes and tokens of the tree, including ones that already exist, and is used
+    /// by methods such as [`SyntaxToken::is_trivia`] and [`non_trivia_tokens`](SyntaxNode::non_trivia_tokens).
+    pub fn with_classifier(self, classifier: impl Classifier<S> + 'static) -> Self {
+        *self.classifier_slot().write() = Some(StdArc::new(classifier));
+        self
+    }
+
+    /// The [`Classifier`] registered with the tree this node belongs to, if any.
+    #[inline]
+    pub fn classifier(&self) -> Option<StdArc<dyn Classifier<S>>> {
+        self.classifier_slot().read().clone()
+    }
+
+    /// Returns an iterator over all tokens in the subtree of this node that are not trivia according to the
+    /// [`Classifier`] of the tree, in document order. If the tree has no classifier, all tokens are returned.
+    pub fn non_trivia_tokens(&self) -> impl Iterator<Item = &SyntaxToken<S, D>> {
+        let classifier = self.classifier();
+        self.tokens().filter(move |token| match &classifier {
+            Some(classifier) => !classifier.is_trivia(token.kind()),
+            None => true,
+        })
+    }
+
+    /// The range of this node without leading and trailing trivia, according to the [`Classifier`] of the tree.
+    ///
+    /// This is the same as [`trimmed_range`](SyntaxNode::trimmed_range), using the registered classifier to
+    /// determine which tokens are trivia. If the tree has no classifier, this is the range of the node.
+    pub fn trimmed_range_by_classifier(&self) -> TextRange {
+        match self.classifier() {
+            Some(classifier) => self.trimmed_range(|kind| classifier.is_trivia(kind)),
+            None => self.text_range(),
+        }
+    }
+}
+
+impl<S: Syntax, D> ResolvedNode<S, D> {
+    /// Registers `classifier` with the tree this node belongs to, replacing any classifier that was registered
+    /// before.
+    ///
+    /// See [`SyntaxNode::with_classifier`].
+    #[inline]
+    pub fn with_classifier(self, classifier: impl Classifier<S> + 'static) -> Self {
+        Self {
+            syntax: self.syntax.with_classifier(classifier),
+        }
+    }
+}
+
+impl<S: Syntax, D> SyntaxToken<S, D> {
+    /// Returns `true` if this token is trivia according to the [`Classifier`] of the tree it belongs to. If the tree
+    /// has no classifier, no token is trivia.
+    #[inline]
+    pub fn is_trivia(&self) -> bool {
+        self.parent()
+            .classifier()
+            .map_or(false, |classifier| classifier.is_trivia(self.kind()))
+    }
+}
@@ -0,0 +1,681 @@
+//! Functional edits of syntax trees.
+//!
+//! Syntax trees cannot be mutated in place. Instead, the methods in this module compute the green tree that results
+//! from applying an edit, re-using all parts of the original tree that are not affected by it. Create a new
+//! [`SyntaxNode`] from the returned [`GreenNode`] to continue working with the edited tree.
+
+use std::{error::Error, fmt};
+
+use triomphe::Arc;
+
+use crate::{
+    build::NodeCache,
+    green::{GreenElement, GreenElementRef, GreenNode, GreenToken},
+    interning::{Interner, Resolver, TokenKey},
+    text::{TextRange, TextSize},
+    util::NodeOrToken,
+    Syntax,
+};
+
+use super::SyntaxNode;
+
+/// An error that occurs when applying an edit to a syntax tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// Two of the given edits overlap, so the order in which they should be applied is ambiguous.
+    Overlap {
+        /// The range of the first of the two overlapping edits.
+        first:  TextRange,
+        /// The range of the second of the two overlapping edits.
+        second: TextRange,
+    },
+    /// An edit is not contained in the range of the edited node, or there is no token to which its text could be
+    /// attached.
+    OutOfBounds(TextRange),
+    /// An edit would change the text of a token whose kind has [static text](Syntax::static_text).
+    StaticText(TextRange),
+    /// The range of an edit does not start and end at the boundaries of children of a common parent node, but would
+    /// have to split a token.
+    Misaligned(TextRange),
+    /// The range of an edit starts or ends inside of a multi-byte character in the text of a token.
+    NotCharBoundary(TextRange),
+    /// The text of a token touched by an edit cannot be resolved with the interner of the given cache.
+    UnresolvedText(TextRange),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::Overlap { first, second } => write!(f, "edits at {first:?} and {second:?} overlap"),
+            EditError::OutOfBounds(range) => write!(f, "edit at {range:?} is out of bounds"),
+            EditError::StaticText(range) => write!(f, "edit at {range:?} would change the text of a static token"),
+            EditError::Misaligned(range) => write!(f, "edit at {range:?} is not aligned with element boundaries"),
+            EditError::NotCharBoundary(range) => write!(f, "edit at {range:?} is not on a character boundary"),
+            EditError::UnresolvedText(range) => {
+                write!(f, "the text of a token touched by the edit at {range:?} is unknown")
+            }
+        }
+    }
+}
+
+impl Error for EditError {}
+
+impl<S: Syntax, D> SyntaxNode<S, D> {
+    /// Applies all text `edits` to the subtree of this node at once and returns the green tree of the whole edited
+    /// tree, equal to the tree this node belongs to except for the edited tokens.
+    ///
+    /// Every edit replaces the text in its range (in the coordinates of the current tree) by the given text. Edits
+    /// may be given in any order, but must not overlap. Insertions are applied before edits that start at the same
+    /// offset, and multiple insertions at the same offset in the order they are given. Edits are applied to the text
+    /// of the tokens they touch: the replacement text of an edit is inserted into the token in which the edit starts,
+    /// and the text of all other tokens in its range is removed. Nodes and tokens that are not touched by any edit are
+    /// re-used as-is.
+    ///
+    /// The `cache` must use the same interner as the tree, since it is used to resolve the original text of the
+    /// edited tokens as well as to intern their new text.
+    ///
+    /// Data attached to nodes of the current tree is not carried over to trees created from the returned green node.
+    /// Use [`transfer_data`](SyntaxNode::transfer_data) to move it to the corresponding nodes of the edited tree.
+    ///
+    /// # Errors
+    /// Returns [`EditError::Overlap`] if two edits overlap and [`EditError::OutOfBounds`] if an edit is not
+    /// contained in this node's text range. Edits that would change the text of a token with
+    /// [static text](Syntax::static_text) result in [`EditError::StaticText`], and edits that start or end inside of
+    /// a multi-byte character in [`EditError::NotCharBoundary`]. If the interner of `cache` cannot resolve the text
+    /// of a token touched by an edit, [`EditError::UnresolvedText`] is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::build::NodeCache;
+    /// # use cstree::text::TextRange;
+    /// let mut cache = NodeCache::new();
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::with_cache(&mut cache);
+    /// builder.start_node(Root);
+    /// builder.token(Identifier, "foo");
+    /// builder.token(Whitespace, " ");
+    /// builder.token(Identifier, "bar");
+    /// builder.finish_node();
+    /// let (green, _) = builder.finish();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(green);
+    ///
+    /// let edits = [
+    ///     (TextRange::new(0.into(), 3.into()), "x"),
+    ///     (TextRange::new(4.into(), 7.into()), "baz"),
+    /// ];
+    /// let edited = root.apply_edits(&edits, &mut cache).unwrap();
+    /// let edited: SyntaxNode<MySyntax> = SyntaxNode::new_root(edited);
+    /// assert_eq!(edited.resolve_text(cache.interner()), "x baz");
+    /// ```
+    pub fn apply_edits<I>(
+        &self,
+        edits: &[(TextRange, &str)],
+        cache: &mut NodeCache<'_, I>,
+    ) -> Result<GreenNode, EditError>
+    where
+        I: Interner<TokenKey>,
+    {
+        let range = self.text_range();
+        let mut edits = edits.to_vec();
+        // insertions sort before replacements that start at the same offset, so the result does not depend on the
+        // order in which they are given
+        edits.sort_by_key(|(range, _)| (range.start(), range.end()));
+        for (edit, _) in &edits {
+            if !range.contains_range(*edit) {
+                return Err(EditError::OutOfBounds(*edit));
+            }
+        }
+        for window in edits.windows(2) {
+            let (first, second) = (window[0].0, window[1].0);
+            if first.end() > second.start() {
+                return Err(EditError::Overlap { first, second });
+            }
+        }
+
+        if range.is_empty() {
+            // there is no token that could hold the text of an insertion
+            if let Some((edit, _)) = edits.iter().find(|(_, text)| !text.is_empty()) {
+                return Err(EditError::OutOfBounds(*edit));
+            }
+        }
+
+        let mut editor = Editor {
+            end: range.end(),
+            cache,
+        };
+        let green = editor.edit_node::<S>(self.green(), range.start(), &edits)?;
+        Ok(self.replace_with(green))
+    }
+
+    /// Replaces the direct child of this node that starts at `offset` with `replacement` and returns the green tree of
+    /// the whole edited tree, or `None` if no child of this node starts exactly at `offset`.
+    ///
+    /// This is useful if the element to replace was located by its position in the text. If multiple children start
+    /// at `offset` because some of them are empty, the first one is replaced. The new version of this node is
+    /// deduplicated using `cache`, while the rest of the tree is rebuilt as in
+    /// [`replace_with`](SyntaxNode::replace_with).
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::build::NodeCache;
+    /// let mut cache = NodeCache::new();
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::with_cache(&mut cache);
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// let (green, _) = builder.finish();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(green);
+    ///
+    /// let three = cache.token(Int, "3");
+    /// let edited = root
+    ///     .replace_element_at(2.into(), three.into(), &mut cache)
+    ///     .unwrap();
+    /// let edited: SyntaxNode<MySyntax> = SyntaxNode::new_root(edited);
+    /// assert_eq!(edited.resolve_text(cache.interner()), "1+3");
+    /// assert!(root
+    ///     .replace_element_at(3.into(), cache.token(Int, "4").into(), &mut cache)
+    ///     .is_none());
+    /// ```
+    pub fn replace_element_at<I>(
+        &self,
+        offset: TextSize,
+        replacement: GreenElement,
+        cache: &mut NodeCache<'_, I>,
+    ) -> Option<GreenNode>
+    where
+        I: Interner<TokenKey>,
+    {
+        let mut child_offset = self.text_range().start();
+        let mut index = None;
+        for (i, child) in self.green().children().enumerate() {
+            if child_offset == offset {
+                index = Some(i);
+                break;
+            }
+            child_offset += child.text_len();
+            if child_offset > offset {
+                return None;
+            }
+        }
+        let index = index?;
+
+        let mut replacement = Some(replacement);
+        let children = self.green().children().enumerate().map(|(i, child)| {
+            if i == index {
+                replacement.take().unwrap()
+            } else {
+                child.cloned()
+            }
+        });
+        let green = cache.node_with_flags(self.syntax_kind(), self.green().own_flags(), children.collect());
+        Some(self.replace_with(green))
+    }
+
+    /// Replaces everything in `range`, which is given in the coordinates of the current tree, by the `replacement`
+    /// subtree and returns the green tree of the whole edited tree.
+    ///
+    /// The replaced elements are the consecutive children of a single node in the subtree of this node that exactly
+    /// cover `range`, choosing the node closest to this node for which such children exist. If `range` is empty,
+    /// `replacement` is inserted before the first child that starts at `range`, or after the last child if `range`
+    /// is at the end. The new version of the parent of the replaced children is deduplicated using `cache`, while
+    /// the rest of the tree is rebuilt as in [`replace_with`](SyntaxNode::replace_with). All elements after `range`
+    /// are shifted by the difference between the lengths of `replacement` and `range`.
+    ///
+    /// # Errors
+    /// Returns [`EditError::OutOfBounds`] if `range` is not contained in this node's text range, and
+    /// [`EditError::Misaligned`] if it does not start and end at the boundaries of children of a common node, for
+    /// example because it starts or ends within a token.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::build::NodeCache;
+    /// # use cstree::syntax::EditError;
+    /// # use cstree::text::TextRange;
+    /// let mut cache = NodeCache::new();
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::with_cache(&mut cache);
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// let (green, _) = builder.finish();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(green);
+    ///
+    /// let one = cache.token(Int, "1");
+    /// let two = cache.token(Float, "2.5");
+    /// let product = cache.node(Operation, vec![one.into(), two.into()]);
+    /// let edited = root
+    ///     .replace_range(TextRange::new(2.into(), 3.into()), product, &mut cache)
+    ///     .unwrap();
+    /// let edited: SyntaxNode<MySyntax> = SyntaxNode::new_root(edited);
+    /// assert_eq!(edited.resolve_text(cache.interner()), "1+12.5");
+    /// assert_eq!(edited.last_child().unwrap().kind(), Operation);
+    ///
+    /// // this would split the `Float` token
+    /// let misaligned = TextRange::new(3.into(), 4.into());
+    /// let err = edited.replace_range(misaligned, cache.node(Root, Vec::new()), &mut cache);
+    /// assert_eq!(err, Err(EditError::Misaligned(misaligned)));
+    /// ```
+    pub fn replace_range<I>(
+        &self,
+        range: TextRange,
+        replacement: GreenNode,
+        cache: &mut NodeCache<'_, I>,
+    ) -> Result<GreenNode, EditError>
+    where
+        I: Interner<TokenKey>,
+    {
+        if !self.text_range().contains_range(range) {
+            return Err(EditError::OutOfBounds(range));
+        }
+        let mut node = self;
+        let (node, replaced) = loop {
+            let mut offset = node.text_range().start();
+            let mut start = None;
+            let mut end = None;
+            let mut containing = None;
+            for (i, child) in node.green().children().enumerate() {
+                let child_range = TextRange::at(offset, child.text_len());
+                if start.is_none() && child_range.start() == range.start() {
+                    start = Some(i);
+                }
+                if child_range.end() == range.end() {
+                    end = Some(i + 1);
+                }
+                if containing.is_none() && child.as_node().is_some() && child_range.contains_range(range) {
+                    containing = Some((child, i, child_range.start()));
+                }
+                offset = child_range.end();
+            }
+            if range.is_empty() {
+                let len = node.green().children().len();
+                let at = start.or_else(|| (range.start() == node.text_range().end()).then_some(len));
+                if let Some(at) = at {
+                    break (node, at..at);
+                }
+            } else if let (Some(start), Some(end)) = (start, end) {
+                if start < end {
+                    break (node, start..end);
+                }
+            }
+            match containing {
+                // NOTE: the child at `index` was found to be a node
+                Some((child, index, offset)) => {
+                    node = node.get_or_add_element(child, index, offset).into_node().unwrap()
+                }
+                None => return Err(EditError::Misaligned(range)),
+            }
+        };
+
+        let children: Vec<GreenElement> = node
+            .green()
+            .children()
+            .take(replaced.start)
+            .map(|child| child.cloned())
+            .chain(Some(replacement.into()))
+            .chain(node.green().children().skip(replaced.end).map(|child| child.cloned()))
+            .collect();
+        let green = cache.node_with_flags(node.syntax_kind(), node.green().own_flags(), children);
+        Ok(node.replace_with(green))
+    }
+
+    /// Returns the green tree of the whole tree this node belongs to, with the kind of this node changed to `kind`.
+    ///
+    /// The children of this node are shared with the new node, and since the text of the tree does not change, the
+    /// ranges of all nodes and tokens are the same in the new tree. Only the nodes on the path from this node to the
+    /// root are rebuilt.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::build::NodeCache;
+    /// let mut cache = NodeCache::new();
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::with_cache(&mut cache);
+    /// builder.start_node(Root);
+    /// builder.start_node(Operation);
+    /// builder.token(Identifier, "x");
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// let (green, _) = builder.finish();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(green);
+    ///
+    /// let operation = root.first_child().unwrap();
+    /// let edited: SyntaxNode<MySyntax> =
+    ///     SyntaxNode::new_root(operation.with_kind(Identifier, &mut cache));
+    /// assert_eq!(edited.first_child().unwrap().kind(), Identifier);
+    /// assert_eq!(edited.resolve_text(cache.interner()), "x");
+    /// ```
+    pub fn with_kind<I>(&self, kind: S, cache: &mut NodeCache<'_, I>) -> GreenNode
+    where
+        I: Interner<TokenKey>,
+    {
+        let green = cache.node_with_flags(
+            S::into_raw(kind),
+            self.green().own_flags(),
+            self.green().children().map(|child| child.cloned()).collect(),
+        );
+        let Some((parent, Some(index))) = self.ancestors_with_index().nth(1) else {
+            // `self` is the root
+            return green;
+        };
+        let mut green = Some(green);
+        let children = parent.green().children().enumerate().map(|(i, child)| {
+            if i == index {
+                green.take().unwrap().into()
+            } else {
+                child.cloned()
+            }
+        });
+        let new_parent = cache.node_with_flags(parent.syntax_kind(), parent.green().own_flags(), children.collect());
+        parent.replace_with(new_parent)
+    }
+
+    /// Carries the data attached to nodes in the subtree of this node over to the corresponding nodes in the subtree
+    /// of `new`, which is usually the root of a tree created from the result of an edit such as
+    /// [`apply_edits`](SyntaxNode::apply_edits) or [`replace_with`](SyntaxNode::replace_with).
+    ///
+    /// Both trees are walked in parallel, matching children by their index. Nodes are considered to correspond if
+    /// their parents correspond and they have the same kind. Children of nodes with a different number of children
+    /// are not matched, since their positions are ambiguous.
+    ///
+    /// Subtrees that were not changed by the edit (i.e. whose green nodes are equal) keep their data: it is shared
+    /// with the corresponding new nodes as-is. For all other corresponding nodes that have data, `data_map` is
+    /// called with the original node and its data and returns the data for the new node, if any. Return the given
+    /// data to keep it, or new data to replace it. Data of nodes without a corresponding node is dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::build::NodeCache;
+    /// # use cstree::text::TextRange;
+    /// let mut cache = NodeCache::new();
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::with_cache(&mut cache);
+    /// builder.start_node(Root);
+    /// builder.start_node(Operation);
+    /// builder.token(Int, "1");
+    /// builder.finish_node();
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// let (green, _) = builder.finish();
+    /// let root: SyntaxNode<MySyntax, &str> = SyntaxNode::new_root(green);
+    /// root.set_data("root");
+    /// root.first_child().unwrap().set_data("operation");
+    ///
+    /// let edited = root
+    ///     .apply_edits(&[(TextRange::new(1.into(), 2.into()), "3")], &mut cache)
+    ///     .unwrap();
+    /// let edited: SyntaxNode<MySyntax, &str> = SyntaxNode::new_root(edited);
+    /// root.transfer_data(&edited, |_old, _data| None);
+    /// assert_eq!(
+    ///     *edited.first_child().unwrap().get_data().unwrap(),
+    ///     "operation"
+    /// );
+    /// assert!(edited.get_data().is_none());
+    /// ```
+    pub fn transfer_data<F>(&self, new: &SyntaxNode<S, D>, mut data_map: F)
+    where
+        F: FnMut(&SyntaxNode<S, D>, Arc<D>) -> Option<Arc<D>>,
+    {
+        let mut stack = vec![(self, new)];
+        while let Some((old, new)) = stack.pop() {
+            if old.syntax_kind() != new.syntax_kind() {
+                continue;
+            }
+            let unchanged = old.green() == new.green();
+            if let Some(data) = old.get_data() {
+                let data = if unchanged { Some(data) } else { data_map(old, data) };
+                match data {
+                    Some(data) => new.set_shared_data(data),
+                    None => new.clear_data(),
+                }
+            }
+            if old.arity_with_tokens() == new.arity_with_tokens() {
+                let children = old.children_with_tokens().zip(new.children_with_tokens());
+                stack.extend(children.filter_map(|(old, new)| Some((old.into_node()?, new.into_node()?))));
+            }
+        }
+    }
+
+    /// Computes text edits that turn the text of this node into the text of `new`, in the coordinates of the tree
+    /// this node belongs to.
+    ///
+    /// The edits are derived from the structure of both trees: children that are equal at the start and the end of
+    /// two corresponding nodes are skipped, so shared subtrees are never looked at in detail. If the remaining
+    /// children of both nodes have the same count, they are compared pairwise, otherwise they are replaced as a
+    /// whole. Tokens that differ are replaced entirely. The edits are sorted by their range, do not overlap and can be
+    /// passed to [`apply_edits`](SyntaxNode::apply_edits).
+    ///
+    /// Both trees must have been built with the same interner, since tokens are compared by their interned text.
+    /// The `resolver` is used to obtain the text of the edits.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::build::NodeCache;
+    /// # use cstree::text::TextRange;
+    /// let mut cache = NodeCache::new();
+    /// let mut build = |text: &str| {
+    ///     let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::with_cache(&mut cache);
+    ///     builder.start_node(Root);
+    ///     builder.token(Int, "1");
+    ///     builder.static_token(Plus);
+    ///     builder.token(Int, text);
+    ///     builder.finish_node();
+    ///     let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    ///     root
+    /// };
+    /// let old = build("2");
+    /// let new = build("42");
+    ///
+    /// let edits = old.text_edits(&new, cache.interner());
+    /// assert_eq!(
+    ///     edits,
+    ///     [(TextRange::new(2.into(), 3.into()), "42".to_string())]
+    /// );
+    /// ```
+    pub fn text_edits<D2, R>(&self, new: &SyntaxNode<S, D2>, resolver: &R) -> Vec<(TextRange, String)>
+    where
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        let mut edits = Vec::new();
+        diff_nodes::<S, R>(
+            self.green(),
+            new.green(),
+            self.text_range().start(),
+            resolver,
+            &mut edits,
+        );
+        edits
+    }
+}
+
+fn diff_nodes<S: Syntax, R: Resolver<TokenKey> + ?Sized>(
+    old: &GreenNode,
+    new: &GreenNode,
+    offset: TextSize,
+    resolver: &R,
+    edits: &mut Vec<(TextRange, String)>,
+) {
+    if old.ptr_eq(new) {
+        return;
+    }
+    if old.kind() != new.kind() {
+        edits.push((
+            TextRange::at(offset, old.text_len()),
+            green_text::<S, R>(new.into(), resolver),
+        ));
+        return;
+    }
+
+    let old_children: Vec<_> = old.children().collect();
+    let new_children: Vec<_> = new.children().collect();
+    let prefix = old_children
+        .iter()
+        .zip(&new_children)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old_children[prefix..]
+        .iter()
+        .rev()
+        .zip(new_children[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let old_changed = &old_children[prefix..old_children.len() - suffix];
+    let new_changed = &new_children[prefix..new_children.len() - suffix];
+
+    let mut offset = offset
+        + old_children[..prefix]
+            .iter()
+            .map(|child| child.text_len())
+            .sum::<TextSize>();
+    if old_changed.len() == new_changed.len() {
+        for (&old, &new) in old_changed.iter().zip(new_changed) {
+            match (old, new) {
+                (NodeOrToken::Node(old), NodeOrToken::Node(new)) => {
+                    diff_nodes::<S, R>(old, new, offset, resolver, edits)
+                }
+                _ => {
+                    let old_text = green_text::<S, R>(old, resolver);
+                    let new_text = green_text::<S, R>(new, resolver);
+                    if old_text != new_text {
+                        edits.push((TextRange::at(offset, old.text_len()), new_text));
+                    }
+                }
+            }
+            offset += old.text_len();
+        }
+    } else {
+        let len = old_changed.iter().map(|child| child.text_len()).sum();
+        let mut text = String::new();
+        for &child in new_changed {
+            text.push_str(&green_text::<S, R>(child, resolver));
+        }
+        if len != TextSize::from(0) || !text.is_empty() {
+            edits.push((TextRange::at(offset, len), text));
+        }
+    }
+}
+
+/// Collects the text of a green element.
+fn green_text<S: Syntax, R: Resolver<TokenKey> + ?Sized>(element: GreenElementRef<'_>, resolver: &R) -> String {
+    let mut text = String::new();
+    let mut stack = vec![element];
+    while let Some(element) = stack.pop() {
+        match element {
+            NodeOrToken::Node(node) => stack.extend(node.children().rev()),
+            NodeOrToken::Token(token) => {
+                let static_text = S::static_text(S::from_raw(token.kind()));
+                // NOTE: tokens have either static or interned text
+                text.push_str(static_text.or_else(|| token.text(resolver)).unwrap());
+            }
+        }
+    }
+    text
+}
+
+struct Editor<'c, 'i, I> {
+    /// End of the edited node, at which the last non-empty token also takes insertions.
+    end:   TextSize,
+    cache: &'c mut NodeCache<'i, I>,
+}
+
+impl<I> Editor<'_, '_, I>
+where
+    I: Interner<TokenKey>,
+{
+    /// Whether an edit starting at `offset` inserts its text into the element spanning `range`.
+    fn takes_insertion(&self, range: TextRange, offset: TextSize) -> bool {
+        range.contains(offset) || (offset == self.end && range.end() == self.end && !range.is_empty())
+    }
+
+    fn touches(&self, range: TextRange, edit: TextRange) -> bool {
+        (edit.start() < range.end() && range.start() < edit.end()) || self.takes_insertion(range, edit.start())
+    }
+
+    /// Narrows `edits` down to the contiguous run of edits that touch `range`.
+    fn relevant<'e>(&self, range: TextRange, edits: &'e [(TextRange, &'e str)]) -> &'e [(TextRange, &'e str)] {
+        let first = edits.iter().position(|(edit, _)| self.touches(range, *edit));
+        match first {
+            Some(first) => {
+                let len = edits[first..]
+                    .iter()
+                    .take_while(|(edit, _)| self.touches(range, *edit))
+                    .count();
+                &edits[first..first + len]
+            }
+            None => &[],
+        }
+    }
+
+    fn edit_node<S: Syntax>(
+        &mut self,
+        node: &GreenNode,
+        offset: TextSize,
+        edits: &[(TextRange, &str)],
+    ) -> Result<GreenNode, EditError> {
+        let mut children = Vec::with_capacity(node.children().len());
+        let mut offset = offset;
+        for child in node.children() {
+            let range = TextRange::at(offset, child.text_len());
+            let child_edits = self.relevant(range, edits);
+            let child = if child_edits.is_empty() {
+                child.cloned()
+            } else {
+                match child {
+                    NodeOrToken::Node(node) => self.edit_node::<S>(node, offset, child_edits)?.into(),
+                    NodeOrToken::Token(token) => self.edit_token::<S>(token, range, child_edits)?.into(),
+                }
+            };
+            children.push(child);
+            offset = range.end();
+        }
+        Ok(self.cache.node_with_flags(node.kind(), node.own_flags(), children))
+    }
+
+    fn edit_token<S: Syntax>(
+        &mut self,
+        token: &GreenToken,
+        range: TextRange,
+        edits: &[(TextRange, &str)],
+    ) -> Result<GreenToken, EditError> {
+        let kind = S::from_raw(token.kind());
+        let original = match S::static_text(kind) {
+            Some(text) => text,
+            None => token
+                .text_key()
+                .and_then(|key| self.cache.interner().try_resolve(key))
+                .ok_or(EditError::UnresolvedText(edits[0].0))?,
+        };
+        let mut text = String::with_capacity(original.len());
+        let mut cursor = range.start();
+        for (edit, replacement) in edits {
+            let delete_start = edit.start().max(range.start());
+            let delete_end = edit.end().min(range.end());
+            for boundary in [delete_start, delete_end] {
+                if !original.is_char_boundary((boundary - range.start()).into()) {
+                    return Err(EditError::NotCharBoundary(*edit));
+                }
+            }
+            if delete_start > cursor {
+                text.push_str(&original[TextRange::new(cursor, delete_start) - range.start()]);
+            }
+            if self.takes_insertion(range, edit.start()) {
+                text.push_str(replacement);
+            }
+            cursor = cursor.max(delete_end);
+        }
+        text.push_str(&original[TextRange::new(cursor, range.end()) - range.start()]);
+
+        if text == original {
+            return Ok(token.clone());
+        }
+        if S::static_text(kind).is_some() {
+            return Err(EditError::StaticText(edits[0].0));
+        }
+        Ok(self.cache.token(kind, &text))
+    }
+}
@@ -25,6 +25,78 @@ impl<S: Syntax, D> From<SyntaxToken<S, D>> for SyntaxElement<S, D> {
     }
 }
 
+impl<S: Syntax, D> TryFrom<SyntaxElement<S, D>> for SyntaxNode<S, D> {
+    type Error = ElementConversionError<SyntaxElement<S, D>>;
+
+    #[inline]
+    fn try_from(element: SyntaxElement<S, D>) -> Result<Self, Self::Error> {
+        match element {
+            NodeOrToken::Node(node) => Ok(node),
+            element => Err(ElementConversionError(element)),
+        }
+    }
+}
+
+impl<S: Syntax, D> TryFrom<SyntaxElement<S, D>> for SyntaxToken<S, D> {
+    type Error = ElementConversionError<SyntaxElement<S, D>>;
+
+    #[inline]
+    fn try_from(element: SyntaxElement<S, D>) -> Result<Self, Self::Error> {
+        match element {
+            NodeOrToken::Token(token) => Ok(token),
+            element => Err(ElementConversionError(element)),
+        }
+    }
+}
+
+/// The error returned when converting an element into a node or a token fails because the element is the other one of
+/// the two. The original element can be recovered with [`into_element`](ElementConversionError::into_element).
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::syntax::SyntaxElement;
+///
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.token(Int, "1");
+/// builder.finish_node();
+/// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+///
+/// let element: SyntaxElement<MySyntax> = root.first_token().unwrap().clone().into();
+/// let err = SyntaxNode::try_from(element).unwrap_err();
+/// assert_eq!(err.to_string(), "expected a node, found a token");
+/// let token: SyntaxToken<MySyntax> = err.into_element().try_into().unwrap();
+/// assert_eq!(token.kind(), Int);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementConversionError<E>(E);
+
+impl<E> ElementConversionError<E> {
+    /// Returns the element that could not be converted.
+    #[inline]
+    pub fn into_element(self) -> E {
+        self.0
+    }
+
+    /// The element that could not be converted.
+    #[inline]
+    pub fn element(&self) -> &E {
+        &self.0
+    }
+}
+
+impl<N, T> fmt::Display for ElementConversionError<NodeOrToken<N, T>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            NodeOrToken::Node(_) => write!(f, "expected a token, found a node"),
+            NodeOrToken::Token(_) => write!(f, "expected a node, found a token"),
+        }
+    }
+}
+
+impl<N: fmt::Debug, T: fmt::Debug> std::error::Error for ElementConversionError<NodeOrToken<N, T>> {}
+
 impl<S: Syntax, D> SyntaxElement<S, D> {
     /// Returns this element's [`Display`](fmt::Display) representation as a string.
     ///
@@ -103,6 +175,30 @@ impl<'a, S: Syntax, D> From<&'a SyntaxElement<S, D>> for SyntaxElementRef<'a, S,
     }
 }
 
+impl<'a, S: Syntax, D> TryFrom<SyntaxElementRef<'a, S, D>> for &'a SyntaxNode<S, D> {
+    type Error = ElementConversionError<SyntaxElementRef<'a, S, D>>;
+
+    #[inline]
+    fn try_from(element: SyntaxElementRef<'a, S, D>) -> Result<Self, Self::Error> {
+        match element {
+            NodeOrToken::Node(node) => Ok(node),
+            element => Err(ElementConversionError(element)),
+        }
+    }
+}
+
+impl<'a, S: Syntax, D> TryFrom<SyntaxElementRef<'a, S, D>> for &'a SyntaxToken<S, D> {
+    type Error = ElementConversionError<SyntaxElementRef<'a, S, D>>;
+
+    #[inline]
+    fn try_from(element: SyntaxElementRef<'a, S, D>) -> Result<Self, Self::Error> {
+        match element {
+            NodeOrToken::Token(token) => Ok(token),
+            element => Err(ElementConversionError(element)),
+        }
+    }
+}
+
 impl<'a, S: Syntax, D> SyntaxElementRef<'a, S, D> {
     /// Returns this element's [`Display`](fmt::Display) representation as a string.
     ///
@@ -198,6 +294,14 @@ impl<S: Syntax, D> SyntaxElement<S, D> {
         }
     }
 
+    /// Returns `true` if this element, whether it is a node or a token, is of the given `kind`.
+    ///
+    /// This compares raw kinds and does not convert the kind of this element to `S` first.
+    #[inline]
+    pub fn matches_kind(&self, kind: S) -> bool {
+        self.syntax_kind() == kind.into_raw()
+    }
+
     /// The parent node of this element, except if this element is the root.
     #[inline]
     pub fn parent(&self) -> Option<&SyntaxNode<S, D>> {
@@ -281,7 +385,19 @@ impl<'a, S: Syntax, D> SyntaxElementRef<'a, S, D> {
         }
     }
 
+    /// Returns `true` if this element, whether it is a node or a token, is of the given `kind`.
+    ///
+    /// This compares raw kinds and does not convert the kind of this element to `S` first.
+    #[inline]
+    pub fn matches_kind(&self, kind: S) -> bool {
+        self.syntax_kind() == kind.into_raw()
+    }
+
     /// The parent node of this element, except if this element is the root.
+    ///
+    /// Tokens always have a parent, so this only returns `None` for a root node. This allows navigating upwards from
+    /// the elements of heterogeneous iterators such as [`SyntaxNode::children_with_tokens`] without matching on
+    /// whether they are nodes or tokens.
     #[inline]
     pub fn parent(&self) -> Option<&'a SyntaxNode<S, D>> {
         match self {
@@ -0,0 +1,90 @@
+//! Classification of tokens for syntax highlighting.
+
+use crate::{text::TextRange, Syntax};
+
+use super::{SyntaxNode, SyntaxToken};
+
+/// Maps tokens to semantic classes (such as keywords, strings or comments) for syntax highlighting.
+///
+/// Use [`SyntaxNode::highlight`] to classify all tokens of a subtree.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::{syntax::Highlighter, text::TextRange};
+///
+/// #[derive(Debug, PartialEq, Eq)]
+/// enum Class {
+///     Number,
+///     Operator,
+///     Other,
+/// }
+///
+/// struct MyHighlighter;
+/// impl Highlighter<MySyntax> for MyHighlighter {
+///     type Class = Class;
+///
+///     fn classify(&self, token: &SyntaxToken<MySyntax>) -> Class {
+///         match token.kind() {
+///             Int | Float => Class::Number,
+///             Plus => Class::Operator,
+///             _ => Class::Other,
+///         }
+///     }
+/// }
+///
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.token(Int, "1");
+/// builder.token(Plus, "+");
+/// builder.token(Int, "2");
+/// builder.finish_node();
+/// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+///
+/// let spans = root.highlight(&MyHighlighter);
+/// assert_eq!(
+///     spans[1],
+///     (TextRange::new(1.into(), 2.into()), Class::Operator)
+/// );
+/// ```
+pub trait Highlighter<S: Syntax, D: 'static = ()> {
+    /// The semantic class assigned to tokens.
+    type Class: PartialEq;
+
+    /// Returns the class of the given `token`.
+    fn classify(&self, token: &SyntaxToken<S, D>) -> Self::Class;
+}
+
+impl<S: Syntax, D> SyntaxNode<S, D> {
+    /// Classifies all tokens in the subtree of this node with the given `highlighter`.
+    ///
+    /// The returned spans are in document order and do not overlap. There is exactly one span per token, see
+    /// [`highlight_merged`](SyntaxNode::highlight_merged) to combine the spans of consecutive tokens with the same
+    /// class.
+    pub fn highlight<H>(&self, highlighter: &H) -> Vec<(TextRange, H::Class)>
+    where
+        H: Highlighter<S, D>,
+    {
+        self.tokens()
+            .map(|token| (token.text_range(), highlighter.classify(token)))
+            .collect()
+    }
+
+    /// Classifies all tokens in the subtree of this node with the given `highlighter`, merging the spans of
+    /// consecutive tokens with the same class.
+    ///
+    /// See also [`highlight`](SyntaxNode::highlight).
+    pub fn highlight_merged<H>(&self, highlighter: &H) -> Vec<(TextRange, H::Class)>
+    where
+        H: Highlighter<S, D>,
+    {
+        let mut spans: Vec<(TextRange, H::Class)> = Vec::new();
+        for (range, class) in self.highlight(highlighter) {
+            match spans.last_mut() {
+                Some((last_range, last_class)) if *last_class == class => *last_range = last_range.cover(range),
+                _ => spans.push((range, class)),
+            }
+        }
+        spans
+    }
+}
@@ -7,6 +7,8 @@ use text_size::TextSize;
 use crate::{
     green::{GreenElementRef, GreenNodeChildren},
     syntax::{SyntaxElementRef, SyntaxNode},
+    traversal::WalkEvent,
+    util::{KindRange, NodeOrToken},
     Syntax,
 };
 
@@ -66,6 +68,10 @@ impl<'n> ExactSizeIterator for Iter<'n> {
 impl<'n> FusedIterator for Iter<'n> {}
 
 /// An iterator over the child nodes of a [`SyntaxNode`].
+///
+/// The iterator knows its exact length, but determining it with [`len`](ExactSizeIterator::len) or
+/// [`size_hint`](Iterator::size_hint) counts the child nodes that have not been returned yet, which takes time
+/// linear in the number of remaining children.
 #[derive(Clone, Debug)]
 pub struct SyntaxNodeChildren<'n, S: Syntax, D: 'static = ()> {
     inner:  Iter<'n>,
@@ -80,6 +86,10 @@ impl<'n, S: Syntax, D> SyntaxNodeChildren<'n, S, D> {
             parent,
         }
     }
+
+    fn remaining(&self) -> usize {
+        self.inner.green.clone().filter(|child| child.as_node().is_some()).count()
+    }
 }
 
 impl<'n, S: Syntax, D> Iterator for SyntaxNodeChildren<'n, S, D> {
@@ -97,7 +107,8 @@ impl<'n, S: Syntax, D> Iterator for SyntaxNodeChildren<'n, S, D> {
 
     #[inline(always)]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.inner.size_hint()
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
     }
 
     #[inline(always)]
@@ -105,16 +116,17 @@ impl<'n, S: Syntax, D> Iterator for SyntaxNodeChildren<'n, S, D> {
     where
         Self: Sized,
     {
-        self.inner.count()
+        self.remaining()
     }
 }
 
 impl<'n, S: Syntax, D> ExactSizeIterator for SyntaxNodeChildren<'n, S, D> {
     #[inline(always)]
     fn len(&self) -> usize {
-        self.inner.len()
+        self.remaining()
     }
 }
+
 impl<'n, S: Syntax, D> FusedIterator for SyntaxNodeChildren<'n, S, D> {}
 
 /// An iterator over the children of a [`SyntaxNode`].
@@ -132,6 +144,64 @@ impl<'n, S: Syntax, D> SyntaxElementChildren<'n, S, D> {
             parent,
         }
     }
+
+    /// Restricts this iterator to the children (nodes or tokens) of the given `kind`.
+    #[inline]
+    pub fn of_kind(self, kind: S) -> impl Iterator<Item = SyntaxElementRef<'n, S, D>> {
+        self.filter(move |child| child.matches_kind(kind))
+    }
+
+    /// Restricts this iterator to the children (nodes or tokens) whose kind lies in the given `range`, for example to
+    /// find all keyword tokens if keywords are numbered consecutively.
+    ///
+    /// See [`KindRange`].
+    #[inline]
+    pub fn in_range(self, range: impl Into<KindRange<S>>) -> impl Iterator<Item = SyntaxElementRef<'n, S, D>> {
+        let range = range.into();
+        self.filter(move |child| range.contains_raw(child.syntax_kind()))
+    }
+
+    /// Pairs each remaining child with the kinds of the `n` children that follow it, for making decisions based on
+    /// lookahead without peeking manually.
+    ///
+    /// Near the end of the children, the kinds of missing children are `None`, so every `Vec` of kinds has a length
+    /// of `n`. Looking ahead does not create the following children, so this is cheap even for large `n`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    ///
+    /// let kinds: Vec<_> = root
+    ///     .children_with_tokens()
+    ///     .with_lookahead(2)
+    ///     .map(|(child, ahead)| (child.kind(), ahead))
+    ///     .collect();
+    /// assert_eq!(
+    ///     kinds,
+    ///     [
+    ///         (Int, vec![Some(Plus), Some(Int)]),
+    ///         (Plus, vec![Some(Int), None]),
+    ///         (Int, vec![None, None]),
+    ///     ]
+    /// );
+    /// ```
+    pub fn with_lookahead(mut self, n: usize) -> impl Iterator<Item = (SyntaxElementRef<'n, S, D>, Vec<Option<S>>)> {
+        std::iter::from_fn(move || {
+            let child = self.next()?;
+            let mut ahead = self.inner.green.clone();
+            let kinds = (0..n)
+                .map(|_| ahead.next().map(|element| S::from_raw(element.kind())))
+                .collect();
+            Some((child, kinds))
+        })
+    }
 }
 
 impl<'n, S: Syntax, D> Iterator for SyntaxElementChildren<'n, S, D> {
@@ -166,3 +236,171 @@ impl<'n, S: Syntax, D> ExactSizeIterator for SyntaxElementChildren<'n, S, D> {
     }
 }
 impl<'n, S: Syntax, D> FusedIterator for SyntaxElementChildren<'n, S, D> {}
+
+impl<'n, S: Syntax, D> IntoIterator for &'n SyntaxNode<S, D> {
+    type IntoIter = SyntaxElementChildren<'n, S, D>;
+    type Item = SyntaxElementRef<'n, S, D>;
+
+    /// Iterates over the children of this node, including tokens.
+    /// This is equivalent to [`children_with_tokens`](SyntaxNode::children_with_tokens).
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.children_with_tokens()
+    }
+}
+
+/// An iterator over the nodes in the subtree of a [`SyntaxNode`] in preorder, excluding tokens.
+///
+/// Create one with [`SyntaxNode::preorder`].
+#[derive(Clone, Debug)]
+pub struct Preorder<'n, S: Syntax, D: 'static = ()> {
+    start:       &'n SyntaxNode<S, D>,
+    next:        Option<WalkEvent<&'n SyntaxNode<S, D>>>,
+    /// Whether the last event returned was [`WalkEvent::Enter`], in which case a pending `Enter` is for its first
+    /// child.
+    after_enter: bool,
+}
+
+impl<'n, S: Syntax, D> Preorder<'n, S, D> {
+    #[inline]
+    pub(super) fn new(start: &'n SyntaxNode<S, D>) -> Self {
+        Self {
+            start,
+            next: Some(WalkEvent::Enter(start)),
+            after_enter: false,
+        }
+    }
+
+    /// Skips the children of the node that was just entered.
+    ///
+    /// If the last event returned by this iterator was [`WalkEvent::Enter`], the next event will be the matching
+    /// [`WalkEvent::Leave`] of the same node, without any events for its descendants in between. Otherwise, this
+    /// does nothing.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::traversal::WalkEvent;
+    ///
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.start_node(Operation);
+    /// builder.start_node(Operation);
+    /// builder.token(Int, "1");
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    ///
+    /// let mut preorder = root.preorder();
+    /// let mut kinds = Vec::new();
+    /// while let Some(event) = preorder.next() {
+    ///     if let WalkEvent::Enter(node) = event {
+    ///         kinds.push(node.kind());
+    ///         if node.kind() == Operation {
+    ///             preorder.skip_subtree();
+    ///         }
+    ///     }
+    /// }
+    /// assert_eq!(kinds, [Root, Operation]);
+    /// ```
+    #[inline]
+    pub fn skip_subtree(&mut self) {
+        if !self.after_enter {
+            return;
+        }
+        if let Some(WalkEvent::Enter(first_child)) = self.next {
+            // the first child was about to be entered, so its parent is the node that was just entered
+            self.next = Some(WalkEvent::Leave(first_child.parent().unwrap()));
+        }
+    }
+}
+
+impl<'n, S: Syntax, D> Iterator for Preorder<'n, S, D> {
+    type Item = WalkEvent<&'n SyntaxNode<S, D>>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        self.after_enter = matches!(current, WalkEvent::Enter(_));
+        self.next = match current {
+            WalkEvent::Enter(node) => match node.first_child() {
+                Some(child) => Some(WalkEvent::Enter(child)),
+                None => Some(WalkEvent::Leave(node)),
+            },
+            WalkEvent::Leave(node) if node == self.start => None,
+            WalkEvent::Leave(node) => match node.next_sibling() {
+                Some(sibling) => Some(WalkEvent::Enter(sibling)),
+                None => Some(WalkEvent::Leave(node.parent().unwrap())),
+            },
+        };
+        Some(current)
+    }
+}
+
+impl<'n, S: Syntax, D> FusedIterator for Preorder<'n, S, D> {}
+
+/// An iterator over the elements in the subtree of a [`SyntaxNode`] in preorder, including tokens.
+///
+/// Create one with [`SyntaxNode::preorder_with_tokens`].
+#[derive(Clone, Debug)]
+pub struct PreorderWithTokens<'n, S: Syntax, D: 'static = ()> {
+    start:       SyntaxElementRef<'n, S, D>,
+    next:        Option<WalkEvent<SyntaxElementRef<'n, S, D>>>,
+    /// Whether the last event returned was [`WalkEvent::Enter`], in which case a pending `Enter` is for its first
+    /// child.
+    after_enter: bool,
+}
+
+impl<'n, S: Syntax, D> PreorderWithTokens<'n, S, D> {
+    #[inline]
+    pub(super) fn new(start: &'n SyntaxNode<S, D>) -> Self {
+        let start = start.into();
+        Self {
+            start,
+            next: Some(WalkEvent::Enter(start)),
+            after_enter: false,
+        }
+    }
+
+    /// Skips the children of the node that was just entered.
+    ///
+    /// If the last event returned by this iterator was [`WalkEvent::Enter`], the next event will be the matching
+    /// [`WalkEvent::Leave`] of the same element, without any events for its descendants in between. Otherwise, or if
+    /// the entered element is a token, this does nothing.
+    #[inline]
+    pub fn skip_subtree(&mut self) {
+        if !self.after_enter {
+            return;
+        }
+        if let Some(WalkEvent::Enter(first_child)) = self.next {
+            // the first child was about to be entered, so its parent is the node that was just entered
+            self.next = Some(WalkEvent::Leave(first_child.parent().unwrap().into()));
+        }
+    }
+}
+
+impl<'n, S: Syntax, D> Iterator for PreorderWithTokens<'n, S, D> {
+    type Item = WalkEvent<SyntaxElementRef<'n, S, D>>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        self.after_enter = matches!(current, WalkEvent::Enter(_));
+        self.next = match current {
+            WalkEvent::Enter(NodeOrToken::Node(node)) => match node.first_child_or_token() {
+                Some(child) => Some(WalkEvent::Enter(child)),
+                None => Some(WalkEvent::Leave(node.into())),
+            },
+            WalkEvent::Enter(NodeOrToken::Token(token)) => Some(WalkEvent::Leave(token.into())),
+            WalkEvent::Leave(element) if element == self.start => None,
+            WalkEvent::Leave(element) => match element.next_sibling_or_token() {
+                Some(sibling) => Some(WalkEvent::Enter(sibling)),
+                None => Some(WalkEvent::Leave(element.parent().unwrap().into())),
+            },
+        };
+        Some(current)
+    }
+}
+
+impl<'n, S: Syntax, D> FusedIterator for PreorderWithTokens<'n, S, D> {}
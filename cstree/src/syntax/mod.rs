@@ -5,20 +5,50 @@
 //! Use [`SyntaxNode::new_root`] and [`SyntaxNode::new_root_with_resolver`] to construct a syntax
 //! tree on top of a green tree.
 
+use std::fmt;
+
+use crate::Syntax;
+
+mod classifier;
+pub use classifier::Classifier;
+mod edit;
+pub use edit::EditError;
 mod element;
-pub use element::{SyntaxElement, SyntaxElementRef};
+pub use element::{ElementConversionError, SyntaxElement, SyntaxElementRef};
+mod highlight;
+pub use highlight::Highlighter;
 mod node;
-pub use node::SyntaxNode;
+pub use node::{FileId, SyntaxNode};
+mod node_map;
+pub use node_map::NodeMap;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod pattern;
+pub use pattern::Pattern;
+mod thin;
+pub use thin::{ThinElement, ThinElementChildren, ThinNode, ThinToken};
 mod token;
 pub use token::SyntaxToken;
+mod token_index;
+pub use token_index::TokenIndex;
 mod resolved;
 pub use resolved::{ResolvedElement, ResolvedElementRef, ResolvedNode, ResolvedToken};
 mod iter;
-pub use iter::{SyntaxElementChildren, SyntaxNodeChildren};
+pub use iter::{Preorder, PreorderWithTokens, SyntaxElementChildren, SyntaxNodeChildren};
+mod visit;
+pub use visit::Visitor;
 
 mod text;
 pub use text::SyntaxText;
 
+/// Writes the [name](Syntax::kind_name) of `kind` into `target`, falling back to its `Debug` representation.
+fn write_kind<S: Syntax>(kind: S, target: &mut impl fmt::Write) -> fmt::Result {
+    match kind.kind_name() {
+        Some(name) => target.write_str(name),
+        None => write!(target, "{kind:?}"),
+    }
+}
+
 // A note on `#[inline]` usage in this module:
 // In `rowan`, there are two layers of `SyntaxXY`s: the `cursor` layer and the `api` layer.
 // The `cursor` layer handles all of the actual methods on the tree, while the `api` layer is
@@ -65,5 +95,7 @@ mod tests {
         assert_eq!(size_of::<Option<SyntaxNode<TestSyntaxKind>>>(),  size_of::<*const u8>()); 
         // parent + child index + text len
         assert_eq!(size_of::<SyntaxToken<TestSyntaxKind>>(),         size_of::<SyntaxNode<TestSyntaxKind>>() + size_of::<u32>() * 2);
+        // kind + green + ref count + data + children and their locks, with root-only information boxed
+        assert_eq!(size_of::<node::NodeData<TestSyntaxKind, ()>>(), size_of::<*const u8>() * 13);
     }
 }
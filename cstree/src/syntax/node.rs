@@ -2,8 +2,9 @@ use super::*;
 #[cfg(feature = "serialize")]
 use crate::serde_impls::{SerializeWithData, SerializeWithResolver};
 use crate::{
+    build::NodeCache,
     green::{GreenElementRef, GreenNode},
-    interning::{Resolver, TokenKey},
+    interning::{Interner, Resolver, TokenInterner, TokenKey},
     text::*,
     traversal::*,
     util::*,
@@ -12,9 +13,11 @@ use crate::{
 use parking_lot::RwLock;
 use std::{
     cell::UnsafeCell,
+    collections::{HashMap, HashSet},
     fmt,
     hash::{Hash, Hasher},
-    iter,
+    io,
+    iter::{self, FusedIterator},
     ptr::{self, NonNull},
     sync::{
         atomic::{AtomicU32, Ordering},
@@ -23,10 +26,22 @@ use std::{
 };
 use triomphe::Arc;
 
+/// An identifier of the source file a syntax tree was created from, for example an index into a list of files.
+///
+/// Associate a tree with a file using [`SyntaxNode::with_file_id`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(pub u32);
+
 /// Inner syntax tree node.
 /// Syntax nodes can be shared between threads.
 /// Every syntax tree is reference counted as a whole and nodes are pointer-sized, so copying
 /// individual nodes is relatively cheap.
+///
+/// Syntax nodes implement [`PartialEq`] and [`Hash`] by identity: two nodes are equal only if they are the same node
+/// of the same tree. Nodes at different positions are never equal, even if they share the same deduplicated green
+/// node, and neither are the nodes of two trees created from the same green tree. This makes nodes suitable as keys
+/// of side tables for a single tree. See [`ptr_eq_position`](SyntaxNode::ptr_eq_position) to compare nodes across
+/// trees, and compare their [`green`](SyntaxNode::green) nodes to compare them structurally.
 #[derive(Debug)]
 #[repr(transparent)]
 pub struct SyntaxNode<S: Syntax, D: 'static = ()> {
@@ -62,7 +77,8 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
             assert_eq!(level, 0);
             Ok(())
         } else {
-            write!(target, "{:?}@{:?}", self.kind(), self.text_range())
+            super::write_kind(self.kind(), target)?;
+            write!(target, "@{:?}", self.text_range())
         }
     }
 
@@ -109,10 +125,194 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
         res
     }
 
+    /// Writes the structure of the entire subtree rooted in this node into the given `target`, without requiring a
+    /// resolver.
+    ///
+    /// The output has the same shape as the recursive [`Debug`](fmt::Debug) representation of this node, except that
+    /// the text of every token is replaced with a `<token>` placeholder.
+    pub fn write_structure(&self, target: &mut impl fmt::Write) -> fmt::Result {
+        let mut level = 0;
+        for event in self.preorder_with_tokens() {
+            match event {
+                WalkEvent::Enter(element) => {
+                    for _ in 0..level {
+                        write!(target, "  ")?;
+                    }
+                    super::write_kind(element.kind(), target)?;
+                    write!(target, "@{:?}", element.text_range())?;
+                    if element.as_token().is_some() {
+                        write!(target, " <token>")?;
+                    }
+                    writeln!(target)?;
+                    level += 1;
+                }
+                WalkEvent::Leave(_) => level -= 1,
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a value that [`Display`](fmt::Display)s the structure of the entire subtree rooted in this node,
+    /// without requiring a resolver.
+    ///
+    /// See [`write_structure`](SyntaxNode::write_structure) for the output format.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "42");
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    /// assert_eq!(root.display_structure().to_string(), "Root@0..2\n  Int@0..2 <token>\n");
+    /// ```
+    #[inline]
+    pub fn display_structure(&self) -> impl fmt::Display + '_ {
+        struct Structure<'n, S: Syntax, D: 'static>(&'n SyntaxNode<S, D>);
+
+        impl<S: Syntax, D> fmt::Display for Structure<'_, S, D> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.write_structure(f)
+            }
+        }
+
+        Structure(self)
+    }
+
+    /// Writes the entire subtree rooted in this node into the given `target` as JSON, for use by tools that are not
+    /// written in Rust.
+    ///
+    /// Every node is written as an object with the keys `"kind"`, `"range"` and `"children"`, and every token as an
+    /// object with the keys `"kind"`, `"range"` and `"text"`, always in this order and without any whitespace, so
+    /// the output is deterministic. Kinds are given by their [raw representation](Syntax::into_raw) and ranges as
+    /// arrays of their start and end offsets.
+    pub fn write_json<R>(&self, resolver: &R, target: &mut impl fmt::Write) -> fmt::Result
+    where
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        // whether the next element is preceded by a sibling
+        let mut after_sibling = false;
+        for event in self.preorder_with_tokens() {
+            match event {
+                WalkEvent::Enter(element) => {
+                    if after_sibling {
+                        target.write_char(',')?;
+                    }
+                    let range = element.text_range();
+                    write!(
+                        target,
+                        r#"{{"kind":{},"range":[{},{}],"#,
+                        element.syntax_kind().0,
+                        u32::from(range.start()),
+                        u32::from(range.end())
+                    )?;
+                    match element {
+                        NodeOrToken::Node(_) => {
+                            target.write_str(r#""children":["#)?;
+                            after_sibling = false;
+                        }
+                        NodeOrToken::Token(token) => {
+                            target.write_str(r#""text":"#)?;
+                            write_json_string(token.resolve_text(resolver), target)?;
+                            target.write_char('}')?;
+                            after_sibling = true;
+                        }
+                    }
+                }
+                WalkEvent::Leave(NodeOrToken::Node(_)) => {
+                    target.write_str("]}")?;
+                    after_sibling = true;
+                }
+                WalkEvent::Leave(NodeOrToken::Token(_)) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the entire subtree rooted in this node as JSON.
+    ///
+    /// See [`write_json`](SyntaxNode::write_json) for the output format.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Identifier, "\"x\"");
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    /// assert_eq!(
+    ///     root.to_json(&interner),
+    ///     r#"{"kind":5,"range":[0,3],"children":[{"kind":1,"range":[0,3],"text":"\"x\""}]}"#
+    /// );
+    /// ```
+    #[inline]
+    pub fn to_json<R>(&self, resolver: &R) -> String
+    where
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        // NOTE: `fmt::Write` methods on `String` never fail
+        let mut res = String::new();
+        self.write_json(resolver, &mut res).unwrap();
+        res
+    }
+
     /// If there is a resolver associated with this tree, returns it.
     pub fn resolver(&self) -> Option<&StdArc<dyn Resolver<TokenKey>>> {
         match &self.root().data().kind {
-            Kind::Root(_, resolver) => resolver.as_ref(),
+            Kind::Root { meta, .. } => meta.resolver.as_ref(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// The slot of the [`Classifier`] registered for this tree.
+    pub(super) fn classifier_slot(&self) -> &RwLock<Option<StdArc<dyn Classifier<S>>>> {
+        match &self.root().data().kind {
+            Kind::Root { meta, .. } => &meta.classifier,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Associates the tree this node belongs to with the source file identified by `file_id`, replacing any file that
+    /// was associated with it before.
+    ///
+    /// The file is shared by all nodes and tokens of the tree, including ones that already exist. It is only
+    /// metadata for telling apart trees of different files, for example when navigating between files, and does not
+    /// affect the tree in any other way.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::syntax::FileId;
+    ///
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    /// assert_eq!(root.file_id(), None);
+    ///
+    /// let root = root.with_file_id(FileId(7));
+    /// assert_eq!(root.first_token().unwrap().file_id(), Some(FileId(7)));
+    /// ```
+    pub fn with_file_id(self, file_id: FileId) -> Self {
+        match &self.root().data().kind {
+            Kind::Root { meta, .. } => *meta.file_id.write() = Some(file_id),
+            _ => unreachable!(),
+        }
+        self
+    }
+
+    /// The source file the tree this node belongs to is associated with, if any.
+    ///
+    /// See [`with_file_id`](SyntaxNode::with_file_id).
+    #[inline]
+    pub fn file_id(&self) -> Option<FileId> {
+        match &self.root().data().kind {
+            Kind::Root { meta, .. } => *meta.file_id.read(),
             _ => unreachable!(),
         }
     }
@@ -237,7 +437,10 @@ impl<S: Syntax, D> Hash for SyntaxNode<S, D> {
 }
 
 enum Kind<S: Syntax, D: 'static> {
-    Root(GreenNode, Option<StdArc<dyn Resolver<TokenKey>>>),
+    Root {
+        green: GreenNode,
+        meta:  Box<RootMeta<S>>,
+    },
     Child {
         parent: SyntaxNode<S, D>,
         index:  u32,
@@ -254,6 +457,15 @@ impl<S: Syntax, D> Kind<S, D> {
     }
 }
 
+/// Information about a tree that is only stored with its root.
+///
+/// This is boxed so that it does not increase the size of the [`NodeData`] of every node.
+struct RootMeta<S: Syntax> {
+    resolver:   Option<StdArc<dyn Resolver<TokenKey>>>,
+    classifier: RwLock<Option<StdArc<dyn Classifier<S>>>>,
+    file_id:    RwLock<Option<FileId>>,
+}
+
 pub(super) struct NodeData<S: Syntax, D: 'static> {
     kind:        Kind<S, D>,
     green:       NonNull<GreenNode>,
@@ -308,14 +520,21 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
         let ref_count = Box::new(AtomicU32::new(1));
         let n_children = green.children().count();
         let data = NodeData::new(
-            Kind::Root(green, resolver),
+            Kind::Root {
+                green,
+                meta: Box::new(RootMeta {
+                    resolver,
+                    classifier: RwLock::default(),
+                    file_id: RwLock::default(),
+                }),
+            },
             NonNull::dangling(),
             Box::into_raw(ref_count),
             n_children,
         );
         let ret = Self::new(data);
         let green: NonNull<GreenNode> = match &ret.data().kind {
-            Kind::Root(green, _resolver) => green.into(),
+            Kind::Root { green, .. } => green.into(),
             _ => unreachable!(),
         };
         // safety: we have just created `ret` and have not shared it.
@@ -329,6 +548,10 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
     /// Build a new syntax tree on top of a green tree and associate a resolver with the tree to
     /// resolve interned Strings.
     ///
+    /// The resolver is stored once for the entire tree, behind an [`Arc`](StdArc) that is shared by all of its
+    /// nodes and tokens. Cloning a node or token only increments the reference count of the tree and never clones
+    /// the resolver.
+    ///
     /// # Example
     /// ```
     /// # use cstree::testing::*;
@@ -399,10 +622,43 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
         Ok(data)
     }
 
+    /// Stores data that may already be associated with another node for this node.
+    pub(super) fn set_shared_data(&self, data: Arc<D>) {
+        let mut ptr = self.data().data.write();
+        *ptr = Some(data);
+    }
+
     /// Returns the data associated with this node, if any.
     pub fn get_data(&self) -> Option<Arc<D>> {
         let ptr = self.data().data.read();
-        (*ptr).as_ref().map(Arc::clone)
+        (*ptr).clone()
+    }
+
+    /// Returns an iterator over all nodes in the subtree starting at this node, including this node, that have data
+    /// associated with them, together with their data. Nodes are visited in preorder.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.start_node(Operation);
+    /// builder.token(Int, "1");
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax, &str> = SyntaxNode::new_root(builder.finish().0);
+    ///
+    /// let operation = root.first_child().unwrap();
+    /// operation.set_data("checked");
+    /// let annotated: Vec<_> = root.descendants_with_data().collect();
+    /// assert_eq!(annotated.len(), 1);
+    /// assert_eq!(annotated[0].0, operation);
+    /// assert_eq!(*annotated[0].1, "checked");
+    /// ```
+    #[inline]
+    pub fn descendants_with_data(&self) -> impl FusedIterator<Item = (&SyntaxNode<S, D>, Arc<D>)> {
+        self.descendants()
+            .filter_map(|node| node.get_data().map(|data| (node, data)))
     }
 
     /// Removes the data associated with this node.
@@ -515,12 +771,83 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
                         child.cloned()
                     }
                 });
-                let new_parent = GreenNode::new(parent.syntax_kind(), children);
+                let new_parent = parent.green().with_children(children);
                 parent.replace_with(new_parent)
             }
         }
     }
 
+    /// Copies the subtree of this node into a standalone tree, with a new interner that only contains the text of
+    /// the tokens in the subtree. `resolver` must be able to resolve the text of this node.
+    ///
+    /// The returned green node is the root of the new tree, and its text can be resolved with the returned
+    /// interner. Unlike the green node of this node, which may share the interner with many other trees, the
+    /// extracted tree can be moved elsewhere without keeping the original interner alive.
+    ///
+    /// See also [`NodeCache::reintern`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Identifier, "foo");
+    /// builder.start_node(Operation);
+    /// builder.token(Int, "1");
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    ///
+    /// let (green, resolver) = root.first_child().unwrap().extract(&interner);
+    /// drop(interner);
+    /// let operation: SyntaxNode<MySyntax> = SyntaxNode::new_root(green);
+    /// assert_eq!(operation.resolve_text(&resolver), "1");
+    /// ```
+    pub fn extract<R>(&self, resolver: &R) -> (GreenNode, TokenInterner)
+    where
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        let mut cache = NodeCache::new();
+        let (mut roots, _) = cache.reintern(std::slice::from_ref(self.green()), resolver);
+        // NOTE: the cache was created with `new`, so it owns its interner
+        (roots.pop().unwrap(), cache.into_interner().unwrap())
+    }
+
+    /// Rebuilds the subtree of this node into new green nodes built with `cache`.
+    ///
+    /// Cloning the [green node](SyntaxNode::green) of this node only increments a reference count, so the clone
+    /// shares all of its nodes with the original tree. A deep copy instead rebuilds every node, which results in new
+    /// nodes unless `cache` deduplicates them with identical nodes it has built before. Tokens keep their interned
+    /// text, so the copy can be resolved with the same interner as this node.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::build::NodeCache;
+    ///
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    ///
+    /// let copy = root.deep_copy(&mut NodeCache::new());
+    /// assert_eq!(&copy, root.green());
+    /// assert!(!copy.ptr_eq(root.green()));
+    /// let copy: SyntaxNode<MySyntax> = SyntaxNode::new_root(copy);
+    /// assert_eq!(copy.resolve_text(&interner), "1");
+    /// ```
+    pub fn deep_copy<I>(&self, cache: &mut NodeCache<'_, I>) -> GreenNode
+    where
+        I: Interner<TokenKey>,
+    {
+        self.green().map_kinds(|kind| kind, cache)
+    }
+
     /// The internal representation of the kind of this node.
     #[inline]
     pub fn syntax_kind(&self) -> RawSyntaxKind {
@@ -533,6 +860,14 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
         S::from_raw(self.syntax_kind())
     }
 
+    /// The flags of this node, including the flags of all of its descendants.
+    ///
+    /// See [`GreenNode::flags`] and [`GreenNodeBuilder::set_flags`](crate::build::GreenNodeBuilder::set_flags).
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.green().flags()
+    }
+
     /// The range this node covers in the source text, in bytes.
     #[inline]
     pub fn text_range(&self) -> TextRange {
@@ -543,6 +878,107 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
         TextRange::at(offset, self.green().text_len())
     }
 
+    /// The range this node covers in the source text, excluding any trivia tokens at the start and at the end of
+    /// the node. A token is trivia if `is_trivia` returns `true` for its kind.
+    ///
+    /// If the node only contains trivia (or no tokens at all), returns an empty range at the start of the node.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::text::TextRange;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Whitespace, " ");
+    /// builder.token(Int, "1");
+    /// builder.token(Whitespace, "\n");
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    /// assert_eq!(
+    ///     root.trimmed_range(|kind| kind == Whitespace),
+    ///     TextRange::new(1.into(), 2.into())
+    /// );
+    /// ```
+    pub fn trimmed_range(&self, is_trivia: impl Fn(S) -> bool) -> TextRange {
+        let mut trimmed: Option<TextRange> = None;
+        for token in self.thin().descendant_tokens() {
+            if is_trivia(token.kind()) {
+                continue;
+            }
+            let range = token.text_range();
+            trimmed = Some(match trimmed {
+                Some(trimmed) => trimmed.cover(range),
+                None => range,
+            });
+        }
+        trimmed.unwrap_or_else(|| TextRange::empty(self.text_range().start()))
+    }
+
+    /// The line and column at which this node starts, according to the `line_index` of the text of the tree this
+    /// node belongs to.
+    ///
+    /// ## Panics
+    /// If the start of this node is past the end of the text indexed by `line_index`.
+    #[inline]
+    pub fn position(&self, line_index: &LineIndex) -> LineCol {
+        line_index.line_col(self.text_range().start())
+    }
+
+    /// Returns an iterator over the range of every line that this node spans, clipped to the range of this node,
+    /// according to the `line_index` of the text of the tree this node belongs to.
+    ///
+    /// Like with [`LineIndex::line_range`], the ranges exclude line breaks. A node that ends with a line break does
+    /// not span the following line, while an empty node yields a single empty range at its position.
+    ///
+    /// ## Panics
+    /// If the end of this node is past the end of the text indexed by `line_index`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::text::{LineIndex, TextRange};
+    ///
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Identifier, "foo");
+    /// builder.start_node(Operation);
+    /// builder.token(Int, "1");
+    /// builder.token(Whitespace, "\n");
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    /// let line_index = LineIndex::new(&root.resolve_text(&interner).to_string());
+    ///
+    /// let operation = root.first_child().unwrap();
+    /// let lines: Vec<_> = operation.line_ranges(&line_index).collect();
+    /// assert_eq!(
+    ///     lines,
+    ///     [
+    ///         TextRange::new(3.into(), 4.into()),
+    ///         TextRange::new(5.into(), 6.into())
+    ///     ]
+    /// );
+    /// ```
+    pub fn line_ranges<'l>(&self, line_index: &'l LineIndex) -> impl FusedIterator<Item = TextRange> + 'l {
+        let range = self.text_range();
+        let first = line_index.line_col(range.start()).line;
+        let end = line_index.line_col(range.end());
+        // a node that ends with a line break ends at the start of the next line, which it does not span
+        let last = if end.col == 0 && end.line > first {
+            end.line - 1
+        } else {
+            end.line
+        };
+        (first..=last).map(move |line| {
+            let line_range = line_index.line_range(line).unwrap();
+            // NOTE: the node intersects every line between its first and its last one
+            line_range.intersect(range).unwrap()
+        })
+    }
+
     /// Uses the provided resolver to return an efficient representation of all source text covered
     /// by this node, i.e. the combined text of all token leafs of the subtree originating in this
     /// node.
@@ -554,6 +990,176 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
         SyntaxText::new(self, resolver)
     }
 
+    /// Returns an iterator over the text of every token in the subtree of this node, in document order, for example
+    /// to feed the text into a hasher or writer without first collecting it into a `String`.
+    ///
+    /// Concatenating all chunks results in the text returned by [`resolve_text`](SyntaxNode::resolve_text).
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// let (green, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(green);
+    ///
+    /// let chunks: Vec<_> = root.text_chunks(&interner).collect();
+    /// assert_eq!(chunks, ["1", "+", "2"]);
+    /// ```
+    #[inline]
+    pub fn text_chunks<'n, 'i, I>(&'n self, resolver: &'i I) -> impl FusedIterator<Item = &'i str> + 'n
+    where
+        I: Resolver<TokenKey> + ?Sized,
+        'i: 'n,
+    {
+        self.tokens().map(move |token| token.resolve_text(resolver))
+    }
+
+    /// The number of `char`s in the source text of this node, resolved with `resolver`.
+    ///
+    /// This counts Unicode scalar values of all tokens in the subtree of this node, unlike the
+    /// [`text_range`](SyntaxNode::text_range) of the node, which is measured in bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Identifier, "größe");
+    /// builder.static_token(Plus);
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    /// assert_eq!(root.char_len(&interner), 6);
+    /// assert_eq!(root.text_range().len(), 8.into());
+    /// ```
+    pub fn char_len<I>(&self, resolver: &I) -> usize
+    where
+        I: Resolver<TokenKey> + ?Sized,
+    {
+        self.text_chunks(resolver).map(|text| text.chars().count()).sum()
+    }
+
+    /// Returns a reader that yields the source text of this node, the same as
+    /// [`resolve_text`](SyntaxNode::resolve_text), without concatenating it into a string first.
+    ///
+    /// The text is read from the tokens of this node on demand, so reads into small buffers can end in the middle of
+    /// a token (or of a UTF-8 sequence) and continue where they left off.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use std::io::Read;
+    ///
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "12");
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "3");
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    ///
+    /// let mut text = String::new();
+    /// root.text_reader(&interner)
+    ///     .read_to_string(&mut text)
+    ///     .unwrap();
+    /// assert_eq!(text, "12+3");
+    /// ```
+    #[inline]
+    pub fn text_reader<'n, 'i, I>(&'n self, resolver: &'i I) -> impl io::Read + 'n
+    where
+        I: Resolver<TokenKey> + ?Sized,
+        'i: 'n,
+    {
+        struct TextReader<'i, C> {
+            chunks:  C,
+            /// The part of the current chunk that has not been read yet.
+            current: &'i [u8],
+        }
+
+        impl<'i, C: Iterator<Item = &'i str>> io::Read for TextReader<'i, C> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                while self.current.is_empty() {
+                    match self.chunks.next() {
+                        Some(chunk) => self.current = chunk.as_bytes(),
+                        None => return Ok(0),
+                    }
+                }
+                let len = buf.len().min(self.current.len());
+                buf[..len].copy_from_slice(&self.current[..len]);
+                self.current = &self.current[len..];
+                Ok(len)
+            }
+        }
+
+        TextReader {
+            chunks:  self.text_chunks(resolver),
+            current: &[],
+        }
+    }
+
+    /// If all source text covered by this node comes from a single token, returns that text without allocating.
+    ///
+    /// Returns `None` if the text of this node is spread across multiple non-empty tokens. Tokens with empty text do
+    /// not count towards this, and a node without any text returns an empty string.
+    ///
+    /// Use [`resolve_text`](SyntaxNode::resolve_text) to access the text of any node.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.start_node(Operation);
+    /// builder.token(Int, "1");
+    /// builder.finish_node();
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    ///
+    /// assert_eq!(root.first_child().unwrap().as_str(&interner), Some("1"));
+    /// assert_eq!(root.as_str(&interner), None);
+    /// ```
+    pub fn as_str<'i, I>(&self, resolver: &'i I) -> Option<&'i str>
+    where
+        I: Resolver<TokenKey> + ?Sized,
+    {
+        let mut text = None;
+        let mut stack = vec![self.green().children()];
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                None => {
+                    stack.pop();
+                }
+                Some(NodeOrToken::Node(node)) => {
+                    if node.text_len() > 0.into() {
+                        stack.push(node.children());
+                    }
+                }
+                Some(NodeOrToken::Token(token)) => {
+                    if token.text_len() > 0.into() {
+                        if text.is_some() {
+                            return None;
+                        }
+                        let static_text = S::static_text(S::from_raw(token.kind()));
+                        text = static_text.or_else(|| token.text(resolver));
+                    }
+                }
+            }
+        }
+        Some(text.unwrap_or(""))
+    }
+
     /// Returns the unterlying green tree node of this node.
     #[inline]
     pub fn green(&self) -> &GreenNode {
@@ -564,7 +1170,7 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
     #[inline]
     pub fn parent(&self) -> Option<&SyntaxNode<S, D>> {
         match &self.data().kind {
-            Kind::Root(_, _) => None,
+            Kind::Root { .. } => None,
             Kind::Child { parent, .. } => Some(parent),
         }
     }
@@ -583,26 +1189,204 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
         self.data().children.len()
     }
 
+    /// Returns `true` if this node has no children at all, neither nodes nor tokens.
+    ///
+    /// Note that a node may also have empty text if all of its children are empty, which this does not check.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.arity_with_tokens() == 0
+    }
+
+    /// Returns `true` if this node has at least one child, either a node or a token. This is the opposite of
+    /// [`is_empty`](SyntaxNode::is_empty).
+    ///
+    /// To only check for child nodes, use [`first_child`](SyntaxNode::first_child).
+    #[inline]
+    pub fn has_children(&self) -> bool {
+        !self.is_empty()
+    }
+
+    /// The number of direct children of this node, including tokens, which is the same as
+    /// [`arity_with_tokens`](SyntaxNode::arity_with_tokens).
+    ///
+    /// This takes constant time.
+    #[inline]
+    pub fn child_count(&self) -> usize {
+        self.arity_with_tokens()
+    }
+
     /// Returns an iterator along the chain of parents of this node.
     #[inline]
-    pub fn ancestors(&self) -> impl Iterator<Item = &SyntaxNode<S, D>> {
+    pub fn ancestors(&self) -> impl FusedIterator<Item = &SyntaxNode<S, D>> {
         iter::successors(Some(self), |&node| node.parent())
     }
 
+    /// The nearest ancestor of this node with the given `kind`, not counting the node itself, or `None` if there is
+    /// no such ancestor up to and including the root.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.start_node(Operation);
+    /// builder.start_node(Operation);
+    /// builder.token(Int, "1");
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    ///
+    /// let inner = root.first_child().unwrap().first_child().unwrap();
+    /// assert_eq!(inner.ancestor_of_kind(Operation), root.first_child());
+    /// assert_eq!(inner.self_or_ancestor_of_kind(Operation), Some(inner));
+    /// assert_eq!(inner.ancestor_of_kind(Root), Some(&root));
+    /// assert_eq!(root.ancestor_of_kind(Root), None);
+    /// ```
+    #[inline]
+    pub fn ancestor_of_kind(&self, kind: S) -> Option<&SyntaxNode<S, D>> {
+        self.parent()?.self_or_ancestor_of_kind(kind)
+    }
+
+    /// The nearest node with the given `kind` on the chain of parents of this node, starting with the node itself.
+    ///
+    /// See also [`ancestor_of_kind`](SyntaxNode::ancestor_of_kind).
+    #[inline]
+    pub fn self_or_ancestor_of_kind(&self, kind: S) -> Option<&SyntaxNode<S, D>> {
+        self.ancestors().find(|node| node.kind() == kind)
+    }
+
+    /// Returns an iterator along the chain of parents of this node, like [`ancestors`](SyntaxNode::ancestors), where
+    /// each ancestor is paired with the index of the child it was reached from.
+    ///
+    /// The first item is this node itself, paired with `None`. Every further item is a parent, paired with the index
+    /// of the previous item among the children (including tokens) of that parent.
+    #[inline]
+    pub fn ancestors_with_index(&self) -> impl Iterator<Item = (&SyntaxNode<S, D>, Option<usize>)> {
+        iter::successors(Some((self, None)), |&(node, _)| {
+            let (parent, index, _) = node.data().kind.as_child()?;
+            Some((parent, Some(index as usize)))
+        })
+    }
+
+    /// Returns `true` if this node and `other` have the same green node at the same offset, i.e. if they represent
+    /// the same subtree at the same position.
+    ///
+    /// Unlike `==`, this is also `true` for the corresponding nodes of two trees that were created from the same
+    /// green tree. Nodes of shared, deduplicated subtrees at different positions have the same green node, but are
+    /// not at the same position.
+    #[inline]
+    pub fn ptr_eq_position<D2>(&self, other: &SyntaxNode<S, D2>) -> bool {
+        self.green().ptr_eq(other.green()) && self.text_range().start() == other.text_range().start()
+    }
+
+    /// Returns `true` if this node is a (transitive) parent of `other`.
+    ///
+    /// Nodes are compared by identity, so nodes of shared subtrees at different positions in the tree or nodes of
+    /// other trees are never ancestors of each other. A node is not its own ancestor, see
+    /// [`contains`](SyntaxNode::contains) for the inclusive check.
+    #[inline]
+    pub fn is_ancestor_of(&self, other: &SyntaxNode<S, D>) -> bool {
+        self.text_range().contains_range(other.text_range()) && other.ancestors().skip(1).any(|node| node == self)
+    }
+
+    /// Returns `true` if `other` is a (transitive) parent of this node.
+    ///
+    /// This is the inverse of [`is_ancestor_of`](SyntaxNode::is_ancestor_of).
+    #[inline]
+    pub fn is_descendant_of(&self, other: &SyntaxNode<S, D>) -> bool {
+        other.is_ancestor_of(self)
+    }
+
+    /// Returns `true` if `other` is in the subtree of this node, i.e. if it is this node or one of its
+    /// descendants.
+    #[inline]
+    pub fn contains(&self, other: &SyntaxNode<S, D>) -> bool {
+        self == other || self.is_ancestor_of(other)
+    }
+
+    /// The range this node covers in the source text, relative to the start of `ancestor`.
+    ///
+    /// This is what the range of this node would be if the subtree of `ancestor` were a tree of its own, for example
+    /// after rebuilding it from its green node with [`new_root`](SyntaxNode::new_root). Returns `None` if `ancestor`
+    /// is neither this node nor one of its ancestors, see [`contains`](SyntaxNode::contains).
+    #[inline]
+    pub fn range_in(&self, ancestor: &SyntaxNode<S, D>) -> Option<TextRange> {
+        if !ancestor.contains(self) {
+            return None;
+        }
+        Some(self.text_range() - ancestor.text_range().start())
+    }
+
     /// Returns an iterator over all nodes that are children of this node.
     ///
+    /// Red children are created lazily on first access and then persisted, so repeated traversals return
+    /// references to the same nodes (which compare equal) instead of allocating new ones.
+    ///
     /// If you want to also consider leafs, see [`children_with_tokens`](SyntaxNode::children_with_tokens).
     #[inline]
     pub fn children(&self) -> SyntaxNodeChildren<'_, S, D> {
         SyntaxNodeChildren::new(self)
     }
 
+    /// Returns an iterator over all child nodes of this node that have the given `kind`.
+    ///
+    /// Only the direct children of this node are considered, tokens are skipped.
+    #[inline]
+    pub fn child_nodes(&self, kind: S) -> impl Iterator<Item = &SyntaxNode<S, D>> {
+        self.children().filter(move |child| child.kind() == kind)
+    }
+
+    /// The first child node of this node that has the given `kind`, if any.
+    ///
+    /// Only the direct children of this node are considered, tokens are skipped.
+    #[inline]
+    pub fn child_node(&self, kind: S) -> Option<&SyntaxNode<S, D>> {
+        self.child_nodes(kind).next()
+    }
+
     /// Returns an iterator over child elements of this node, including tokens.
+    ///
+    /// This is also the iterator used when iterating over a `&SyntaxNode` directly, as in `for child in &node`.
     #[inline]
     pub fn children_with_tokens(&self) -> SyntaxElementChildren<'_, S, D> {
         SyntaxElementChildren::new(self)
     }
 
+    /// Returns an iterator over child elements of this node, including tokens, together with the absolute offset at
+    /// which each child starts.
+    ///
+    /// The offsets are accumulated while iterating and are equal to `child.text_range().start()`.
+    #[inline]
+    pub fn children_with_offsets(&self) -> impl Iterator<Item = (SyntaxElementRef<'_, S, D>, TextSize)> {
+        self.green()
+            .children_from(0, self.text_range().start())
+            .map(move |(element, (index, offset))| (self.get_or_add_element(element, index, offset), offset))
+    }
+
+    /// The child element of this node at the given `index` among all its children, including tokens, or `None` if
+    /// this node has fewer children.
+    ///
+    /// If the child has been accessed before, this takes constant time. Otherwise, its offset is computed by summing
+    /// the lengths of the preceding children in the green tree, which takes time linear in `index`, but does not
+    /// require creating any other children. To visit many children in order, use
+    /// [`children_with_offsets`](SyntaxNode::children_with_offsets) instead.
+    #[inline]
+    pub fn child(&self, index: usize) -> Option<SyntaxElementRef<'_, S, D>> {
+        let element = self.green().child(index)?;
+        if let Some(child) = self.read(index) {
+            return Some(child);
+        }
+        let offset = self.text_range().start()
+            + self
+                .green()
+                .iter()
+                .take(index)
+                .map(|child| child.as_ref().text_len())
+                .sum::<TextSize>();
+        Some(self.get_or_add_element(element, index, offset))
+    }
+
     /// The first child node of this node, if any.
     ///
     /// If you want to also consider leafs, see [`first_child_or_token`](SyntaxNode::first_child_or_token).
@@ -746,13 +1530,31 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
         self.last_child_or_token()?.last_token()
     }
 
+    /// Returns the first token after the end of this node, which may belong to any later part of the tree.
+    /// Returns `None` if there are no tokens after this node.
+    #[inline]
+    pub fn next_token(&self) -> Option<&SyntaxToken<S, D>> {
+        self.ancestors()
+            .flat_map(|node| node.siblings_with_tokens(Direction::Next).skip(1))
+            .find_map(|element| element.first_token())
+    }
+
+    /// Returns the last token before the start of this node, which may belong to any earlier part of the tree.
+    /// Returns `None` if there are no tokens before this node, for example if it is at the start of the document.
+    #[inline]
+    pub fn prev_token(&self) -> Option<&SyntaxToken<S, D>> {
+        self.ancestors()
+            .flat_map(|node| node.siblings_with_tokens(Direction::Prev).skip(1))
+            .find_map(|element| element.last_token())
+    }
+
     /// Returns an iterator over all sibling nodes of this node in the given `direction`, i.e. all of
     /// this node's parent's child nodes (!) from this node on to the left or the right. The first
     /// item in the iterator will always be this node.
     ///
     /// If you want to also consider leafs, see [`siblings_with_tokens`](SyntaxNode::siblings_with_tokens).
     #[inline]
-    pub fn siblings(&self, direction: Direction) -> impl Iterator<Item = &SyntaxNode<S, D>> {
+    pub fn siblings(&self, direction: Direction) -> impl FusedIterator<Item = &SyntaxNode<S, D>> {
         iter::successors(Some(self), move |node| match direction {
             Direction::Next => node.next_sibling(),
             Direction::Prev => node.prev_sibling(),
@@ -763,7 +1565,7 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
     /// node's parent's children from this node on to the left or the right.
     /// The first item in the iterator will always be this node.
     #[inline]
-    pub fn siblings_with_tokens(&self, direction: Direction) -> impl Iterator<Item = SyntaxElementRef<'_, S, D>> {
+    pub fn siblings_with_tokens(&self, direction: Direction) -> impl FusedIterator<Item = SyntaxElementRef<'_, S, D>> {
         let me: SyntaxElementRef<'_, S, D> = self.into();
         iter::successors(Some(me), move |el| match direction {
             Direction::Next => el.next_sibling_or_token(),
@@ -771,11 +1573,53 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
         })
     }
 
+    /// Returns an iterator over the children of this node (including tokens) that are located strictly between the
+    /// children `first` and `last`, for example the items between two delimiters.
+    ///
+    /// The iterator is empty if `first` and `last` are not both children of this node, or if `last` does not come
+    /// after `first`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "1");
+    /// builder.token(Int, "2");
+    /// builder.static_token(Plus);
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    ///
+    /// let first = root.first_child_or_token().unwrap();
+    /// let last = root.last_child_or_token().unwrap();
+    /// let between: Vec<_> = root
+    ///     .elements_between(first, last)
+    ///     .map(|el| el.kind())
+    ///     .collect();
+    /// assert_eq!(between, [Int, Int]);
+    /// ```
+    pub fn elements_between<'n>(
+        &'n self,
+        first: SyntaxElementRef<'n, S, D>,
+        last: SyntaxElementRef<'n, S, D>,
+    ) -> impl FusedIterator<Item = SyntaxElementRef<'n, S, D>> {
+        let is_child = |element: SyntaxElementRef<'n, S, D>| element.parent() == Some(self);
+        let between = iter::successors(first.next_sibling_or_token(), |el| el.next_sibling_or_token());
+        // only yield elements if `last` is actually found after `first`
+        let count = if is_child(first) && is_child(last) {
+            between.clone().position(|el| el == last)
+        } else {
+            None
+        };
+        between.take(count.unwrap_or(0))
+    }
+
     /// Returns an iterator over all nodes (!) in the subtree starting at this node, including this node.
     ///
     /// If you want to also consider leafs, see [`descendants_with_tokens`](SyntaxNode::descendants_with_tokens).
     #[inline]
-    pub fn descendants(&self) -> impl Iterator<Item = &SyntaxNode<S, D>> {
+    pub fn descendants(&self) -> impl FusedIterator<Item = &SyntaxNode<S, D>> {
         self.preorder().filter_map(|event| match event {
             WalkEvent::Enter(node) => Some(node),
             WalkEvent::Leave(_) => None,
@@ -784,63 +1628,240 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
 
     /// Returns an iterator over all elements in the subtree starting at this node, including this node.
     #[inline]
-    pub fn descendants_with_tokens(&self) -> impl Iterator<Item = SyntaxElementRef<'_, S, D>> {
+    pub fn descendants_with_tokens(&self) -> impl FusedIterator<Item = SyntaxElementRef<'_, S, D>> {
         self.preorder_with_tokens().filter_map(|event| match event {
             WalkEvent::Enter(it) => Some(it),
             WalkEvent::Leave(_) => None,
         })
     }
 
-    /// Traverse the subtree rooted at the current node (including the current
-    /// node) in preorder, excluding tokens.
-    #[inline(always)]
-    pub fn preorder(&self) -> impl Iterator<Item = WalkEvent<&SyntaxNode<S, D>>> {
-        iter::successors(Some(WalkEvent::Enter(self)), move |pos| {
-            let next = match pos {
-                WalkEvent::Enter(node) => match node.first_child() {
-                    Some(child) => WalkEvent::Enter(child),
-                    None => WalkEvent::Leave(*node),
+    /// Returns an iterator over all tokens in the subtree of this node, in document order.
+    #[inline]
+    pub fn tokens(&self) -> impl FusedIterator<Item = &SyntaxToken<S, D>> {
+        self.descendants_with_tokens().filter_map(|element| element.into_token())
+    }
+
+    /// Returns an iterator over all tokens in the subtree of this node, in document order, together with the offset
+    /// at which each token starts.
+    ///
+    /// The offsets are the same as the start of each token's [`text_range`](SyntaxToken::text_range), but are
+    /// computed by adding up the lengths of the preceding tokens while iterating.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "12");
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "3");
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    ///
+    /// let offsets: Vec<_> = root
+    ///     .tokens_with_offsets()
+    ///     .map(|(token, offset)| (token.kind(), u32::from(offset)))
+    ///     .collect();
+    /// assert_eq!(offsets, [(Int, 0), (Plus, 2), (Int, 3)]);
+    /// ```
+    pub fn tokens_with_offsets(&self) -> impl FusedIterator<Item = (&SyntaxToken<S, D>, TextSize)> {
+        let mut offset = self.text_range().start();
+        self.tokens().map(move |token| {
+            let start = offset;
+            offset += token.green().text_len();
+            (token, start)
+        })
+    }
+
+    /// Returns an iterator over all tokens in the subtree of this node in reverse document order, from the last token
+    /// to the first one.
+    ///
+    /// The tree is traversed lazily from the back, so this is suitable for scanning backwards from the end of a node
+    /// without visiting all of its tokens.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.start_node(Operation);
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    ///
+    /// let kinds: Vec<_> = root.tokens_rev().map(|token| token.kind()).collect();
+    /// assert_eq!(kinds, [Int, Plus, Int]);
+    /// ```
+    pub fn tokens_rev<'n>(&'n self) -> impl FusedIterator<Item = &'n SyntaxToken<S, D>> {
+        // the element preceding `element` in a reverse preorder traversal of the subtree, not counting descendants
+        let preceding = move |mut element: SyntaxElementRef<'n, S, D>| -> Option<SyntaxElementRef<'n, S, D>> {
+            loop {
+                if let Some(prev) = element.prev_sibling_or_token() {
+                    return Some(prev);
+                }
+                // NOTE: all elements are descendants of `self`, so they have a parent until `self` is reached
+                let parent = element.parent()?;
+                if parent == self {
+                    return None;
+                }
+                element = parent.into();
+            }
+        };
+        let mut next = self.last_child_or_token();
+        iter::from_fn(move || loop {
+            let element = next?;
+            match element {
+                NodeOrToken::Node(node) => match node.last_child_or_token() {
+                    Some(last) => next = Some(last),
+                    None => next = preceding(element),
                 },
-                WalkEvent::Leave(node) => {
-                    if node == &self {
-                        return None;
-                    }
-                    match node.next_sibling() {
-                        Some(sibling) => WalkEvent::Enter(sibling),
-                        None => WalkEvent::Leave(node.parent().unwrap()),
-                    }
+                NodeOrToken::Token(token) => {
+                    next = preceding(element);
+                    return Some(token);
                 }
+            }
+        })
+        .fuse()
+    }
+
+    /// Returns an iterator over the tokens in the subtree of this node that overlap `range`, in document order.
+    ///
+    /// Tokens that are only partially contained in `range` are included. If `range` is empty, the tokens that touch
+    /// its position are returned, like with [`token_at_offset`](SyntaxNode::token_at_offset). Subtrees outside of
+    /// `range` are not descended into, so this is cheaper than filtering [`tokens`](SyntaxNode::tokens) when
+    /// `range` is small.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::text::TextRange;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "12");
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "34");
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    ///
+    /// let tokens: Vec<_> = root
+    ///     .tokens_in_range(TextRange::new(1.into(), 3.into()))
+    ///     .map(|token| token.resolve_text(&interner))
+    ///     .collect();
+    /// assert_eq!(tokens, ["12", "+"]);
+    /// ```
+    pub fn tokens_in_range(&self, range: TextRange) -> impl Iterator<Item = &SyntaxToken<S, D>> {
+        let overlaps = move |element: TextRange| {
+            if range.is_empty() {
+                element.contains_inclusive(range.start())
+            } else {
+                element.start() < range.end() && range.start() < element.end()
+            }
+        };
+        let mut preorder = self.preorder_with_tokens();
+        iter::from_fn(move || loop {
+            let WalkEvent::Enter(element) = preorder.next()? else {
+                continue;
             };
-            Some(next)
+            let element_range = element.text_range();
+            if element_range.start() > range.end() || (element_range.start() == range.end() && !range.is_empty()) {
+                // all remaining elements come after `range`
+                return None;
+            }
+            if !overlaps(element_range) {
+                preorder.skip_subtree();
+                continue;
+            }
+            if let NodeOrToken::Token(token) = element {
+                return Some(token);
+            }
         })
     }
 
-    /// Traverse the subtree rooted at the current node (including the current
-    /// node) in preorder, including tokens.
-    #[inline(always)]
-    pub fn preorder_with_tokens(&self) -> impl Iterator<Item = WalkEvent<SyntaxElementRef<'_, S, D>>> {
-        let me = self.into();
-        iter::successors(Some(WalkEvent::Enter(me)), move |pos| {
-            let next = match pos {
-                WalkEvent::Enter(el) => match el {
-                    NodeOrToken::Node(node) => match node.first_child_or_token() {
-                        Some(child) => WalkEvent::Enter(child),
-                        None => WalkEvent::Leave((*node).into()),
-                    },
-                    NodeOrToken::Token(token) => WalkEvent::Leave((*token).into()),
-                },
-                WalkEvent::Leave(el) => {
-                    if el == &me {
-                        return None;
+    /// Returns the set of all distinct kinds of nodes and tokens in the subtree of this node, including the kind of
+    /// this node itself.
+    ///
+    /// This walks the underlying green tree in a single pass and does not create red nodes.
+    #[inline]
+    pub fn kinds_present(&self) -> HashSet<S>
+    where
+        S: Hash,
+    {
+        let mut kinds = HashSet::new();
+        let mut stack = vec![self.green()];
+        while let Some(node) = stack.pop() {
+            kinds.insert(S::from_raw(node.kind()));
+            for child in node.children() {
+                match child {
+                    NodeOrToken::Node(node) => stack.push(node),
+                    NodeOrToken::Token(token) => {
+                        kinds.insert(S::from_raw(token.kind()));
                     }
-                    match el.next_sibling_or_token() {
-                        Some(sibling) => WalkEvent::Enter(sibling),
-                        None => WalkEvent::Leave(el.parent().unwrap().into()),
+                }
+            }
+        }
+        kinds
+    }
+
+    /// Counts how often each kind of node and token occurs in the subtree of this node, including this node itself.
+    ///
+    /// Like [`kinds_present`](SyntaxNode::kinds_present), this walks the underlying green tree in a single pass and
+    /// does not create red nodes.
+    pub fn kind_histogram(&self) -> HashMap<S, usize>
+    where
+        S: Hash,
+    {
+        let mut histogram = HashMap::new();
+        let mut stack = vec![self.green()];
+        while let Some(node) = stack.pop() {
+            *histogram.entry(S::from_raw(node.kind())).or_default() += 1;
+            for child in node.children() {
+                match child {
+                    NodeOrToken::Node(node) => stack.push(node),
+                    NodeOrToken::Token(token) => {
+                        *histogram.entry(S::from_raw(token.kind())).or_default() += 1;
                     }
                 }
-            };
-            Some(next)
-        })
+            }
+        }
+        histogram
+    }
+
+    /// Compares the subtrees of this node and `other` structurally, like comparing their green nodes with `==`, except
+    /// that the children of nodes for which `is_unordered` returns `true` may appear in any order.
+    ///
+    /// The children of such nodes are compared as multisets: the nodes are equal if every child of one node can be
+    /// paired with a distinct child of the other node that is equal to it, again ignoring the order of children of
+    /// unordered nodes below. This is useful for nodes that represent sets, like a list of attributes whose order
+    /// has no meaning. Since children are paired by searching for a match, comparing unordered nodes takes time that
+    /// is quadratic in their number of children.
+    ///
+    /// Like for green nodes, the text of tokens is compared by its interned key, so both trees must have been built
+    /// with the same interner.
+    pub fn eq_unordered<D2>(&self, other: &SyntaxNode<S, D2>, is_unordered: impl Fn(S) -> bool) -> bool {
+        self.green().eq_unordered(other.green(), &is_unordered)
+    }
+
+    /// Traverse the subtree rooted at the current node (including the current
+    /// node) in preorder, excluding tokens.
+    ///
+    /// Use [`Preorder::skip_subtree`] to avoid descending into the children of a node.
+    #[inline(always)]
+    pub fn preorder(&self) -> Preorder<'_, S, D> {
+        Preorder::new(self)
+    }
+
+    /// Traverse the subtree rooted at the current node (including the current
+    /// node) in preorder, including tokens.
+    ///
+    /// Use [`PreorderWithTokens::skip_subtree`] to avoid descending into the children of a node.
+    #[inline(always)]
+    pub fn preorder_with_tokens(&self) -> PreorderWithTokens<'_, S, D> {
+        PreorderWithTokens::new(self)
     }
 
     /// Find a token in the subtree corresponding to this node, which covers the offset.
@@ -906,6 +1927,23 @@ impl<S: Syntax, D> SyntaxNode<S, D> {
             };
         }
     }
+
+    /// Return the deepest node in the current subtree that fully contains the range, for example to implement
+    /// "expand selection". Unlike [`covering_element`](SyntaxNode::covering_element), this never returns a token: if
+    /// the range lies within a single token, the token's parent is returned. If the range is empty and is contained
+    /// in two nodes, either one can be returned.
+    ///
+    /// Returns `None` if the range is not contained within the current node.
+    pub fn smallest_node_covering(&self, range: TextRange) -> Option<&SyntaxNode<S, D>> {
+        if !self.text_range().contains_range(range) {
+            return None;
+        }
+        let mut node = self;
+        while let Some(child) = node.children().find(|child| child.text_range().contains_range(range)) {
+            node = child;
+        }
+        Some(node)
+    }
 }
 
 #[cfg(feature = "serialize")]
@@ -936,12 +1974,45 @@ where
 }
 
 impl GreenNode {
+    fn eq_unordered<S: Syntax>(&self, other: &GreenNode, is_unordered: &impl Fn(S) -> bool) -> bool {
+        if self.ptr_eq(other) {
+            return true;
+        }
+        if self.kind() != other.kind()
+            || self.text_len() != other.text_len()
+            || self.children().len() != other.children().len()
+        {
+            return false;
+        }
+        let element_eq = |left: GreenElementRef<'_>, right: GreenElementRef<'_>| match (left, right) {
+            (NodeOrToken::Node(left), NodeOrToken::Node(right)) => left.eq_unordered(right, is_unordered),
+            (NodeOrToken::Token(left), NodeOrToken::Token(right)) => left == right,
+            _ => false,
+        };
+        if !is_unordered(S::from_raw(self.kind())) {
+            return self
+                .children()
+                .zip(other.children())
+                .all(|(left, right)| element_eq(left, right));
+        }
+        let mut unmatched: Vec<_> = other.children().collect();
+        self.children().all(
+            |left| match unmatched.iter().position(|&right| element_eq(left, right)) {
+                Some(index) => {
+                    unmatched.swap_remove(index);
+                    true
+                }
+                None => false,
+            },
+        )
+    }
+
     #[inline(always)]
     fn children_from(
         &self,
         start_index: usize,
         mut offset: TextSize,
-    ) -> impl Iterator<Item = (GreenElementRef, (usize, TextSize))> {
+    ) -> impl Iterator<Item = (GreenElementRef<'_>, (usize, TextSize))> {
         self.children()
             .skip(start_index)
             .enumerate()
@@ -957,7 +2028,7 @@ impl GreenNode {
         &self,
         end_index: usize,
         mut offset: TextSize,
-    ) -> impl Iterator<Item = (GreenElementRef, (usize, TextSize))> {
+    ) -> impl Iterator<Item = (GreenElementRef<'_>, (usize, TextSize))> {
         self.children()
             .take(end_index)
             .rev()
@@ -978,3 +2049,20 @@ fn filter_nodes<'a, I: Iterator<Item = (GreenElementRef<'a>, T)>, T>(
         NodeOrToken::Token(_) => None,
     })
 }
+
+/// Writes `text` into `target` as a JSON string, including the surrounding quotes.
+fn write_json_string(text: &str, target: &mut impl fmt::Write) -> fmt::Result {
+    target.write_char('"')?;
+    for c in text.chars() {
+        match c {
+            '"' => target.write_str("\\\"")?,
+            '\\' => target.write_str("\\\\")?,
+            '\n' => target.write_str("\\n")?,
+            '\r' => target.write_str("\\r")?,
+            '\t' => target.write_str("\\t")?,
+            c if c < ' ' => write!(target, "\\u{:04x}", c as u32)?,
+            c => target.write_char(c)?,
+        }
+    }
+    target.write_char('"')
+}
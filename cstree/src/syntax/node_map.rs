@@ -0,0 +1,112 @@
+use fxhash::FxHashMap;
+
+use crate::Syntax;
+
+use super::SyntaxNode;
+
+/// A map from syntax nodes to values of type `V`, for attaching information to nodes without storing it as node
+/// data.
+///
+/// Nodes are compared by identity, like with `==` on [`SyntaxNode`]s, so the value of a node is only found through
+/// that same node and not through structurally equal nodes at another position or in another tree. Compared to
+/// [`set_data`](SyntaxNode::set_data), this does not require the tree to have a particular data type, different
+/// passes over the same tree can each use their own map, and all values can be dropped at once with
+/// [`clear`](NodeMap::clear).
+///
+/// The map holds on to the nodes it contains, which keeps the trees they belong to alive until they are removed
+/// from the map or the map is dropped.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::syntax::NodeMap;
+///
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.start_node(Operation);
+/// builder.token(Int, "1");
+/// builder.finish_node();
+/// builder.finish_node();
+/// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+///
+/// let mut depths = NodeMap::new();
+/// for node in root.descendants() {
+///     depths.insert(node, node.ancestors().count() - 1);
+/// }
+/// assert_eq!(depths.get(root.first_child().unwrap()), Some(&1));
+/// ```
+#[derive(Debug, Clone)]
+pub struct NodeMap<S: Syntax, D: 'static, V> {
+    values: FxHashMap<SyntaxNode<S, D>, V>,
+}
+
+impl<S: Syntax, D, V> NodeMap<S, D, V> {
+    /// Creates a new, empty map.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            values: FxHashMap::default(),
+        }
+    }
+
+    /// Associates `value` with `node`, returning the value that was previously associated with `node`, if any.
+    #[inline]
+    pub fn insert(&mut self, node: &SyntaxNode<S, D>, value: V) -> Option<V> {
+        self.values.insert(node.clone(), value)
+    }
+
+    /// Returns a reference to the value associated with `node`, if any.
+    #[inline]
+    pub fn get(&self, node: &SyntaxNode<S, D>) -> Option<&V> {
+        self.values.get(node)
+    }
+
+    /// Returns a mutable reference to the value associated with `node`, if any.
+    #[inline]
+    pub fn get_mut(&mut self, node: &SyntaxNode<S, D>) -> Option<&mut V> {
+        self.values.get_mut(node)
+    }
+
+    /// Returns `true` if there is a value associated with `node`.
+    #[inline]
+    pub fn contains_node(&self, node: &SyntaxNode<S, D>) -> bool {
+        self.values.contains_key(node)
+    }
+
+    /// Removes the value associated with `node` from the map and returns it, if any.
+    #[inline]
+    pub fn remove(&mut self, node: &SyntaxNode<S, D>) -> Option<V> {
+        self.values.remove(node)
+    }
+
+    /// The number of nodes with an associated value.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if no node has an associated value.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Removes all values from the map.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    /// Returns an iterator over all nodes in the map and their associated values, in arbitrary order.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&SyntaxNode<S, D>, &V)> {
+        self.values.iter()
+    }
+}
+
+impl<S: Syntax, D, V> Default for NodeMap<S, D, V> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
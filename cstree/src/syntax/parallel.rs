@@ -0,0 +1,42 @@
+//! Parallel traversals of syntax trees with [`rayon`].
+
+use rayon::iter::{walk_tree_postfix, ParallelIterator};
+
+use crate::Syntax;
+
+use super::SyntaxNode;
+
+impl<S: Syntax, D> SyntaxNode<S, D> {
+    /// Returns a [parallel iterator](ParallelIterator) over all nodes in the subtree of this node, including this
+    /// node itself.
+    ///
+    /// This visits exactly the same nodes as [`descendants`](SyntaxNode::descendants), but not in a defined order:
+    /// subtrees are split up between the threads of the current `rayon` thread pool. Red nodes are created on demand
+    /// as usual, so after the traversal they are available to all threads.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use rayon::prelude::*;
+    ///
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// for _ in 0..100 {
+    ///     builder.start_node(Operation);
+    ///     builder.token(Int, "1");
+    ///     builder.finish_node();
+    /// }
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    ///
+    /// let operations = root
+    ///     .par_descendants()
+    ///     .filter(|node| node.kind() == Operation)
+    ///     .count();
+    /// assert_eq!(operations, 100);
+    /// ```
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "rayon")))]
+    pub fn par_descendants(&self) -> impl ParallelIterator<Item = &SyntaxNode<S, D>> {
+        walk_tree_postfix(self, |node| node.children())
+    }
+}
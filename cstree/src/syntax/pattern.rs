@@ -0,0 +1,134 @@
+//! Lightweight structural matching of syntax trees.
+
+use crate::Syntax;
+
+use super::{SyntaxElementRef, SyntaxNode};
+
+/// A description of the shape of a syntax element, for checking the structure of a tree concisely, for example in
+/// tests.
+///
+/// A pattern is matched against a sequence of elements, such as the children (including tokens) of a node. Most
+/// patterns match a single element, while [`Seq`](Pattern::Seq) and [`Repeat`](Pattern::Repeat) combine patterns to
+/// match several consecutive elements. Use [`SyntaxNode::matches`] to match a node against a pattern.
+///
+/// This is not a query language: patterns cannot bind or extract the elements they match. Matching backtracks over
+/// the possible ways of repeating patterns, so it is meant for the small trees of tests rather than for large inputs.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::syntax::Pattern;
+///
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.token(Int, "1");
+/// builder.static_token(Plus);
+/// builder.token(Int, "2");
+/// builder.static_token(Plus);
+/// builder.token(Int, "3");
+/// builder.finish_node();
+/// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+///
+/// // an `Int`, followed by any number of `Int`s that are each preceded by a `Plus`
+/// let sum = Pattern::Node(
+///     Root,
+///     vec![
+///         Pattern::Kind(Int),
+///         Pattern::Repeat(Box::new(Pattern::Seq(vec![
+///             Pattern::Kind(Plus),
+///             Pattern::Kind(Int),
+///         ]))),
+///     ],
+/// );
+/// assert!(root.matches(&sum));
+/// assert!(!root.matches(&Pattern::Node(Root, vec![Pattern::Kind(Int)])));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern<S: Syntax> {
+    /// Matches any single node or token.
+    Any,
+    /// Matches a single node or token of the given kind, regardless of its children.
+    Kind(S),
+    /// Matches a single node of the given kind whose children, including tokens, match the given patterns in
+    /// sequence.
+    Node(S, Vec<Pattern<S>>),
+    /// Matches consecutive elements that match the given patterns in sequence.
+    Seq(Vec<Pattern<S>>),
+    /// Matches any number of consecutive repetitions of the given pattern, including none.
+    Repeat(Box<Pattern<S>>),
+}
+
+impl<S: Syntax> Pattern<S> {
+    /// Returns all positions at which a match of this pattern against `elements` that starts at `start` can end, in
+    /// ascending order and without duplicates.
+    fn match_ends<D>(&self, elements: &[SyntaxElementRef<'_, S, D>], start: usize) -> Vec<usize> {
+        match self {
+            Pattern::Any => (start < elements.len()).then_some(start + 1).into_iter().collect(),
+            Pattern::Kind(kind) => elements
+                .get(start)
+                .filter(|element| element.kind() == *kind)
+                .map(|_| start + 1)
+                .into_iter()
+                .collect(),
+            Pattern::Node(..) => elements
+                .get(start)
+                .and_then(|element| element.as_node())
+                .filter(|node| node.matches(self))
+                .map(|_| start + 1)
+                .into_iter()
+                .collect(),
+            Pattern::Seq(patterns) => Self::seq_match_ends(patterns, elements, start),
+            Pattern::Repeat(pattern) => {
+                let mut ends = vec![start];
+                let mut frontier = vec![start];
+                while let Some(end) = frontier.pop() {
+                    for next in pattern.match_ends(elements, end) {
+                        // repetitions that match nothing would never make progress
+                        if !ends.contains(&next) {
+                            ends.push(next);
+                            frontier.push(next);
+                        }
+                    }
+                }
+                ends.sort_unstable();
+                ends
+            }
+        }
+    }
+
+    /// Like [`match_ends`](Pattern::match_ends), for matching all `patterns` in sequence.
+    fn seq_match_ends<D>(patterns: &[Self], elements: &[SyntaxElementRef<'_, S, D>], start: usize) -> Vec<usize> {
+        patterns.iter().fold(vec![start], |ends, pattern| {
+            let mut next: Vec<usize> = ends
+                .into_iter()
+                .flat_map(|end| pattern.match_ends(elements, end))
+                .collect();
+            next.sort_unstable();
+            next.dedup();
+            next
+        })
+    }
+}
+
+impl<S: Syntax, D> SyntaxNode<S, D> {
+    /// Returns `true` if this node, as a single element, matches `pattern`.
+    ///
+    /// See [`Pattern`] for how patterns are matched.
+    pub fn matches(&self, pattern: &Pattern<S>) -> bool {
+        match pattern {
+            Pattern::Any => true,
+            Pattern::Kind(kind) => self.kind() == *kind,
+            Pattern::Node(kind, patterns) => {
+                if self.kind() != *kind {
+                    return false;
+                }
+                let children: Vec<_> = self.children_with_tokens().collect();
+                Pattern::seq_match_ends(patterns, &children, 0).contains(&children.len())
+            }
+            Pattern::Seq(_) | Pattern::Repeat(_) => {
+                let element: SyntaxElementRef<'_, S, D> = self.into();
+                pattern.match_ends(&[element], 0).contains(&1)
+            }
+        }
+    }
+}
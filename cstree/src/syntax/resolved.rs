@@ -4,7 +4,8 @@
 //! This means they can implement `Debug` and `Display` and be (de-)serializable by default.
 
 use std::{
-    fmt,
+    fmt, io,
+    iter::FusedIterator,
     ops::{Deref, DerefMut},
     sync::Arc as StdArc,
 };
@@ -13,7 +14,7 @@ use text_size::{TextRange, TextSize};
 
 use crate::{
     green::GreenNode,
-    interning::{Resolver, TokenKey},
+    interning::{Resolver, TokenInterner, TokenKey},
     syntax::*,
     traversal::*,
     util::*,
@@ -183,6 +184,35 @@ impl<S: Syntax, D> ResolvedNode<S, D> {
     pub fn text(&self) -> SyntaxText<'_, '_, dyn Resolver<TokenKey>, S, D> {
         SyntaxText::new(self, &**self.resolver())
     }
+
+    /// Returns an iterator over the text of every token in the subtree of this node, in document order, using the
+    /// resolver associated with this tree. See [`SyntaxNode::text_chunks`].
+    #[inline]
+    pub fn text_chunks(&self) -> impl FusedIterator<Item = &str> {
+        self.syntax.text_chunks(&**self.resolver())
+    }
+
+    /// The number of `char`s in the source text of this node, using the resolver associated with this tree. See
+    /// [`SyntaxNode::char_len`].
+    #[inline]
+    pub fn char_len(&self) -> usize {
+        self.syntax.char_len(&**self.resolver())
+    }
+
+    /// Returns a reader that yields the source text of this node, using the resolver associated with this tree. See
+    /// [`SyntaxNode::text_reader`].
+    #[inline]
+    pub fn text_reader(&self) -> impl io::Read + '_ {
+        self.syntax.text_reader(&**self.resolver())
+    }
+
+    /// If all source text covered by this node comes from a single token, returns that text without allocating.
+    ///
+    /// See [`SyntaxNode::as_str`] for details.
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        self.syntax.as_str(&**self.resolver())
+    }
 }
 
 impl<S: Syntax, D> fmt::Debug for ResolvedNode<S, D> {
@@ -206,6 +236,13 @@ impl<S: Syntax, D> ResolvedToken<S, D> {
             .or_else(|| self.green().text(&**self.resolver()))
             .unwrap()
     }
+
+    /// The number of `char`s in the source text of this token, using the resolver associated with this tree. See
+    /// [`SyntaxToken::char_len`].
+    #[inline]
+    pub fn char_len(&self) -> usize {
+        self.text().chars().count()
+    }
 }
 
 impl<S: Syntax, D> fmt::Debug for ResolvedToken<S, D> {
@@ -279,6 +316,23 @@ impl<S: Syntax, D> ResolvedNode<S, D> {
         SyntaxNode::new_root_with_resolver(green, resolver)
     }
 
+    /// Associates the tree this node belongs to with the source file identified by `file_id`.
+    ///
+    /// See [`SyntaxNode::with_file_id`].
+    #[inline]
+    pub fn with_file_id(self, file_id: FileId) -> Self {
+        Self {
+            syntax: self.syntax.with_file_id(file_id),
+        }
+    }
+
+    /// Copies the subtree of this node into a standalone tree, with a new interner that only contains the text of
+    /// the tokens in the subtree. See [`SyntaxNode::extract`].
+    #[inline]
+    pub fn extract(&self) -> (GreenNode, TokenInterner) {
+        self.syntax.extract(self.resolver().as_ref())
+    }
+
     /// Always returns `Some(self)`.
     ///
     /// This method mostly exists to allow the convenience of being agnostic over [`SyntaxNode`] vs [`ResolvedNode`].
@@ -311,24 +365,83 @@ impl<S: Syntax, D> ResolvedNode<S, D> {
 
     /// Returns an iterator along the chain of parents of this node.
     #[inline]
-    pub fn ancestors(&self) -> impl Iterator<Item = &Self> {
+    pub fn ancestors(&self) -> impl FusedIterator<Item = &Self> {
         forward!(self.syntax.ancestors())
     }
 
+    /// The nearest ancestor of this node with the given `kind`, not counting the node itself.
+    ///
+    /// See [`SyntaxNode::ancestor_of_kind`].
+    #[inline]
+    pub fn ancestor_of_kind(&self, kind: S) -> Option<&Self> {
+        forward!(self.syntax.ancestor_of_kind(kind))
+    }
+
+    /// The nearest node with the given `kind` on the chain of parents of this node, starting with the node itself.
+    ///
+    /// See [`SyntaxNode::self_or_ancestor_of_kind`].
+    #[inline]
+    pub fn self_or_ancestor_of_kind(&self, kind: S) -> Option<&Self> {
+        forward!(self.syntax.self_or_ancestor_of_kind(kind))
+    }
+
+    /// Returns an iterator along the chain of parents of this node, where each ancestor is paired with the index of
+    /// the child it was reached from (`None` for this node itself).
+    #[inline]
+    pub fn ancestors_with_index(&self) -> impl Iterator<Item = (&Self, Option<usize>)> {
+        // safety: if we're starting from a `ResolvedXY`, then the tree must have a resolver
+        self.syntax
+            .ancestors_with_index()
+            .map(|(node, index)| (unsafe { Self::coerce_ref(node) }, index))
+    }
+
     /// Returns an iterator over all nodes that are children of this node.
     ///
     /// If you want to also consider leafs, see [`children_with_tokens`](ResolvedNode::children_with_tokens).
     #[inline]
-    pub fn children(&self) -> impl Iterator<Item = &Self> {
+    pub fn children(&self) -> impl ExactSizeIterator<Item = &Self> + FusedIterator {
         forward!(self.syntax.children())
     }
 
+    /// Returns an iterator over all child nodes of this node that have the given `kind`.
+    ///
+    /// Only the direct children of this node are considered, tokens are skipped.
+    #[inline]
+    pub fn child_nodes(&self, kind: S) -> impl Iterator<Item = &Self> {
+        forward!(self.syntax.child_nodes(kind))
+    }
+
+    /// The first child node of this node that has the given `kind`, if any.
+    ///
+    /// Only the direct children of this node are considered, tokens are skipped.
+    #[inline]
+    pub fn child_node(&self, kind: S) -> Option<&Self> {
+        forward!(self.syntax.child_node(kind))
+    }
+
     /// Returns an iterator over child elements of this node, including tokens.
     #[inline]
-    pub fn children_with_tokens(&self) -> impl Iterator<Item = ResolvedElementRef<'_, S, D>> {
+    pub fn children_with_tokens(&self) -> impl ExactSizeIterator<Item = ResolvedElementRef<'_, S, D>> + FusedIterator {
         forward_as_elem!(self.syntax.children_with_tokens())
     }
 
+    /// Returns an iterator over child elements of this node, including tokens, together with the absolute offset at
+    /// which each child starts.
+    #[inline]
+    pub fn children_with_offsets(&self) -> impl Iterator<Item = (ResolvedElementRef<'_, S, D>, TextSize)> {
+        // safety: if we're starting from a `ResolvedXY`, then the tree must have a resolver
+        self.syntax
+            .children_with_offsets()
+            .map(|(child, offset)| (unsafe { ResolvedElementRef::coerce_ref(child) }, offset))
+    }
+
+    /// The child element of this node at the given `index` among all its children, including tokens, or `None` if
+    /// this node has fewer children.
+    #[inline]
+    pub fn child(&self, index: usize) -> Option<ResolvedElementRef<'_, S, D>> {
+        forward_as_elem!(self.syntax.child(index))
+    }
+
     /// The first child node of this node, if any.
     ///
     /// If you want to also consider leafs, see [`first_child_or_token`](ResolvedNode::first_child_or_token).
@@ -432,13 +545,25 @@ impl<S: Syntax, D> ResolvedNode<S, D> {
         forward_token!(self.syntax.last_token())
     }
 
+    /// Returns the first token after the end of this node, which may belong to any later part of the tree.
+    #[inline]
+    pub fn next_token(&self) -> Option<&ResolvedToken<S, D>> {
+        forward_token!(self.syntax.next_token())
+    }
+
+    /// Returns the last token before the start of this node, which may belong to any earlier part of the tree.
+    #[inline]
+    pub fn prev_token(&self) -> Option<&ResolvedToken<S, D>> {
+        forward_token!(self.syntax.prev_token())
+    }
+
     /// Returns an iterator over all sibling nodes of this node in the given `direction`, i.e. all of
     /// this node's parent's child nodes (!) from this node on to the left or the right. The first
     /// item in the iterator will always be this node.
     ///
     /// If you want to also consider leafs, see [`siblings_with_tokens`](ResolvedNode::siblings_with_tokens).
     #[inline]
-    pub fn siblings(&self, direction: Direction) -> impl Iterator<Item = &ResolvedNode<S, D>> {
+    pub fn siblings(&self, direction: Direction) -> impl FusedIterator<Item = &ResolvedNode<S, D>> {
         forward!(self.syntax.siblings(direction))
     }
 
@@ -446,7 +571,10 @@ impl<S: Syntax, D> ResolvedNode<S, D> {
     /// node's parent's children from this node on to the left or the right.
     /// The first item in the iterator will always be this node.
     #[inline]
-    pub fn siblings_with_tokens(&self, direction: Direction) -> impl Iterator<Item = ResolvedElementRef<'_, S, D>> {
+    pub fn siblings_with_tokens(
+        &self,
+        direction: Direction,
+    ) -> impl FusedIterator<Item = ResolvedElementRef<'_, S, D>> {
         forward_as_elem!(self.syntax.siblings_with_tokens(direction))
     }
 
@@ -454,16 +582,38 @@ impl<S: Syntax, D> ResolvedNode<S, D> {
     ///
     /// If you want to also consider leafs, see [`descendants_with_tokens`](ResolvedNode::descendants_with_tokens).
     #[inline]
-    pub fn descendants(&self) -> impl Iterator<Item = &ResolvedNode<S, D>> {
+    pub fn descendants(&self) -> impl FusedIterator<Item = &ResolvedNode<S, D>> {
         forward!(self.syntax.descendants())
     }
 
     /// Returns an iterator over all elements in the subtree starting at this node, including this node.
     #[inline]
-    pub fn descendants_with_tokens(&self) -> impl Iterator<Item = ResolvedElementRef<'_, S, D>> {
+    pub fn descendants_with_tokens(&self) -> impl FusedIterator<Item = ResolvedElementRef<'_, S, D>> {
         forward_as_elem!(self.syntax.descendants_with_tokens())
     }
 
+    /// Returns an iterator over all tokens in the subtree of this node, in document order.
+    #[inline]
+    pub fn tokens(&self) -> impl FusedIterator<Item = &ResolvedToken<S, D>> {
+        forward_token!(self.syntax.tokens())
+    }
+
+    /// Returns an iterator over all tokens in the subtree of this node in reverse document order.
+    ///
+    /// See [`SyntaxNode::tokens_rev`].
+    #[inline]
+    pub fn tokens_rev(&self) -> impl FusedIterator<Item = &ResolvedToken<S, D>> {
+        forward_token!(self.syntax.tokens_rev())
+    }
+
+    /// Returns an iterator over the tokens in the subtree of this node that overlap `range`, in document order.
+    ///
+    /// See [`SyntaxNode::tokens_in_range`] for details.
+    #[inline]
+    pub fn tokens_in_range(&self, range: TextRange) -> impl Iterator<Item = &ResolvedToken<S, D>> {
+        forward_token!(self.syntax.tokens_in_range(range))
+    }
+
     /// Traverse the subtree rooted at the current node (including the current
     /// node) in preorder, excluding tokens.
     #[inline(always)]
@@ -497,6 +647,14 @@ impl<S: Syntax, D> ResolvedNode<S, D> {
     pub fn covering_element(&self, range: TextRange) -> ResolvedElementRef<'_, S, D> {
         unsafe { ResolvedElementRef::coerce_ref(self.syntax.covering_element(range)) }
     }
+
+    /// Return the deepest node in the current subtree that fully contains the range, never returning a token.
+    ///
+    /// See [`SyntaxNode::smallest_node_covering`].
+    #[inline]
+    pub fn smallest_node_covering(&self, range: TextRange) -> Option<&ResolvedNode<S, D>> {
+        forward!(self.syntax.smallest_node_covering(range))
+    }
 }
 
 impl<S: Syntax, D> ResolvedToken<S, D> {
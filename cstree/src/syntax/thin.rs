@@ -0,0 +1,302 @@
+//! Lightweight, read-only views of syntax nodes for downward traversals.
+
+use std::marker::PhantomData;
+
+use crate::{
+    green::{GreenNode, GreenNodeChildren, GreenToken},
+    interning::{Resolver, TokenKey},
+    text::{TextRange, TextSize},
+    util::NodeOrToken,
+    RawSyntaxKind, Syntax,
+};
+
+use super::SyntaxNode;
+
+/// A child of a [`ThinNode`], which is either a [`ThinNode`] or a [`ThinToken`].
+pub type ThinElement<'a, S> = NodeOrToken<ThinNode<'a, S>, ThinToken<'a, S>>;
+
+/// A read-only view of a syntax node that does not know its parent.
+///
+/// Unlike [`SyntaxNode`]s, thin nodes are not allocated or cached and do not keep track of their position in the
+/// tree apart from their text offset. They are just a reference to a green node, so they can be created, copied and
+/// iterated over very cheaply. The price for this is that there is no way to go back up the tree from a thin node:
+/// thin nodes have no [`parent`](SyntaxNode::parent) and no siblings, and there is no way to attach data to them.
+/// This makes them suited for bulk passes over a tree that only ever descend into it.
+///
+/// Obtain a thin node from any [`SyntaxNode`] with [`SyntaxNode::thin`].
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.start_node(Operation);
+/// builder.token(Int, "1");
+/// builder.static_token(Plus);
+/// builder.token(Int, "2");
+/// builder.finish_node();
+/// builder.finish_node();
+/// let (tree, cache) = builder.finish();
+/// let interner = cache.unwrap().into_interner().unwrap();
+/// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+///
+/// let operation = root.thin().children().next().unwrap();
+/// assert_eq!(operation.kind(), Operation);
+/// let ints: Vec<_> = operation
+///     .descendant_tokens()
+///     .filter(|token| token.kind() == Int)
+///     .map(|token| token.resolve_text(&interner))
+///     .collect();
+/// assert_eq!(ints, ["1", "2"]);
+/// ```
+#[derive(Debug)]
+pub struct ThinNode<'a, S: Syntax> {
+    green:   &'a GreenNode,
+    offset:  TextSize,
+    _syntax: PhantomData<S>,
+}
+
+/// A read-only view of a syntax token that does not know its parent.
+///
+/// See [`ThinNode`] for details.
+#[derive(Debug)]
+pub struct ThinToken<'a, S: Syntax> {
+    green:   &'a GreenToken,
+    offset:  TextSize,
+    _syntax: PhantomData<S>,
+}
+
+impl<S: Syntax> Clone for ThinNode<'_, S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: Syntax> Copy for ThinNode<'_, S> {}
+
+impl<S: Syntax> Clone for ThinToken<'_, S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: Syntax> Copy for ThinToken<'_, S> {}
+
+impl<S: Syntax, D> SyntaxNode<S, D> {
+    /// Returns a [`ThinNode`] for this node, which can be used to traverse the subtree of this node without
+    /// creating any [`SyntaxNode`]s.
+    #[inline]
+    pub fn thin(&self) -> ThinNode<'_, S> {
+        ThinNode::new(self.green(), self.text_range().start())
+    }
+}
+
+impl<'a, S: Syntax> ThinNode<'a, S> {
+    /// Creates a thin node for the green node `green`, whose text starts at `offset`.
+    #[inline]
+    pub fn new(green: &'a GreenNode, offset: TextSize) -> Self {
+        Self {
+            green,
+            offset,
+            _syntax: PhantomData,
+        }
+    }
+
+    /// The green node underlying this node.
+    #[inline]
+    pub fn green(&self) -> &'a GreenNode {
+        self.green
+    }
+
+    /// The internal representation of the kind of this node.
+    #[inline]
+    pub fn syntax_kind(&self) -> RawSyntaxKind {
+        self.green.kind()
+    }
+
+    /// The kind of this node in terms of your language.
+    #[inline]
+    pub fn kind(&self) -> S {
+        S::from_raw(self.syntax_kind())
+    }
+
+    /// The range this node covers in the source text.
+    #[inline]
+    pub fn text_range(&self) -> TextRange {
+        TextRange::at(self.offset, self.green.text_len())
+    }
+
+    /// Returns an iterator over all child nodes of this node, skipping tokens.
+    #[inline]
+    pub fn children(&self) -> impl Iterator<Item = ThinNode<'a, S>> {
+        self.children_with_tokens().filter_map(NodeOrToken::into_node)
+    }
+
+    /// Returns an iterator over all children of this node, including tokens.
+    #[inline]
+    pub fn children_with_tokens(&self) -> ThinElementChildren<'a, S> {
+        ThinElementChildren {
+            inner:   self.green.children(),
+            offset:  self.offset,
+            _syntax: PhantomData,
+        }
+    }
+
+    /// The first child node of this node, if any.
+    #[inline]
+    pub fn first_child(&self) -> Option<ThinNode<'a, S>> {
+        self.children().next()
+    }
+
+    /// Returns an iterator over all nodes in the subtree of this node, including this node, in preorder.
+    #[inline]
+    pub fn descendants(&self) -> impl Iterator<Item = ThinNode<'a, S>> {
+        self.descendants_with_tokens().filter_map(NodeOrToken::into_node)
+    }
+
+    /// Returns an iterator over all elements in the subtree of this node, including this node, in preorder.
+    pub fn descendants_with_tokens(&self) -> impl Iterator<Item = ThinElement<'a, S>> {
+        let mut next = Some(NodeOrToken::Node(*self));
+        let mut stack: Vec<ThinElementChildren<'a, S>> = Vec::new();
+        std::iter::from_fn(move || {
+            let element = next.take().or_else(|| loop {
+                let children = stack.last_mut()?;
+                match children.next() {
+                    Some(child) => break Some(child),
+                    None => {
+                        stack.pop();
+                    }
+                }
+            })?;
+            if let NodeOrToken::Node(node) = element {
+                stack.push(node.children_with_tokens());
+            }
+            Some(element)
+        })
+    }
+
+    /// Returns an iterator over all tokens in the subtree of this node, in document order.
+    #[inline]
+    pub fn descendant_tokens(&self) -> impl Iterator<Item = ThinToken<'a, S>> {
+        self.descendants_with_tokens().filter_map(NodeOrToken::into_token)
+    }
+
+    /// Uses the provided resolver to return the source text of this node.
+    pub fn resolve_text<I>(&self, resolver: &I) -> String
+    where
+        I: Resolver<TokenKey> + ?Sized,
+    {
+        let mut text = String::with_capacity(u32::from(self.green.text_len()) as usize);
+        for token in self.descendant_tokens() {
+            text.push_str(token.resolve_text(resolver));
+        }
+        text
+    }
+}
+
+impl<'a, S: Syntax> ThinToken<'a, S> {
+    /// Creates a thin token for the green token `green`, whose text starts at `offset`.
+    #[inline]
+    pub fn new(green: &'a GreenToken, offset: TextSize) -> Self {
+        Self {
+            green,
+            offset,
+            _syntax: PhantomData,
+        }
+    }
+
+    /// The green token underlying this token.
+    #[inline]
+    pub fn green(&self) -> &'a GreenToken {
+        self.green
+    }
+
+    /// The internal representation of the kind of this token.
+    #[inline]
+    pub fn syntax_kind(&self) -> RawSyntaxKind {
+        self.green.kind()
+    }
+
+    /// The kind of this token in terms of your language.
+    #[inline]
+    pub fn kind(&self) -> S {
+        S::from_raw(self.syntax_kind())
+    }
+
+    /// The range this token covers in the source text.
+    #[inline]
+    pub fn text_range(&self) -> TextRange {
+        TextRange::at(self.offset, self.green.text_len())
+    }
+
+    /// Uses the provided resolver to return the source text of this token.
+    ///
+    /// If no text is explicitly associated with the token, returns its [`static_text`](Syntax::static_text)
+    /// instead.
+    #[inline]
+    pub fn resolve_text<'i, I>(&self, resolver: &'i I) -> &'i str
+    where
+        I: Resolver<TokenKey> + ?Sized,
+    {
+        // one of the two must be present upon construction
+        self.static_text().or_else(|| self.green.text(resolver)).unwrap()
+    }
+
+    /// If the [syntax kind](Syntax) of this token always represents the same text, returns that text.
+    #[inline]
+    pub fn static_text(&self) -> Option<&'static str> {
+        S::static_text(self.kind())
+    }
+}
+
+impl<'a, S: Syntax> ThinElement<'a, S> {
+    /// The range this element covers in the source text.
+    #[inline]
+    pub fn text_range(&self) -> TextRange {
+        match self {
+            NodeOrToken::Node(node) => node.text_range(),
+            NodeOrToken::Token(token) => token.text_range(),
+        }
+    }
+
+    /// The kind of this element in terms of your language.
+    #[inline]
+    pub fn kind(&self) -> S {
+        match self {
+            NodeOrToken::Node(node) => node.kind(),
+            NodeOrToken::Token(token) => token.kind(),
+        }
+    }
+}
+
+/// An iterator over the children of a [`ThinNode`], including tokens.
+#[derive(Clone, Debug)]
+pub struct ThinElementChildren<'a, S: Syntax> {
+    inner:   GreenNodeChildren<'a>,
+    offset:  TextSize,
+    _syntax: PhantomData<S>,
+}
+
+impl<'a, S: Syntax> Iterator for ThinElementChildren<'a, S> {
+    type Item = ThinElement<'a, S>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let child = self.inner.next()?;
+        let offset = self.offset;
+        self.offset += child.text_len();
+        Some(match child {
+            NodeOrToken::Node(node) => NodeOrToken::Node(ThinNode::new(node, offset)),
+            NodeOrToken::Token(token) => NodeOrToken::Token(ThinToken::new(token, offset)),
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Syntax> ExactSizeIterator for ThinElementChildren<'_, S> {}
@@ -9,8 +9,10 @@ use text_size::{TextRange, TextSize};
 
 use super::*;
 use crate::{
+    build::NodeCache,
     green::{GreenNode, GreenToken},
-    interning::{Resolver, TokenKey},
+    interning::{Interner, Resolver, TokenKey},
+    text::{LineCol, LineIndex},
     traversal::Direction,
     RawSyntaxKind, Syntax,
 };
@@ -55,7 +57,8 @@ impl<S: Syntax, D> SyntaxToken<S, D> {
     where
         R: Resolver<TokenKey> + ?Sized,
     {
-        write!(target, "{:?}@{:?}", self.kind(), self.text_range())?;
+        super::write_kind(self.kind(), target)?;
+        write!(target, "@{:?}", self.text_range())?;
         let text = self.resolve_text(resolver);
         if text.len() < 25 {
             return write!(target, " {:?}", text);
@@ -104,12 +107,133 @@ impl<S: Syntax, D> SyntaxToken<S, D> {
         self.resolve_text(resolver).to_string()
     }
 
+    /// Returns the full text of the line(s) this token is on, together with the range of the token relative to the
+    /// start of that text. This is useful for rendering diagnostics that point at the token.
+    ///
+    /// If the token spans multiple lines, the text of all of them is returned. Line breaks at the end of the last
+    /// line are not included. `line_index` must have been created from the text of the entire tree this token
+    /// belongs to, such as with `LineIndex::new(&root.resolve_text(resolver).to_string())`.
+    ///
+    /// ## Panics
+    /// If the token is not contained in the text indexed by `line_index`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::text::{LineIndex, TextRange};
+    ///
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Identifier, "foo");
+    /// builder.token(Whitespace, "\n");
+    /// builder.token(Int, "1");
+    /// builder.static_token(Plus);
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    ///
+    /// let line_index = LineIndex::new(&root.resolve_text(&interner).to_string());
+    /// let plus = root
+    ///     .children_with_tokens()
+    ///     .nth(3)
+    ///     .unwrap()
+    ///     .into_token()
+    ///     .unwrap();
+    /// let (line, range) = plus.line_text(&interner, &line_index);
+    /// assert_eq!(line, "1+2");
+    /// assert_eq!(range, TextRange::new(1.into(), 2.into()));
+    /// ```
+    pub fn line_text<R>(&self, resolver: &R, line_index: &LineIndex) -> (String, TextRange)
+    where
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        let range = self.text_range();
+        let first_line = line_index.line_col(range.start()).line;
+        let mut last_line = line_index.line_col(range.end()).line;
+        if last_line > first_line && line_index.line_col(range.end()).col == 0 {
+            // the token ends with a line break, so the line after it is not part of the token
+            last_line -= 1;
+        }
+        // NOTE: both lines exist, since they were computed by the `line_index`
+        let lines = TextRange::new(
+            line_index.line_range(first_line).unwrap().start(),
+            line_index.line_range(last_line).unwrap().end(),
+        );
+        let text = self.parent.root().resolve_text(resolver).slice(lines).to_string();
+        (text, range - lines.start())
+    }
+
+    /// The line and column at which this token starts, according to the `line_index` of the text of the tree this
+    /// token belongs to.
+    ///
+    /// ## Panics
+    /// If the start of this token is past the end of the text indexed by `line_index`.
+    #[inline]
+    pub fn position(&self, line_index: &LineIndex) -> LineCol {
+        line_index.line_col(self.text_range().start())
+    }
+
+    /// The column at which this token starts within its line, both in bytes and in `char`s, according to the
+    /// `line_index` of the text of the tree this token belongs to. This is useful for rendering markers underneath
+    /// the token, such as carets in diagnostics.
+    ///
+    /// Every `char` counts as one column, including tabs, so rendering code that expands tabs has to account for
+    /// them separately.
+    ///
+    /// ## Panics
+    /// If the start of this token is past the end of the text indexed by `line_index`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// use cstree::text::LineIndex;
+    ///
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Identifier, "résumé");
+    /// builder.static_token(Plus);
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    ///
+    /// let line_index = LineIndex::new(&root.resolve_text(&interner).to_string());
+    /// let plus = root.last_token().unwrap();
+    /// assert_eq!(plus.columns(&interner, &line_index), (8, 6));
+    /// ```
+    pub fn columns<R>(&self, resolver: &R, line_index: &LineIndex) -> (usize, usize)
+    where
+        R: Resolver<TokenKey> + ?Sized,
+    {
+        let start = self.text_range().start();
+        let position = line_index.line_col(start);
+        // NOTE: the line exists, since it was computed by the `line_index`
+        let line_start = line_index.line_range(position.line).unwrap().start();
+        let chars = self
+            .parent
+            .root()
+            .resolve_text(resolver)
+            .slice(TextRange::new(line_start, start))
+            .fold_chunks(0, |chars, chunk| chars + chunk.chars().count());
+        (position.col as usize, chars)
+    }
+
     /// If there is a resolver associated with this tree, returns it.
     #[inline]
     pub fn resolver(&self) -> Option<&StdArc<dyn Resolver<TokenKey>>> {
         self.parent.resolver()
     }
 
+    /// The source file the tree this token belongs to is associated with, if any.
+    ///
+    /// See [`SyntaxNode::with_file_id`].
+    #[inline]
+    pub fn file_id(&self) -> Option<FileId> {
+        self.parent.file_id()
+    }
+
     /// Turns this token into a [`ResolvedToken`](crate::syntax::ResolvedToken), but only if there is a resolver
     /// associated with this tree.
     #[inline]
@@ -152,7 +276,52 @@ impl<S: Syntax, D> SyntaxToken<S, D> {
                 child.cloned()
             }
         });
-        let new_parent = GreenNode::new(parent.syntax_kind(), children);
+        let new_parent = parent.green().with_children(children);
+        parent.replace_with(new_parent)
+    }
+
+    /// Returns the green tree of the whole tree this token belongs to, with the kind of this token changed to
+    /// `kind`, for example to turn an identifier into a contextual keyword.
+    ///
+    /// The text of the token is unchanged, so the ranges of all nodes and tokens are the same in the new tree. Only
+    /// the nodes on the path from this token to the root are rebuilt. The `cache` must use the same interner as the
+    /// tree, since it is used to resolve the text of the token.
+    ///
+    /// ## Panics
+    /// In debug mode, if `kind` has static text, this function will verify that the text of this token matches it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// # use cstree::build::NodeCache;
+    /// let mut cache = NodeCache::new();
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::with_cache(&mut cache);
+    /// builder.start_node(Root);
+    /// builder.token(Identifier, "1");
+    /// builder.finish_node();
+    /// let (green, _) = builder.finish();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(green);
+    ///
+    /// let token = root.first_token().unwrap();
+    /// let edited: SyntaxNode<MySyntax> = SyntaxNode::new_root(token.with_kind(Int, &mut cache));
+    /// assert_eq!(edited.first_token().unwrap().kind(), Int);
+    /// assert_eq!(edited.resolve_text(cache.interner()), "1");
+    /// ```
+    pub fn with_kind<I>(&self, kind: S, cache: &mut NodeCache<'_, I>) -> GreenNode
+    where
+        I: Interner<TokenKey>,
+    {
+        let text = self.resolve_text(cache.interner()).to_string();
+        let mut green = Some(cache.token(kind, &text));
+        let parent = self.parent();
+        let children = parent.green().children().enumerate().map(|(i, child)| {
+            if i as u32 == self.index {
+                green.take().unwrap().into()
+            } else {
+                child.cloned()
+            }
+        });
+        let new_parent = cache.node_with_flags(parent.syntax_kind(), parent.green().own_flags(), children.collect());
         parent.replace_with(new_parent)
     }
 
@@ -169,11 +338,27 @@ impl<S: Syntax, D> SyntaxToken<S, D> {
     }
 
     /// The range this token covers in the source text, in bytes.
+    ///
+    /// Ranges are computed from the lengths of the tokens' texts, so the tokens of a tree always cover its text
+    /// without gaps: every token starts where the previous one ends. Source text that should be part of the range of
+    /// a tree, such as whitespace between other tokens, therefore has to be added to the tree as tokens of its own.
     #[inline]
     pub fn text_range(&self) -> TextRange {
         TextRange::at(self.offset, self.green().text_len())
     }
 
+    /// The range this token covers in the source text, relative to the start of `ancestor`.
+    ///
+    /// Returns `None` if `ancestor` is not an ancestor of this token. See
+    /// [`SyntaxNode::range_in`](crate::syntax::SyntaxNode::range_in).
+    #[inline]
+    pub fn range_in(&self, ancestor: &SyntaxNode<S, D>) -> Option<TextRange> {
+        if !ancestor.contains(self.parent()) {
+            return None;
+        }
+        Some(self.text_range() - ancestor.text_range().start())
+    }
+
     /// Uses the provided resolver to return the source text of this token.
     ///
     /// If no text is explicitly associated with the token, returns its [`static_text`](SyntaxToken::static_text)
@@ -187,6 +372,18 @@ impl<S: Syntax, D> SyntaxToken<S, D> {
         self.static_text().or_else(|| self.green().text(resolver)).unwrap()
     }
 
+    /// The number of `char`s in the source text of this token, resolved with `resolver`.
+    ///
+    /// This counts Unicode scalar values, unlike the [`text_range`](SyntaxToken::text_range) of the token, which is
+    /// measured in bytes.
+    #[inline]
+    pub fn char_len<I>(&self, resolver: &I) -> usize
+    where
+        I: Resolver<TokenKey> + ?Sized,
+    {
+        self.resolve_text(resolver).chars().count()
+    }
+
     /// If the [syntax kind](Syntax) of this token always represents the same text, returns
     /// that text.
     ///
@@ -273,6 +470,50 @@ impl<S: Syntax, D> SyntaxToken<S, D> {
         self.syntax_kind() == other.syntax_kind()
     }
 
+    /// Returns `true` if the source text of this token is equal to `text`.
+    ///
+    /// This is equivalent to comparing the result of [`resolve_text`](SyntaxToken::resolve_text) with `text`, but
+    /// avoids resolving the token text if the lengths already differ. If you look for the same `text` many times,
+    /// intern it once and use [`text_eq_key`](SyntaxToken::text_eq_key) instead, which does not need to resolve any
+    /// text at all.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Identifier, "let");
+    /// builder.finish_node();
+    /// let (tree, cache) = builder.finish();
+    /// let interner = cache.unwrap().into_interner().unwrap();
+    /// let tree: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+    /// let ident = tree.first_token().unwrap();
+    /// assert!(ident.text_eq_str("let", &interner));
+    /// assert!(!ident.text_eq_str("fn", &interner));
+    /// ```
+    #[inline]
+    pub fn text_eq_str<I>(&self, text: &str, resolver: &I) -> bool
+    where
+        I: Resolver<TokenKey> + ?Sized,
+    {
+        if u32::from(self.green().text_len()) as usize != text.len() {
+            return false;
+        }
+        self.resolve_text(resolver) == text
+    }
+
+    /// Returns `true` if the text of this token was interned as `key`, i.e. if it is equal to the text that was
+    /// interned to obtain `key`.
+    ///
+    /// Tokens with [static text](SyntaxToken::static_text) do not have a key and are never equal to any key.
+    ///
+    /// **Note** that `key` must have been obtained from the interner this token's tree was built with. Otherwise,
+    /// the result of the comparison is meaningless.
+    #[inline]
+    pub fn text_eq_key(&self, key: TokenKey) -> bool {
+        self.green().text_key() == Some(key)
+    }
+
     /// Returns the interned key of text covered by this token, if any.
     /// This key may be used for comparisons with other keys of strings interned by the same interner.
     ///
@@ -0,0 +1,99 @@
+//! A precomputed index for repeated token lookups by offset.
+
+use crate::{
+    text::{TextRange, TextSize},
+    utility_types::TokenAtOffset,
+    Syntax,
+};
+
+use super::{SyntaxNode, SyntaxToken};
+
+/// A flat, sorted index of all tokens in a subtree, for answering many [`token_at`](TokenIndex::token_at) queries
+/// without descending into the tree each time.
+///
+/// Create one with [`SyntaxNode::build_token_index`]. Building the index takes time linear in the size of the
+/// subtree, each query afterwards is a binary search.
+///
+/// The index refers to the tree it was built from and will not reflect newer versions of the tree created by edits.
+#[derive(Debug, Clone)]
+pub struct TokenIndex<S: Syntax, D: 'static = ()> {
+    range:  TextRange,
+    tokens: Vec<(TextRange, SyntaxToken<S, D>)>,
+}
+
+impl<S: Syntax, D> TokenIndex<S, D> {
+    /// The number of non-empty tokens in the index.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Returns `true` if there are no non-empty tokens in the index.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Returns an iterator over all non-empty tokens in the index with their ranges, in document order.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (TextRange, &SyntaxToken<S, D>)> {
+        self.tokens.iter().map(|(range, token)| (*range, token))
+    }
+
+    /// Find the token(s) covering `offset`.
+    ///
+    /// This gives the same results as [`SyntaxNode::token_at_offset`] on the node the index was built from: empty
+    /// tokens are never returned, and if `offset` lies exactly between two tokens, both are returned.
+    ///
+    /// ## Panics
+    /// If `offset` is not within the range of the node the index was built from.
+    pub fn token_at(&self, offset: TextSize) -> TokenAtOffset<&SyntaxToken<S, D>> {
+        assert!(
+            self.range.start() <= offset && offset <= self.range.end(),
+            "Bad offset: range {:?} offset {:?}",
+            self.range,
+            offset
+        );
+        let index = self.tokens.partition_point(|(range, _)| range.end() < offset);
+        let Some((range, token)) = self.tokens.get(index) else {
+            return TokenAtOffset::None;
+        };
+        match self.tokens.get(index + 1) {
+            Some((_, next)) if range.end() == offset => TokenAtOffset::Between(token, next),
+            _ => TokenAtOffset::Single(token),
+        }
+    }
+}
+
+impl<S: Syntax, D> SyntaxNode<S, D> {
+    /// Collects all tokens in the subtree of this node into a [`TokenIndex`] that allows looking up tokens by offset
+    /// in logarithmic time.
+    ///
+    /// # Examples
+    /// ```
+    /// # use cstree::testing::*;
+    /// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+    /// builder.start_node(Root);
+    /// builder.token(Int, "1");
+    /// builder.token(Plus, "+");
+    /// builder.token(Int, "2");
+    /// builder.finish_node();
+    /// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+    ///
+    /// let index = root.build_token_index();
+    /// assert_eq!(index.len(), 3);
+    /// let token = index.token_at(2.into()).right_biased().unwrap();
+    /// assert_eq!(token.kind(), Int);
+    /// ```
+    pub fn build_token_index(&self) -> TokenIndex<S, D> {
+        let tokens = self
+            .tokens()
+            .filter(|token| !token.text_range().is_empty())
+            .map(|token| (token.text_range(), token.clone()))
+            .collect();
+        TokenIndex {
+            range: self.text_range(),
+            tokens,
+        }
+    }
+}
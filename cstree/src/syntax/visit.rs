@@ -0,0 +1,104 @@
+//! Push-based traversal of syntax trees.
+
+use crate::{traversal::VisitControl, util::NodeOrToken, Syntax};
+
+use super::{SyntaxNode, SyntaxToken};
+
+/// A visitor that is called for every node and token of a tree, as an alternative to iterating over the tree with
+/// [`preorder_with_tokens`](SyntaxNode::preorder_with_tokens).
+///
+/// Pass a visitor to [`SyntaxNode::accept`] to traverse the subtree of a node. All methods have default
+/// implementations that do nothing, so you only need to implement the ones you are interested in.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::{syntax::Visitor, traversal::VisitControl};
+///
+/// #[derive(Default)]
+/// struct CountInts(usize);
+///
+/// impl Visitor<MySyntax> for CountInts {
+///     fn visit_token(&mut self, token: &SyntaxToken<MySyntax>) {
+///         if token.kind() == Int {
+///             self.0 += 1;
+///         }
+///     }
+/// }
+///
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.token(Int, "1");
+/// builder.token(Plus, "+");
+/// builder.token(Int, "2");
+/// builder.finish_node();
+/// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+///
+/// let mut visitor = CountInts::default();
+/// assert_eq!(root.accept(&mut visitor), VisitControl::Continue);
+/// assert_eq!(visitor.0, 2);
+/// ```
+pub trait Visitor<S: Syntax, D: 'static = ()> {
+    /// Called when the traversal enters `node`, before any of its children are visited.
+    ///
+    /// Return [`VisitControl::SkipChildren`] to not visit the children of `node` or [`VisitControl::Break`] to stop the
+    /// traversal.
+    #[inline]
+    fn visit_node_enter(&mut self, node: &SyntaxNode<S, D>) -> VisitControl {
+        let _ = node;
+        VisitControl::Continue
+    }
+
+    /// Called when the traversal leaves `node`, after all of its children were visited.
+    ///
+    /// This is also called for nodes whose children were skipped, but not when the traversal is stopped.
+    #[inline]
+    fn visit_node_leave(&mut self, node: &SyntaxNode<S, D>) {
+        let _ = node;
+    }
+
+    /// Called for every token of the tree.
+    #[inline]
+    fn visit_token(&mut self, token: &SyntaxToken<S, D>) {
+        let _ = token;
+    }
+}
+
+impl<S: Syntax, D> SyntaxNode<S, D> {
+    /// Traverses the subtree rooted at this node (including this node) in document order, calling the corresponding
+    /// methods of `visitor` for every node and token.
+    ///
+    /// Returns [`VisitControl::Break`] if the traversal was stopped by the visitor and [`VisitControl::Continue`]
+    /// otherwise.
+    pub fn accept<V>(&self, visitor: &mut V) -> VisitControl
+    where
+        V: Visitor<S, D> + ?Sized,
+    {
+        match visitor.visit_node_enter(self) {
+            VisitControl::Continue => (),
+            VisitControl::SkipChildren => {
+                visitor.visit_node_leave(self);
+                return VisitControl::Continue;
+            }
+            VisitControl::Break => return VisitControl::Break,
+        }
+
+        let mut stack = vec![(self, self.children_with_tokens())];
+        while let Some((node, children)) = stack.last_mut() {
+            let node = *node;
+            match children.next() {
+                None => {
+                    visitor.visit_node_leave(node);
+                    stack.pop();
+                }
+                Some(NodeOrToken::Token(token)) => visitor.visit_token(token),
+                Some(NodeOrToken::Node(child)) => match visitor.visit_node_enter(child) {
+                    VisitControl::Continue => stack.push((child, child.children_with_tokens())),
+                    VisitControl::SkipChildren => visitor.visit_node_leave(child),
+                    VisitControl::Break => return VisitControl::Break,
+                },
+            }
+        }
+        VisitControl::Continue
+    }
+}
@@ -0,0 +1,107 @@
+//! Assertions for testing parsers that produce `cstree` syntax trees.
+//!
+//! All assertions panic with a message that includes the structure of the tested tree if they fail, so mismatches
+//! are easy to locate.
+
+use crate::{
+    interning::{Resolver, TokenKey},
+    syntax::SyntaxNode,
+    text::{TextRange, TextSize},
+    Syntax,
+};
+
+/// Asserts that the text of the tree rooted at `root` is equal to `expected`.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::test_utils::assert_tree_text;
+///
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.token(Int, "1");
+/// builder.static_token(Plus);
+/// builder.token(Int, "2");
+/// builder.finish_node();
+/// let (tree, cache) = builder.finish();
+/// let interner = cache.unwrap().into_interner().unwrap();
+/// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(tree);
+/// assert_tree_text(&root, &interner, "1+2");
+/// ```
+#[track_caller]
+pub fn assert_tree_text<S, D, R>(root: &SyntaxNode<S, D>, resolver: &R, expected: &str)
+where
+    S: Syntax,
+    R: Resolver<TokenKey> + ?Sized,
+{
+    let text = root.resolve_text(resolver).to_string();
+    assert!(
+        text == expected,
+        "tree text does not match\n  actual: {text:?}\nexpected: {expected:?}\ntree:\n{}",
+        root.debug(resolver, true)
+    );
+}
+
+/// Asserts that the text in the given `range` of the tree rooted at `root` is equal to `expected`.
+///
+/// ## Panics
+/// Also panics if `range` is not contained in the range of `root`.
+#[track_caller]
+pub fn assert_range_text<S, D, R>(root: &SyntaxNode<S, D>, resolver: &R, range: TextRange, expected: &str)
+where
+    S: Syntax,
+    R: Resolver<TokenKey> + ?Sized,
+{
+    assert!(
+        root.text_range().contains_range(range),
+        "range {range:?} is out of bounds of the tree at {:?}",
+        root.text_range()
+    );
+    let text = root.resolve_text(resolver).slice(range).to_string();
+    assert!(
+        text == expected,
+        "text at {range:?} does not match\n  actual: {text:?}\nexpected: {expected:?}\ntree:\n{}",
+        root.debug(resolver, true)
+    );
+}
+
+/// Asserts that there is a node or token of the given `kind` in the tree rooted at `root` that covers `offset`.
+///
+/// The token at `offset` and all of its ancestors up to `root` are considered. If `offset` lies between two tokens,
+/// both are considered.
+///
+/// # Examples
+/// ```
+/// # use cstree::testing::*;
+/// use cstree::test_utils::assert_kind_at;
+///
+/// let mut builder: GreenNodeBuilder<MySyntax> = GreenNodeBuilder::new();
+/// builder.start_node(Root);
+/// builder.start_node(Operation);
+/// builder.token(Int, "1");
+/// builder.static_token(Plus);
+/// builder.token(Int, "2");
+/// builder.finish_node();
+/// builder.finish_node();
+/// let root: SyntaxNode<MySyntax> = SyntaxNode::new_root(builder.finish().0);
+/// assert_kind_at(&root, 1.into(), Plus);
+/// assert_kind_at(&root, 1.into(), Operation);
+/// ```
+#[track_caller]
+pub fn assert_kind_at<S: Syntax, D>(root: &SyntaxNode<S, D>, offset: TextSize, kind: S) {
+    let mut found = Vec::new();
+    for token in root.token_at_offset(offset) {
+        found.push(token.kind());
+        for node in token.parent().ancestors() {
+            found.push(node.kind());
+            if node == root {
+                break;
+            }
+        }
+    }
+    assert!(
+        found.contains(&kind),
+        "no element of kind {kind:?} at offset {offset:?}, found {found:?}\ntree:\n{}",
+        root.display_structure()
+    );
+}
@@ -47,7 +47,8 @@ impl<N, T> NodeOrToken<N, T> {
 }
 
 impl<N: Clone, T: Clone> NodeOrToken<&N, &T> {
-    pub(crate) fn cloned(&self) -> NodeOrToken<N, T> {
+    /// Maps a reference to a node or token to an owned node or token by cloning it.
+    pub fn cloned(&self) -> NodeOrToken<N, T> {
         match *self {
             NodeOrToken::Node(node) => NodeOrToken::Node(node.clone()),
             NodeOrToken::Token(token) => NodeOrToken::Token(token.clone()),
@@ -71,7 +72,7 @@ pub enum Direction {
 }
 
 /// `WalkEvent` describes tree walking process.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum WalkEvent<T> {
     /// Fired before traversing the node.
     Enter(T),
@@ -88,6 +89,17 @@ impl<T> WalkEvent<T> {
     }
 }
 
+/// Controls how a [`Visitor`](crate::syntax::Visitor) continues traversing a tree.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VisitControl {
+    /// Continue with the children of the current node.
+    Continue,
+    /// Skip the children of the current node and continue after it.
+    SkipChildren,
+    /// Stop the traversal.
+    Break,
+}
+
 #[derive(Debug)]
 pub(crate) enum MaybeOwned<'a, T> {
     Owned(T),
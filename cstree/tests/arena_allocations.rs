@@ -0,0 +1,87 @@
+//! Compares the number of allocations needed by the standard and the arena builder.
+//!
+//! This is a separate test binary because it installs a counting global allocator, which would otherwise count the
+//! allocations of unrelated tests running in parallel.
+
+use cstree::{
+    build::{ArenaGreenNodeBuilder, NodeCache},
+    interning::{new_interner, Interner},
+    testing::*,
+};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns the number of allocations made while running `f`.
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn build_standard<I: Interner>(texts: &[String], interner: &mut I) {
+    let mut cache = NodeCache::with_interner(interner);
+    let mut builder: GreenNodeBuilder<MySyntax, I> = GreenNodeBuilder::with_cache(&mut cache);
+    builder.start_node(Root);
+    for text in texts {
+        builder.start_node(Operation);
+        builder.token(Int, text);
+        builder.static_token(Plus);
+        builder.token(Int, text);
+        builder.finish_node();
+    }
+    builder.finish_node();
+    drop(builder.finish());
+}
+
+fn build_arena<I: Interner>(texts: &[String], interner: &mut I) {
+    let mut builder: ArenaGreenNodeBuilder<MySyntax, I> = ArenaGreenNodeBuilder::with_interner(interner);
+    builder.start_node(Root);
+    for text in texts {
+        builder.start_node(Operation);
+        builder.token(Int, text);
+        builder.static_token(Plus);
+        builder.token(Int, text);
+        builder.finish_node();
+    }
+    builder.finish_node();
+    drop(builder.finish());
+}
+
+#[test]
+fn arena_builder_allocates_less() {
+    let texts: Vec<String> = (0..1_000).map(|i| i.to_string()).collect();
+    let mut interner = new_interner();
+    // intern all text up front, so that only allocations for the tree itself are counted
+    build_standard(&texts, &mut interner);
+
+    let standard = count_allocations(|| build_standard(&texts, &mut interner));
+    let arena = count_allocations(|| build_arena(&texts, &mut interner));
+    // the standard builder allocates every distinct node individually, while the arena builder only grows its buffers
+    assert!(standard > texts.len(), "standard builder made {standard} allocations");
+    assert!(arena < 100, "arena builder made {arena} allocations");
+}
@@ -0,0 +1,108 @@
+use super::*;
+use cstree::{
+    build::{ArenaGreenNodeBuilder, NodeCache},
+    green::{ArenaNodeRef, GreenNode},
+    interning::new_interner,
+    util::NodeOrToken,
+};
+
+fn build_arena_recursive<I>(
+    root: &Element<'_>,
+    builder: &mut ArenaGreenNodeBuilder<'_, SyntaxKind, I>,
+    mut from: u32,
+) -> u32
+where
+    I: Interner,
+{
+    match root {
+        Element::Node(children) => {
+            builder.start_node(SyntaxKind(from));
+            for child in children {
+                from = build_arena_recursive(child, builder, from + 1);
+            }
+            builder.finish_node();
+        }
+        Element::Token(text) => {
+            builder.token(SyntaxKind(from), text);
+        }
+    }
+    from
+}
+
+fn assert_same_tree(arena: ArenaNodeRef<'_>, green: &GreenNode) {
+    assert_eq!(arena.kind(), green.kind());
+    assert_eq!(arena.text_len(), green.text_len());
+    assert_eq!(arena.children().len(), green.children().len());
+    for (arena, green) in arena.children().zip(green.children()) {
+        match (arena, green) {
+            (NodeOrToken::Node(arena), NodeOrToken::Node(green)) => assert_same_tree(arena, green),
+            (NodeOrToken::Token(arena), NodeOrToken::Token(green)) => {
+                assert_eq!(arena.kind(), green.kind());
+                assert_eq!(arena.text_len(), green.text_len());
+                assert_eq!(arena.text_key(), green.text_key());
+            }
+            (arena, green) => panic!("element mismatch: {arena:?} vs {green:?}"),
+        }
+    }
+}
+
+fn nested_tree() -> Element<'static> {
+    use Element::*;
+    Node(vec![
+        Node(vec![Token("fn"), Token(" "), Token("main")]),
+        Node(vec![Node(vec![Token("("), Token(")")]), Token(" ")]),
+        Node(vec![
+            Token("{"),
+            Node(vec![Node(vec![Token("1")]), Token("+"), Node(vec![Token("2")])]),
+            Token("}"),
+        ]),
+    ])
+}
+
+#[test]
+fn arena_matches_standard_builder() {
+    let mut interner = new_interner();
+    for tree in [nested_tree(), Element::Node(vec![])] {
+        let green = {
+            let mut cache = NodeCache::with_interner(&mut interner);
+            build_tree_with_cache(&tree, &mut cache)
+        };
+        let mut builder = ArenaGreenNodeBuilder::with_interner(&mut interner);
+        build_arena_recursive(&tree, &mut builder, 0);
+        let (arena, owned) = builder.finish();
+        assert!(owned.is_none());
+
+        assert_same_tree(arena.root(), &green);
+        assert_eq!(arena.to_green(), green);
+    }
+}
+
+#[test]
+fn arena_red_tree() {
+    let mut builder = ArenaGreenNodeBuilder::new();
+    build_arena_recursive(&nested_tree(), &mut builder, 0);
+    let (arena, interner) = builder.finish();
+    assert_eq!(arena.node_count(), 8);
+
+    let root: ResolvedNode = SyntaxNode::new_root_with_resolver(arena.to_green(), interner.unwrap());
+    assert_eq!(root.text(), "fn main() {1+2}");
+    let body = root.children().nth(2).unwrap();
+    assert_eq!(body.kind(), SyntaxKind(10));
+    assert_eq!(body.text(), "{1+2}");
+}
+
+#[test]
+fn arena_read_access() {
+    let mut builder = ArenaGreenNodeBuilder::new();
+    build_arena_recursive(&nested_tree(), &mut builder, 0);
+    let (arena, interner) = builder.finish();
+    let interner = interner.unwrap();
+
+    assert_eq!(arena.resolve_text::<SyntaxKind, _>(&interner), "fn main() {1+2}");
+    let texts: Vec<_> = arena.tokens().map(|token| token.text(&interner).unwrap()).collect();
+    assert_eq!(texts, ["fn", " ", "main", "(", ")", " ", "{", "1", "+", "2", "}"]);
+    let body = arena.root().child(2).unwrap().into_node().unwrap();
+    assert_eq!(body.resolve_text::<SyntaxKind, _>(&interner), "{1+2}");
+    assert_eq!(body.tokens().count(), 5);
+    assert!(arena.root().child(3).is_none());
+}
@@ -1,10 +1,14 @@
 use super::*;
 use cstree::{
-    build::{GreenNodeBuilder, NodeCache},
-    interning::{new_interner, Resolver},
-    text::TextRange,
+    build::{BuildError, BuildStats, GreenNodeBuilder, NodeCache},
+    green::{GreenElement, GreenNode},
+    interning::{new_interner, Interner, Resolver},
+    syntax::{FileId, NodeMap},
+    text::{TextRange, TextSize},
+    util::{KindRange, KindSet},
     RawSyntaxKind,
 };
+use std::collections::HashSet;
 
 fn build_tree<D>(root: &Element<'_>) -> (SyntaxNode<D>, impl Resolver) {
     let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
@@ -54,6 +58,137 @@ fn create() {
     }
 }
 
+#[test]
+fn children_are_cached() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+
+    // red nodes are created once and persisted, so repeated traversals return the same node
+    let first = tree.children().nth(2).unwrap();
+    let second = tree.children().nth(2).unwrap();
+    assert_eq!(first, second);
+    assert!(std::ptr::eq(first, second));
+
+    let first = first.children_with_tokens().nth(1).unwrap();
+    let second = tree.children().nth(2).unwrap().children_with_tokens().nth(1).unwrap();
+    assert_eq!(first, second);
+    assert!(std::ptr::eq(first.into_token().unwrap(), second.into_token().unwrap()));
+}
+
+#[test]
+fn iterate_by_ref() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let mut count = 0;
+    for child in &tree {
+        assert!(child.as_node().is_some());
+        count += 1;
+    }
+    assert_eq!(count, 3);
+
+    let node2 = tree.children().nth(2).unwrap();
+    let kinds: Vec<_> = node2.into_iter().map(|child| child.kind()).collect();
+    assert_eq!(kinds, [SyntaxKind(7), SyntaxKind(8), SyntaxKind(9)]);
+}
+
+#[test]
+fn filter_by_kind() {
+    let tree = two_level_tree();
+    let (tree, resolver) = build_tree::<()>(&tree);
+    let matches: Vec<_> = tree
+        .descendants_with_tokens()
+        .filter(|element| element.matches_kind(SyntaxKind(8)))
+        .collect();
+    assert_eq!(matches.len(), 1);
+    let token = matches[0].into_token().unwrap();
+    assert_eq!(token.resolve_text(&resolver), "2.1");
+
+    let node2 = tree.children().nth(2).unwrap();
+    assert_eq!(node2.children_with_tokens().of_kind(SyntaxKind(9)).count(), 1);
+    assert_eq!(tree.children_with_tokens().of_kind(SyntaxKind(4)).count(), 1);
+    assert_eq!(tree.children_with_tokens().of_kind(SyntaxKind(5)).count(), 0);
+}
+
+#[test]
+fn kinds_present() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let expected: HashSet<_> = (0..=9).map(SyntaxKind).collect();
+    assert_eq!(tree.kinds_present(), expected);
+
+    let node2 = tree.children().nth(2).unwrap();
+    let expected: HashSet<_> = (6..=9).map(SyntaxKind).collect();
+    assert_eq!(node2.kinds_present(), expected);
+}
+
+#[test]
+fn first_and_last_child_or_token() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let first = tree.first_child_or_token().unwrap();
+    assert_eq!(first.kind(), SyntaxKind(1));
+    assert_eq!(first.text_range(), TextRange::new(0.into(), 6.into()));
+
+    let last = tree.last_child_or_token().unwrap();
+    assert_eq!(last.kind(), SyntaxKind(6));
+    assert_eq!(last.text_range(), TextRange::new(9.into(), 18.into()));
+    assert_eq!(Some(last), tree.child(2));
+
+    // offsets of tokens in a subtree are absolute
+    let node = last.into_node().unwrap();
+    let token = node.last_child_or_token().unwrap();
+    assert_eq!(token.text_range(), TextRange::new(15.into(), 18.into()));
+    assert_eq!(node.first_child_or_token().unwrap().text_range().start(), 9.into());
+}
+
+#[test]
+fn kind_histogram() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let histogram = tree.kind_histogram();
+    assert_eq!(histogram.len(), 10);
+    assert!(histogram.values().all(|&count| count == 1));
+
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    for _ in 0..3 {
+        builder.start_node(SyntaxKind(1));
+        builder.token(SyntaxKind(2), "a");
+        builder.token(SyntaxKind(3), " ");
+        builder.finish_node();
+    }
+    builder.token(SyntaxKind(2), "b");
+    builder.finish_node();
+    let root: SyntaxNode = SyntaxNode::new_root(builder.finish().0);
+    let histogram = root.kind_histogram();
+    assert_eq!(histogram[&SyntaxKind(0)], 1);
+    assert_eq!(histogram[&SyntaxKind(1)], 3);
+    assert_eq!(histogram[&SyntaxKind(2)], 4);
+    assert_eq!(histogram[&SyntaxKind(3)], 3);
+    assert_eq!(root.first_child().unwrap().kind_histogram().len(), 3);
+}
+
+#[test]
+fn children_with_offsets() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let offsets: Vec<_> = tree
+        .children_with_offsets()
+        .map(|(_, offset)| u32::from(offset))
+        .collect();
+    assert_eq!(offsets, [0, 6, 9]);
+    for (child, offset) in tree.children_with_offsets() {
+        assert_eq!(child.text_range().start(), offset);
+    }
+
+    let node2 = tree.children().nth(2).unwrap();
+    let offsets: Vec<_> = node2
+        .children_with_offsets()
+        .map(|(_, offset)| u32::from(offset))
+        .collect();
+    assert_eq!(offsets, [9, 12, 15]);
+}
+
 #[test]
 fn token_text_eq() {
     let tree = tree_with_eq_tokens();
@@ -83,6 +218,38 @@ fn token_text_eq() {
     assert!(!leaf1_0.text_eq(leaf2_0));
 }
 
+#[test]
+fn token_text_eq_str() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let tree: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&two_level_tree(), &mut cache));
+    let leaf1_0 = tree.children().nth(1).unwrap().first_token().unwrap();
+
+    assert!(leaf1_0.text_eq_str("1.0", &interner));
+    assert!(!leaf1_0.text_eq_str("9.9", &interner));
+    assert!(!leaf1_0.text_eq_str("1.00", &interner));
+
+    let key = interner.get_or_intern("1.0");
+    let other_key = interner.get_or_intern("9.9");
+    assert!(leaf1_0.text_eq_key(key));
+    assert!(!leaf1_0.text_eq_key(other_key));
+}
+
+#[test]
+fn green_node_as_map_key() {
+    use std::collections::HashMap;
+
+    let mut interner = new_interner();
+    let first = build_tree_with_cache(&two_level_tree(), &mut NodeCache::with_interner(&mut interner));
+    let second = build_tree_with_cache(&two_level_tree(), &mut NodeCache::with_interner(&mut interner));
+    let other = build_tree_with_cache(&tree_with_eq_tokens(), &mut NodeCache::with_interner(&mut interner));
+
+    let mut memo = HashMap::new();
+    memo.insert(first, "two level");
+    assert_eq!(memo.get(&second), Some(&"two level"));
+    assert_eq!(memo.get(&other), None);
+}
+
 #[test]
 fn data() {
     let tree = two_level_tree();
@@ -137,6 +304,30 @@ fn with_interner() {
     }
 }
 
+#[test]
+fn finish_node_tree() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let mut builder: GreenNodeBuilder<SyntaxKind, _> = GreenNodeBuilder::with_cache(&mut cache);
+    build_recursive(&two_level_tree(), &mut builder, 0);
+    let green = builder.finish_node_tree();
+    let tree: SyntaxNode = SyntaxNode::new_root(green);
+    assert_eq!(tree.resolve_text(&interner), "0.00.11.02.02.12.2");
+
+    // the builder owns its cache, but the cache does not own the interner
+    let mut builder: GreenNodeBuilder<SyntaxKind, _> = GreenNodeBuilder::with_interner(&mut interner);
+    build_recursive(&two_level_tree(), &mut builder, 0);
+    assert_eq!(&builder.finish_node_tree(), tree.green());
+}
+
+#[test]
+#[should_panic = "use `finish` to keep it"]
+fn finish_node_tree_with_owned_interner() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    build_recursive(&two_level_tree(), &mut builder, 0);
+    let _ = builder.finish_node_tree();
+}
+
 #[test]
 fn inline_resolver() {
     let mut interner = new_interner();
@@ -170,6 +361,46 @@ fn inline_resolver() {
     }
 }
 
+#[test]
+fn empty_root() {
+    let (tree, resolver) = build_tree::<()>(&Element::Node(vec![]));
+    assert_eq!(tree.children().count(), 0);
+    assert_eq!(tree.children_with_tokens().count(), 0);
+    assert_eq!(tree.tokens().count(), 0);
+    assert_eq!(tree.text_range(), TextRange::empty(0.into()));
+    assert_eq!(tree.resolve_text(&resolver), "");
+    assert!(tree.first_child_or_token().is_none());
+    assert!(tree.first_token().is_none());
+    assert!(tree.last_token().is_none());
+    assert_eq!(tree.descendants_with_tokens().count(), 1);
+
+    let tree: ResolvedNode = SyntaxNode::new_root_with_resolver(tree.green().clone(), resolver);
+    assert_eq!(tree.text(), "");
+    assert_eq!(format!("{}", tree), "");
+    assert_eq!(format!("{:?}", tree), "SyntaxKind(0)@0..0");
+    assert_eq!(format!("{:#?}", tree), "SyntaxKind(0)@0..0\n");
+}
+
+#[test]
+fn display_structure() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    assert_eq!(
+        tree.display_structure().to_string(),
+        r#"SyntaxKind(0)@0..18
+  SyntaxKind(1)@0..6
+    SyntaxKind(2)@0..3 <token>
+    SyntaxKind(3)@3..6 <token>
+  SyntaxKind(4)@6..9
+    SyntaxKind(5)@6..9 <token>
+  SyntaxKind(6)@9..18
+    SyntaxKind(7)@9..12 <token>
+    SyntaxKind(8)@12..15 <token>
+    SyntaxKind(9)@15..18 <token>
+"#
+    );
+}
+
 #[test]
 fn assert_debug_display() {
     use std::fmt;
@@ -184,3 +415,1653 @@ fn assert_debug_display() {
     fn dbg<T: fmt::Debug>() {}
     dbg::<GreenNodeBuilder<'static, 'static, SyntaxKind>>();
 }
+
+#[test]
+fn token_index() {
+    let tree = two_level_tree();
+    let (tree, resolver) = build_tree::<()>(&tree);
+    let index = tree.build_token_index();
+    assert_eq!(index.len(), 6);
+
+    let texts = |offset: u32| -> Vec<_> {
+        index
+            .token_at(offset.into())
+            .map(|token| token.resolve_text(&resolver))
+            .collect()
+    };
+    assert_eq!(texts(0), ["0.0"]);
+    assert_eq!(texts(1), ["0.0"]);
+    assert_eq!(texts(3), ["0.0", "0.1"]);
+    assert_eq!(texts(7), ["1.0"]);
+    assert_eq!(texts(9), ["1.0", "2.0"]);
+    assert_eq!(texts(18), ["2.2"]);
+
+    for offset in 0..=18 {
+        let expected: Vec<_> = tree.token_at_offset(offset.into()).collect();
+        let actual: Vec<_> = index.token_at(offset.into()).cloned().collect();
+        assert_eq!(actual, expected, "mismatch at offset {offset}");
+    }
+}
+
+#[test]
+fn assemble_with_cache() {
+    let mut cache = NodeCache::new();
+    let built = build_tree_with_cache(&two_level_tree(), &mut cache);
+
+    let mut token = |kind: u32, text: &str| GreenElement::from(cache.token(SyntaxKind(kind), text));
+    let children1 = vec![token(2, "0.0"), token(3, "0.1")];
+    let children4 = vec![token(5, "1.0")];
+    let children6 = vec![token(7, "2.0"), token(8, "2.1"), token(9, "2.2")];
+    let node1 = cache.node(SyntaxKind(1), children1);
+    let node4 = cache.node(SyntaxKind(4), children4);
+    let node6 = cache.node(SyntaxKind(6), children6);
+    let root = cache.node(SyntaxKind(0), vec![node1.into(), node4.into(), node6.into()]);
+    assert_eq!(root, built);
+
+    // small nodes are deduplicated with the ones created by the builder
+    assert!(root.ptr_eq(&built));
+    assert!(!root.ptr_eq(&GreenNode::new(
+        root.kind(),
+        root.children().map(|child| child.cloned())
+    )));
+}
+
+#[test]
+fn ancestors_with_index() {
+    use Element::*;
+    let tree = Node(vec![
+        Token("a"),
+        Node(vec![Token("b"), Token("c"), Node(vec![Node(vec![Token("d")])])]),
+    ]);
+    let (tree, _) = build_tree::<()>(&tree);
+    let leaf_parent = tree.descendants().last().unwrap();
+    assert_eq!(leaf_parent.first_token().unwrap().syntax_kind(), RawSyntaxKind(7));
+
+    let ancestors: Vec<_> = leaf_parent
+        .ancestors_with_index()
+        .map(|(node, index)| (node.kind(), index))
+        .collect();
+    assert_eq!(
+        ancestors,
+        [
+            (SyntaxKind(6), None),
+            (SyntaxKind(5), Some(0)),
+            (SyntaxKind(2), Some(2)),
+            (SyntaxKind(0), Some(1)),
+        ]
+    );
+}
+
+struct TwoLevelTree;
+
+cstree::syntax_kind_constants! {
+    impl TwoLevelTree: SyntaxKind {
+        ROOT,
+        FIRST,
+        FIRST_0,
+        FIRST_1,
+        SECOND,
+        SECOND_0,
+    }
+}
+
+#[test]
+fn kind_constants() {
+    let tree = two_level_tree();
+    let (tree, resolver) = build_tree::<()>(&tree);
+    assert_eq!(tree.kind(), TwoLevelTree::ROOT);
+
+    let first = tree.children_with_tokens().of_kind(TwoLevelTree::FIRST).next().unwrap();
+    let first = first.into_node().unwrap();
+    let tokens: Vec<_> = first
+        .children_with_tokens()
+        .of_kind(TwoLevelTree::FIRST_1)
+        .map(|token| token.into_token().unwrap().resolve_text(&resolver))
+        .collect();
+    assert_eq!(tokens, ["0.1"]);
+    assert_eq!(tree.children_with_tokens().of_kind(TwoLevelTree::SECOND).count(), 1);
+    assert_eq!(tree.children_with_tokens().of_kind(TwoLevelTree::SECOND_0).count(), 0);
+}
+
+#[test]
+fn node_prev_next_token() {
+    let tree = two_level_tree();
+    let (tree, resolver) = build_tree::<()>(&tree);
+    let node1 = tree.children().nth(1).unwrap();
+    assert_eq!(node1.prev_token().unwrap().resolve_text(&resolver), "0.1");
+    assert_eq!(node1.next_token().unwrap().resolve_text(&resolver), "2.0");
+
+    let node0 = tree.children().next().unwrap();
+    assert!(node0.prev_token().is_none());
+    assert_eq!(node0.next_token().unwrap().resolve_text(&resolver), "1.0");
+    let node2 = tree.children().nth(2).unwrap();
+    assert!(node2.next_token().is_none());
+    assert!(tree.prev_token().is_none());
+    assert!(tree.next_token().is_none());
+}
+
+#[test]
+fn ancestor_checks() {
+    let tree = tree_with_eq_tokens();
+    let (tree, _) = build_tree::<()>(&tree);
+    let node0 = tree.children().next().unwrap();
+    let node2 = tree.children().nth(2).unwrap();
+    assert!(tree.is_ancestor_of(node0));
+    assert!(!node0.is_ancestor_of(&tree));
+    assert!(node0.is_descendant_of(&tree));
+    assert!(!tree.is_descendant_of(node0));
+    assert!(!tree.is_ancestor_of(&tree));
+    assert!(tree.contains(&tree));
+    assert!(tree.contains(node2));
+    assert!(!node0.contains(node2));
+
+    // the same green subtree in a different tree is unrelated
+    let (other, _) = build_tree::<()>(&tree_with_eq_tokens());
+    assert_eq!(other.green(), tree.green());
+    assert!(!tree.is_ancestor_of(other.children().next().unwrap()));
+    assert!(!tree.contains(&other));
+}
+
+#[test]
+fn contiguous_interner() {
+    use cstree::interning::ContiguousInterner;
+
+    let tree = tree_with_eq_tokens();
+    let (expected, default_resolver) = build_tree::<()>(&tree);
+
+    let mut interner = ContiguousInterner::new();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let green = build_tree_with_cache(&tree, &mut cache);
+    let actual: SyntaxNode = SyntaxNode::new_root(green);
+    // 3 distinct strings: "a", "b", "c"
+    assert_eq!(interner.len(), 3);
+
+    let expected_tokens: Vec<_> = expected
+        .tokens()
+        .map(|token| token.resolve_text(&default_resolver))
+        .collect();
+    let actual_tokens: Vec<_> = actual.tokens().map(|token| token.resolve_text(&interner)).collect();
+    assert_eq!(actual_tokens, expected_tokens);
+    assert_eq!(actual.resolve_text(&interner), expected.resolve_text(&default_resolver));
+}
+
+#[test]
+fn contiguous_interner_keys_are_stable() {
+    use cstree::interning::ContiguousInterner;
+
+    let mut interner = ContiguousInterner::new();
+    let keys: Vec<_> = (0..1000).map(|i| interner.get_or_intern(&i.to_string())).collect();
+    assert_eq!(interner.len(), 1000);
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(interner.resolve(*key), i.to_string());
+        assert_eq!(interner.get_or_intern(&i.to_string()), *key);
+    }
+    assert_eq!(interner.len(), 1000);
+}
+
+#[test]
+fn element_text_ranges() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let ranges: Vec<_> = tree.children_with_tokens().map(|child| child.text_range()).collect();
+    assert_eq!(
+        ranges,
+        [
+            TextRange::new(0.into(), 6.into()),
+            TextRange::new(6.into(), 9.into()),
+            TextRange::new(9.into(), 18.into()),
+        ]
+    );
+
+    let node2 = tree.children().nth(2).unwrap();
+    let ranges: Vec<_> = node2.children_with_tokens().map(|child| child.text_range()).collect();
+    assert_eq!(
+        ranges,
+        [
+            TextRange::new(9.into(), 12.into()),
+            TextRange::new(12.into(), 15.into()),
+            TextRange::new(15.into(), 18.into()),
+        ]
+    );
+}
+
+#[test]
+fn thin_traversal() {
+    let tree = two_level_tree();
+    let (tree, resolver) = build_tree::<()>(&tree);
+    let thin = tree.thin();
+    assert_eq!(thin.kind(), tree.kind());
+    assert_eq!(thin.text_range(), tree.text_range());
+    assert_eq!(thin.resolve_text(&resolver), tree.resolve_text(&resolver).to_string());
+
+    let children: Vec<_> = thin
+        .children()
+        .map(|child| (child.kind(), child.text_range()))
+        .collect();
+    let expected: Vec<_> = tree
+        .children()
+        .map(|child| (child.kind(), child.text_range()))
+        .collect();
+    assert_eq!(children, expected);
+
+    let descendants: Vec<_> = thin
+        .descendants_with_tokens()
+        .map(|element| (element.kind(), element.text_range()))
+        .collect();
+    let expected: Vec<_> = tree
+        .descendants_with_tokens()
+        .map(|element| (element.kind(), element.text_range()))
+        .collect();
+    assert_eq!(descendants, expected);
+
+    let tokens: Vec<_> = thin
+        .descendant_tokens()
+        .map(|token| token.resolve_text(&resolver))
+        .collect();
+    assert_eq!(tokens, ["0.0", "0.1", "1.0", "2.0", "2.1", "2.2"]);
+
+    let node2 = tree.children().nth(2).unwrap();
+    let thin2 = node2.thin();
+    assert_eq!(thin2.text_range(), TextRange::new(9.into(), 18.into()));
+    assert!(thin2.first_child().is_none());
+    assert_eq!(thin2.children_with_tokens().len(), 3);
+}
+
+#[test]
+fn debug_kind_names() {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct NamedKind(u32);
+
+    impl Syntax for NamedKind {
+        fn from_raw(raw: RawSyntaxKind) -> Self {
+            Self(raw.0)
+        }
+
+        fn into_raw(self) -> RawSyntaxKind {
+            RawSyntaxKind(self.0)
+        }
+
+        fn static_text(self) -> Option<&'static str> {
+            None
+        }
+
+        fn kind_name(self) -> Option<&'static str> {
+            match self.0 {
+                0 => Some("Root"),
+                4 => Some("Ident"),
+                _ => None,
+            }
+        }
+    }
+
+    let mut builder: GreenNodeBuilder<NamedKind> = GreenNodeBuilder::new();
+    builder.start_node(NamedKind(0));
+    builder.token(NamedKind(1), "0.0");
+    builder.start_node(NamedKind(4));
+    builder.token(NamedKind(5), "1.0");
+    builder.finish_node();
+    builder.finish_node();
+    let (green, cache) = builder.finish();
+    let resolver = cache.unwrap().into_interner().unwrap();
+    let root: cstree::syntax::SyntaxNode<NamedKind> = cstree::syntax::SyntaxNode::new_root(green);
+
+    let node = root.first_child().unwrap();
+    assert_eq!(node.debug(&resolver, false), "Ident@3..6");
+    assert_eq!(
+        node.first_token().unwrap().debug(&resolver),
+        "NamedKind(5)@3..6 \"1.0\""
+    );
+    assert_eq!(
+        root.debug(&resolver, true),
+        "Root@0..6\n  NamedKind(1)@0..3 \"0.0\"\n  Ident@3..6\n    NamedKind(5)@3..6 \"1.0\"\n"
+    );
+    assert_eq!(
+        root.display_structure().to_string(),
+        "Root@0..6\n  NamedKind(1)@0..3 <token>\n  Ident@3..6\n    NamedKind(5)@3..6 <token>\n"
+    );
+}
+
+#[test]
+fn node_as_str() {
+    let tree = two_level_tree();
+    let (tree, resolver) = build_tree::<()>(&tree);
+    let node1 = tree.children().nth(1).unwrap();
+    let node2 = tree.children().nth(2).unwrap();
+    assert_eq!(node1.as_str(&resolver), Some("1.0"));
+    assert_eq!(node2.as_str(&resolver), None);
+    assert_eq!(tree.as_str(&resolver), None);
+}
+
+#[test]
+fn builder_open_kinds() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    assert_eq!(builder.current_depth(), 0);
+    builder.start_node(SyntaxKind(0));
+    builder.token(SyntaxKind(1), "a");
+    builder.start_node(SyntaxKind(2));
+    assert_eq!(builder.current_depth(), 2);
+    assert_eq!(builder.open_kinds().collect::<Vec<_>>(), [SyntaxKind(0), SyntaxKind(2)]);
+    builder.token(SyntaxKind(3), "b");
+    builder.finish_node();
+    assert_eq!(builder.current_depth(), 1);
+    assert_eq!(builder.open_kinds().collect::<Vec<_>>(), [SyntaxKind(0)]);
+    builder.finish_node();
+    assert_eq!(builder.current_depth(), 0);
+    assert_eq!(builder.open_kinds().len(), 0);
+
+    let (green, _) = builder.finish();
+    let mut reference: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    reference.start_node(SyntaxKind(0));
+    reference.token(SyntaxKind(1), "a");
+    reference.start_node(SyntaxKind(2));
+    reference.token(SyntaxKind(3), "b");
+    reference.finish_node();
+    reference.finish_node();
+    assert_eq!(green, reference.finish().0);
+}
+
+#[test]
+fn token_line_text() {
+    use cstree::text::LineIndex;
+
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    builder.token(SyntaxKind(1), "let");
+    builder.token(SyntaxKind(2), " ");
+    builder.token(SyntaxKind(3), "x");
+    builder.token(SyntaxKind(2), "\n");
+    builder.token(SyntaxKind(4), "\"a\nb\"");
+    builder.token(SyntaxKind(2), "\n");
+    builder.finish_node();
+    let (green, cache) = builder.finish();
+    let resolver = cache.unwrap().into_interner().unwrap();
+    let root: SyntaxNode = SyntaxNode::new_root(green);
+    let line_index = LineIndex::new(&root.resolve_text(&resolver).to_string());
+    let tokens: Vec<_> = root
+        .children_with_tokens()
+        .map(|t| t.into_token().unwrap().clone())
+        .collect();
+
+    assert_eq!(
+        tokens[2].line_text(&resolver, &line_index),
+        ("let x".to_string(), TextRange::new(4.into(), 5.into()))
+    );
+    // the line break belongs to the first line
+    assert_eq!(
+        tokens[3].line_text(&resolver, &line_index),
+        ("let x".to_string(), TextRange::new(5.into(), 6.into()))
+    );
+    // tokens that contain line breaks return all lines
+    assert_eq!(
+        tokens[4].line_text(&resolver, &line_index),
+        ("\"a\nb\"".to_string(), TextRange::new(0.into(), 5.into()))
+    );
+}
+
+#[test]
+fn identical_tokens_are_shared() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    builder.token(SyntaxKind(5), "x");
+    builder.start_node(SyntaxKind(1));
+    builder.token(SyntaxKind(5), "x");
+    builder.token(SyntaxKind(6), "x");
+    builder.finish_node();
+    builder.finish_node();
+    let (green, _) = builder.finish();
+    let root: SyntaxNode = SyntaxNode::new_root(green);
+
+    let tokens: Vec<_> = root.tokens().map(|token| token.green().clone()).collect();
+    assert!(tokens[0].ptr_eq(&tokens[1]));
+    assert_eq!(tokens[0].text_key(), tokens[2].text_key());
+    // tokens with the same text, but a different kind
+    assert!(!tokens[0].ptr_eq(&tokens[2]));
+}
+
+#[test]
+fn child_nodes_of_kind() {
+    let tree = two_level_tree();
+    let (tree, resolver) = build_tree::<()>(&tree);
+    let node = tree.child_node(SyntaxKind(6)).unwrap();
+    assert_eq!(node.text_range(), TextRange::new(9.into(), 18.into()));
+    assert_eq!(node.resolve_text(&resolver), "2.02.12.2");
+    assert_eq!(tree.child_nodes(SyntaxKind(4)).count(), 1);
+
+    // tokens and descendants are not considered
+    assert!(node.child_node(SyntaxKind(7)).is_none());
+    assert!(tree.child_node(SyntaxKind(2)).is_none());
+    assert_eq!(tree.child_nodes(SyntaxKind(0)).count(), 0);
+}
+
+#[test]
+fn growable_tree() {
+    use cstree::build::GrowableTree;
+
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+
+    let mut tree: GrowableTree<SyntaxKind, _> = GrowableTree::new(green.clone(), &mut cache);
+    tree.append_token(SyntaxKind(10), "3.0");
+    tree.append_token(SyntaxKind(11), "3.1");
+    assert_eq!(tree.text_len(), 24.into());
+    let root: SyntaxNode = SyntaxNode::new_root(tree.finish());
+    let ranges: Vec<_> = root.children_with_tokens().map(|child| child.text_range()).collect();
+    assert_eq!(
+        ranges[3..],
+        [
+            TextRange::new(18.into(), 21.into()),
+            TextRange::new(21.into(), 24.into())
+        ]
+    );
+    assert_eq!(root.resolve_text(cache.interner()), "0.00.11.02.02.12.23.03.1");
+    // existing children are re-used
+    assert!(root
+        .children()
+        .next()
+        .unwrap()
+        .green()
+        .ptr_eq(green.children().next().unwrap().as_node().unwrap()));
+
+    // appending to a nested node
+    let mut tree: GrowableTree<SyntaxKind, _> = GrowableTree::with_open_node(green.clone(), &[1], &mut cache).unwrap();
+    tree.append_token(SyntaxKind(12), "!");
+    let root: SyntaxNode = SyntaxNode::new_root(tree.green());
+    assert_eq!(root.resolve_text(cache.interner()), "0.00.11.0!2.02.12.2");
+    let node1 = root.children().nth(1).unwrap();
+    assert_eq!(node1.text_range(), TextRange::new(6.into(), 10.into()));
+    assert_eq!(
+        root.children().nth(2).unwrap().text_range(),
+        TextRange::new(10.into(), 19.into())
+    );
+
+    assert!(GrowableTree::<SyntaxKind, _>::with_open_node(green.clone(), &[1, 0], &mut cache).is_none());
+    assert!(GrowableTree::<SyntaxKind, _>::with_open_node(green, &[3], &mut cache).is_none());
+}
+
+#[test]
+fn duplicate_candidates() {
+    use cstree::build::StructuralKey;
+
+    let mut cache = NodeCache::new();
+    let tree = two_level_tree();
+    build_tree_with_cache(&tree, &mut cache);
+    // building the same tree again is fully deduplicated
+    build_tree_with_cache(&tree, &mut cache);
+    assert!(cache.duplicate_candidates().is_empty());
+
+    // a near-duplicate of node 1 that uses a different kind for its token
+    let token = cache.token(SyntaxKind(9), "1.0");
+    cache.node(SyntaxKind(4), vec![token.into()]);
+    let key = StructuralKey {
+        kind:     RawSyntaxKind(4),
+        text_len: 3.into(),
+        children: 1,
+    };
+    assert_eq!(cache.duplicate_candidates(), [(key, 2)]);
+}
+
+#[test]
+fn preorder_skip_subtree() {
+    use cstree::traversal::WalkEvent;
+
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let mut preorder = tree.preorder_with_tokens();
+    let mut entered = Vec::new();
+    while let Some(event) = preorder.next() {
+        if let WalkEvent::Enter(element) = event {
+            entered.push(element.kind());
+            if element.kind() == SyntaxKind(6) {
+                preorder.skip_subtree();
+            }
+        }
+    }
+    let expected: Vec<_> = [0, 1, 2, 3, 4, 5, 6].into_iter().map(SyntaxKind).collect();
+    assert_eq!(entered, expected);
+
+    // the matching `Leave` event is still produced
+    let mut preorder = tree.preorder();
+    let mut events = Vec::new();
+    while let Some(event) = preorder.next() {
+        if matches!(event, WalkEvent::Enter(node) if node == &tree) {
+            preorder.skip_subtree();
+        }
+        events.push(event);
+    }
+    assert_eq!(events, [WalkEvent::Enter(&tree), WalkEvent::Leave(&tree)]);
+}
+
+#[test]
+fn preorder_skip_subtree_after_leave() {
+    use cstree::traversal::WalkEvent;
+
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+
+    // skipping after a `Leave` must not skip the following siblings
+    let mut preorder = tree.preorder();
+    let mut events = Vec::new();
+    while let Some(event) = preorder.next() {
+        if matches!(event, WalkEvent::Leave(_)) {
+            preorder.skip_subtree();
+        }
+        events.push(event);
+    }
+    assert_eq!(events, tree.preorder().collect::<Vec<_>>());
+
+    let mut preorder = tree.preorder_with_tokens();
+    let mut events = Vec::new();
+    while let Some(event) = preorder.next() {
+        if matches!(event, WalkEvent::Leave(_)) {
+            preorder.skip_subtree();
+        }
+        events.push(event);
+    }
+    assert_eq!(events, tree.preorder_with_tokens().collect::<Vec<_>>());
+}
+
+#[test]
+fn dedup_threshold() {
+    let tree = two_level_tree();
+    let mut cache = NodeCache::new();
+    let deduplicated: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&tree, &mut cache));
+    for threshold in [2, 4] {
+        let mut thresholded = NodeCache::new().with_dedup_threshold(threshold);
+        let root: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&tree, &mut thresholded));
+        assert_eq!(root.green(), deduplicated.green());
+        assert_eq!(root.resolve_text(thresholded.interner()), "0.00.11.02.02.12.2");
+        let ranges: Vec<_> = root.descendants_with_tokens().map(|e| e.text_range()).collect();
+        let expected: Vec<_> = deduplicated.descendants_with_tokens().map(|e| e.text_range()).collect();
+        assert_eq!(ranges, expected);
+
+        // nodes with fewer children are not shared
+        let token = thresholded.token(SyntaxKind(5), "1.0");
+        let single = || vec![token.clone().into()];
+        assert!(!thresholded
+            .node(SyntaxKind(4), single())
+            .ptr_eq(&thresholded.node(SyntaxKind(4), single())));
+        let pair = || vec![token.clone().into(), token.clone().into()];
+        let first = thresholded.node(SyntaxKind(4), pair());
+        assert_eq!(first.ptr_eq(&thresholded.node(SyntaxKind(4), pair())), threshold <= 2);
+    }
+}
+
+#[test]
+fn tokens_in_range() {
+    let tree = two_level_tree();
+    let (tree, resolver) = build_tree::<()>(&tree);
+    let tokens = |start: u32, end: u32| -> Vec<_> {
+        tree.tokens_in_range(TextRange::new(start.into(), end.into()))
+            .map(|token| token.resolve_text(&resolver))
+            .collect()
+    };
+    assert_eq!(tokens(5, 13), ["0.1", "1.0", "2.0", "2.1"]);
+    assert_eq!(tokens(6, 9), ["1.0"]);
+    assert_eq!(tokens(0, 18), ["0.0", "0.1", "1.0", "2.0", "2.1", "2.2"]);
+    // empty ranges return the tokens on both sides
+    assert_eq!(tokens(9, 9), ["1.0", "2.0"]);
+    assert_eq!(tokens(10, 10), ["2.0"]);
+    assert_eq!(tokens(18, 18), ["2.2"]);
+
+    let node2 = tree.children().nth(2).unwrap();
+    let tokens: Vec<_> = node2
+        .tokens_in_range(TextRange::new(0.into(), 10.into()))
+        .map(|token| token.text_range())
+        .collect();
+    assert_eq!(tokens, [TextRange::new(9.into(), 12.into())]);
+}
+
+#[test]
+fn element_try_from() {
+    use cstree::syntax::SyntaxElement;
+
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let node2 = tree.children().nth(2).unwrap();
+
+    let element: SyntaxElement<SyntaxKind> = node2.clone().into();
+    let node = SyntaxNode::try_from(element).unwrap();
+    assert_eq!(&node, node2);
+
+    let element: SyntaxElement<SyntaxKind> = node2.first_token().unwrap().clone().into();
+    let err = SyntaxNode::try_from(element.clone()).unwrap_err();
+    assert_eq!(err.element(), &element);
+    assert_eq!(err.into_element(), element);
+    let token: SyntaxToken = element.try_into().unwrap();
+    assert_eq!(token.text_range(), TextRange::new(9.into(), 12.into()));
+
+    // by reference
+    let child = node2.children_with_tokens().next().unwrap();
+    let token: &SyntaxToken = child.try_into().unwrap();
+    assert_eq!(token.kind(), SyntaxKind(7));
+    let err = <&SyntaxNode>::try_from(child).unwrap_err();
+    assert_eq!(err.to_string(), "expected a node, found a token");
+    assert_eq!(err.into_element(), child);
+}
+
+#[test]
+fn heap_size() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let node = tree.child_node(SyntaxKind(6)).unwrap();
+    let token = node.first_token().unwrap();
+    assert!(token.green().heap_size() > 0);
+    assert!(node.green().heap_size() > token.green().heap_size());
+    assert!(tree.green().heap_size() > node.green().heap_size());
+    // the root contains all other nodes
+    let children: usize = tree.children().map(|child| child.green().heap_size()).sum();
+    assert!(tree.green().heap_size() > children);
+}
+
+#[test]
+fn kind_set() {
+    let tree = two_level_tree();
+    let (tree, resolver) = build_tree::<()>(&tree);
+    let set = KindSet::from([SyntaxKind(5), SyntaxKind(8), SyntaxKind(200)]);
+    assert_eq!(set.len(), 3);
+    assert!(set.contains(SyntaxKind(200)));
+    assert!(!set.contains(SyntaxKind(7)));
+    let tokens: Vec<_> = tree
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| set.contains(token.kind()))
+        .map(|token| token.resolve_text(&resolver))
+        .collect();
+    assert_eq!(tokens, ["1.0", "2.1"]);
+}
+
+#[test]
+fn range_in_ancestor() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let node = tree.child_node(SyntaxKind(6)).unwrap();
+    let ranges: Vec<_> = node
+        .children_with_tokens()
+        .map(|child| child.into_token().unwrap())
+        .map(|token| token.range_in(node).unwrap())
+        .collect();
+    assert_eq!(
+        ranges,
+        [
+            TextRange::new(0.into(), 3.into()),
+            TextRange::new(3.into(), 6.into()),
+            TextRange::new(6.into(), 9.into()),
+        ]
+    );
+    assert_eq!(node.range_in(node), Some(TextRange::up_to(9.into())));
+    assert_eq!(node.range_in(&tree), Some(node.text_range()));
+    let other = tree.child_node(SyntaxKind(4)).unwrap();
+    assert_eq!(other.range_in(node), None);
+    assert_eq!(tree.range_in(node), None);
+}
+
+#[test]
+fn indexed_child() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let child = tree.child(2).unwrap();
+    assert_eq!(child.kind(), SyntaxKind(6));
+    assert_eq!(child.text_range(), TextRange::new(9.into(), 18.into()));
+    // indexed access returns the same node as iteration
+    assert_eq!(child, tree.children_with_tokens().nth(2).unwrap());
+    assert!(tree.child(3).is_none());
+
+    let node = child.into_node().unwrap();
+    let token = node.child(2).unwrap();
+    assert_eq!(token.kind(), SyntaxKind(9));
+    assert_eq!(token.text_range(), TextRange::new(15.into(), 18.into()));
+    assert_eq!(tree.green().child(2).unwrap().kind(), RawSyntaxKind(6));
+}
+
+#[test]
+fn eq_unordered() {
+    const LIST: SyntaxKind = SyntaxKind(0);
+    const ATTRIBUTE: SyntaxKind = SyntaxKind(1);
+    const NAME: SyntaxKind = SyntaxKind(2);
+
+    let mut cache = NodeCache::new();
+    let mut build = |names: &[&str]| {
+        let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::with_cache(&mut cache);
+        builder.start_node(LIST);
+        for name in names {
+            builder.start_node(ATTRIBUTE);
+            builder.token(NAME, name);
+            builder.finish_node();
+        }
+        builder.finish_node();
+        SyntaxNode::<()>::new_root(builder.finish().0)
+    };
+    let left = build(&["inline", "cold", "inline"]);
+    let reordered = build(&["cold", "inline", "inline"]);
+    let different = build(&["cold", "cold", "inline"]);
+
+    assert!(left.eq_unordered(&reordered, |kind| kind == LIST));
+    assert!(!left.eq_unordered(&reordered, |_| false));
+    assert_ne!(left.green(), reordered.green());
+    // children are compared as multisets, not sets
+    assert!(!left.eq_unordered(&different, |kind| kind == LIST));
+}
+
+#[test]
+fn reintern_live_trees() {
+    let mut cache = NodeCache::new();
+    let closed = build_tree_with_cache(&Element::Node(vec![Element::Token("closed")]), &mut cache);
+    let open = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let text = SyntaxNode::<()>::new_root(open.clone())
+        .resolve_text(cache.interner())
+        .to_string();
+    drop(closed);
+
+    let mut compacted = NodeCache::new();
+    let (roots, keys) = compacted.reintern(std::slice::from_ref(&open), cache.interner());
+    let resolver = cache.into_interner().unwrap();
+    // only the text of the surviving tree was interned again
+    assert_eq!(keys.len(), 6);
+    for (old, new) in &keys {
+        assert_eq!(resolver.resolve(*old), compacted.interner().resolve(*new));
+    }
+    drop(resolver);
+
+    let [root] = &roots[..] else { panic!() };
+    let root: SyntaxNode = SyntaxNode::new_root(root.clone());
+    assert_eq!(root.resolve_text(compacted.interner()), text.as_str());
+    assert_eq!(root.green().children().len(), open.children().len());
+}
+
+#[test]
+fn start_node_at_trimmed() {
+    const WHITESPACE: SyntaxKind = SyntaxKind(1);
+    let is_trivia = |kind| kind == WHITESPACE;
+
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    let checkpoint = builder.checkpoint();
+    builder.token(WHITESPACE, " ");
+    builder.token(WHITESPACE, "\n");
+    builder.token(SyntaxKind(2), "a");
+    builder.token(WHITESPACE, " ");
+    builder.start_node_at_trimmed(checkpoint, SyntaxKind(3), is_trivia);
+    builder.finish_node();
+    // only trivia: the new node is empty
+    let checkpoint = builder.checkpoint();
+    builder.token(WHITESPACE, " ");
+    builder.start_node_at_trimmed(checkpoint, SyntaxKind(4), is_trivia);
+    builder.finish_node();
+    builder.finish_node();
+    let (tree, cache) = builder.finish();
+    let resolver = cache.unwrap().into_interner().unwrap();
+    let root: SyntaxNode = SyntaxNode::new_root(tree);
+
+    let kinds: Vec<_> = root.children_with_tokens().map(|child| child.kind()).collect();
+    assert_eq!(
+        kinds,
+        [WHITESPACE, WHITESPACE, SyntaxKind(3), WHITESPACE, SyntaxKind(4)]
+    );
+    let node = root.child_node(SyntaxKind(3)).unwrap();
+    assert_eq!(node.text_range(), TextRange::new(2.into(), 4.into()));
+    assert_eq!(node.resolve_text(&resolver), "a ");
+    assert!(root.child_node(SyntaxKind(4)).unwrap().text_range().is_empty());
+}
+
+#[test]
+fn flags_propagate_to_ancestors() {
+    const ERROR: u8 = 0b01;
+    const MACRO: u8 = 0b10;
+
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    builder.start_node(SyntaxKind(1));
+    builder.start_node(SyntaxKind(2));
+    builder.token(SyntaxKind(3), "x");
+    builder.set_flags(ERROR);
+    builder.finish_node();
+    builder.finish_node();
+    builder.start_node(SyntaxKind(2));
+    builder.set_flags(MACRO);
+    builder.token(SyntaxKind(3), "x");
+    builder.finish_node();
+    builder.start_node(SyntaxKind(2));
+    builder.token(SyntaxKind(3), "x");
+    builder.finish_node();
+    builder.finish_node();
+    let root: SyntaxNode = SyntaxNode::new_root(builder.finish().0);
+
+    assert_eq!(root.flags(), ERROR | MACRO);
+    let children: Vec<_> = root.children().map(|child| child.flags()).collect();
+    assert_eq!(children, [ERROR, MACRO, 0]);
+    let deep = root.first_child().unwrap().first_child().unwrap();
+    assert_eq!(deep.flags(), ERROR);
+    // nodes with different flags are not deduplicated
+    let plain = root.last_child().unwrap();
+    assert_ne!(deep.green(), plain.green());
+    // subtrees without errors can be skipped
+    let errors = root.descendants().filter(|node| node.flags() & ERROR != 0).count();
+    assert_eq!(errors, 3);
+}
+
+#[test]
+fn flags_survive_rebuilds() {
+    use cstree::text::TextRange;
+
+    const ERROR: u8 = 0b01;
+
+    let mut cache = NodeCache::new();
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::with_cache(&mut cache);
+    builder.start_node(SyntaxKind(0));
+    builder.start_node(SyntaxKind(1));
+    builder.set_flags(ERROR);
+    builder.token(SyntaxKind(2), "x");
+    builder.finish_node();
+    builder.start_node(SyntaxKind(1));
+    builder.token(SyntaxKind(2), "y");
+    builder.finish_node();
+    builder.finish_node();
+    let (green, _) = builder.finish();
+    let flags = |green: &GreenNode| {
+        let root: SyntaxNode = SyntaxNode::new_root(green.clone());
+        root.descendants().map(|node| node.flags()).collect::<Vec<_>>()
+    };
+    assert_eq!(flags(&green), [ERROR, ERROR, 0]);
+
+    let mut decoded = NodeCache::new();
+    let bytes = green.to_bytes(cache.interner());
+    assert_eq!(
+        flags(&GreenNode::from_bytes::<SyntaxKind, _>(&bytes, &mut decoded).unwrap()),
+        [ERROR, ERROR, 0]
+    );
+    let (reinterned, _) = NodeCache::new().reintern(std::slice::from_ref(&green), cache.interner());
+    assert_eq!(flags(&reinterned[0]), [ERROR, ERROR, 0]);
+
+    // rebuilding the flagged node keeps its own flags
+    let root: SyntaxNode = SyntaxNode::new_root(green);
+    let edited = root
+        .apply_edits(&[(TextRange::new(0.into(), 1.into()), "z")], &mut cache)
+        .unwrap();
+    assert_eq!(flags(&edited), [ERROR, ERROR, 0]);
+    let token = root.first_child().unwrap().first_token().unwrap();
+    assert_eq!(
+        flags(&token.replace_with(cache.token(SyntaxKind(2), "z"))),
+        [ERROR, ERROR, 0]
+    );
+    // replacing it with an unflagged node clears the flags of its ancestors
+    let plain = root.last_child().unwrap().green().clone();
+    assert_eq!(flags(&root.first_child().unwrap().replace_with(plain)), [0, 0, 0]);
+}
+
+#[test]
+fn extract_subtree() {
+    let tree = two_level_tree();
+    let (tree, resolver) = build_tree::<()>(&tree);
+    let node2 = tree.child_node(SyntaxKind(6)).unwrap();
+    let (green, extracted) = node2.extract(&resolver);
+    assert_eq!(green.kind(), RawSyntaxKind(6));
+
+    let node: SyntaxNode = SyntaxNode::new_root(green);
+    assert_eq!(node.text_range(), TextRange::up_to(9.into()));
+    let tokens: Vec<_> = node
+        .children_with_tokens()
+        .map(|child| child.into_token().unwrap())
+        .collect();
+    let texts: Vec<_> = tokens.iter().map(|token| token.resolve_text(&extracted)).collect();
+    assert_eq!(texts, ["2.0", "2.1", "2.2"]);
+    // the new interner only contains the text of the subtree
+    let keys: HashSet<_> = tokens.iter().map(|token| token.green().text_key().unwrap()).collect();
+    assert_eq!(keys.len(), 3);
+    assert!(keys.iter().all(|&key| extracted.try_resolve(key).is_some()));
+}
+
+#[test]
+fn node_map() {
+    let tree = two_level_tree();
+    let (tree, _) = build_tree::<()>(&tree);
+    let node1 = tree.child_node(SyntaxKind(1)).unwrap();
+    let node2 = tree.child_node(SyntaxKind(6)).unwrap();
+
+    let mut map = NodeMap::new();
+    assert_eq!(map.insert(node1, "first"), None);
+    assert_eq!(map.insert(node2, "second"), None);
+    assert_eq!(map.insert(node2, "third"), Some("second"));
+    assert_eq!(map.get(node1), Some(&"first"));
+    assert_eq!(map.get(node2), Some(&"third"));
+    assert_eq!(map.get(&tree), None);
+    assert_eq!(map.len(), 2);
+
+    // looking up structurally equal nodes of another tree does not find the values
+    let (other, _) = build_tree::<()>(&two_level_tree());
+    assert_eq!(map.get(other.child_node(SyntaxKind(1)).unwrap()), None);
+
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.get(node1), None);
+}
+
+#[test]
+fn from_nested() {
+    let mut cache = NodeCache::new();
+    let expected = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let k = SyntaxKind;
+    let items = [
+        (0, k(0), None),
+        (1, k(1), None),
+        (2, k(2), Some("0.0")),
+        (2, k(3), Some("0.1")),
+        (1, k(4), None),
+        (2, k(5), Some("1.0")),
+        (1, k(6), None),
+        (2, k(7), Some("2.0")),
+        (2, k(8), Some("2.1")),
+        (2, k(9), Some("2.2")),
+    ];
+    let tree = GreenNode::from_nested(&items, &mut cache).unwrap();
+    assert_eq!(tree, expected);
+
+    let invalid =
+        |items: &[(usize, SyntaxKind, Option<&str>)]| match GreenNode::from_nested(items, &mut NodeCache::new()) {
+            Err(BuildError::InvalidNesting { index }) => index,
+            result => panic!("expected invalid nesting, got {result:?}"),
+        };
+    // skipping a level
+    assert_eq!(invalid(&[(0, k(0), None), (1, k(1), None), (3, k(2), Some("x"))]), 2);
+    // a second root
+    assert_eq!(invalid(&[(0, k(0), None), (0, k(1), None)]), 1);
+    // a token as the root, or no root at all
+    assert_eq!(invalid(&[(0, k(0), Some("x"))]), 0);
+    assert_eq!(invalid(&[(1, k(0), None)]), 0);
+    assert_eq!(invalid(&[]), 0);
+}
+
+#[test]
+fn ptr_eq_position() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    for _ in 0..2 {
+        builder.start_node(SyntaxKind(1));
+        builder.token(SyntaxKind(2), "x");
+        builder.finish_node();
+    }
+    builder.finish_node();
+    let (green, _) = builder.finish();
+    let root: SyntaxNode = SyntaxNode::new_root(green.clone());
+    let (first, second) = (root.first_child().unwrap(), root.last_child().unwrap());
+    // both children share the same deduplicated green node
+    assert!(first.green().ptr_eq(second.green()));
+    assert!(!first.ptr_eq_position(second));
+    assert_ne!(first, second);
+    assert!(first.ptr_eq_position(first));
+
+    // the same node of another tree over the same green tree
+    let other: SyntaxNode = SyntaxNode::new_root(green);
+    let other_first = other.first_child().unwrap();
+    assert_ne!(first, other_first);
+    assert!(first.ptr_eq_position(other_first));
+}
+
+#[test]
+fn pre_interned_tokens() {
+    let mut interner = new_interner();
+    let keywords: Vec<_> = ["fn", "let"]
+        .iter()
+        .map(|keyword| (interner.get_or_intern(keyword), TextSize::of(*keyword)))
+        .collect();
+
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::with_interner(&mut interner);
+    builder.start_node(SyntaxKind(0));
+    for &(key, len) in &keywords {
+        builder.token_interned(SyntaxKind(1), key, len);
+    }
+    builder.token(SyntaxKind(1), "let");
+    builder.finish_node();
+    let (tree, _) = builder.finish();
+    let root: SyntaxNode = SyntaxNode::new_root(tree);
+    assert_eq!(root.resolve_text(&interner), "fnletlet");
+    let tokens: Vec<_> = root
+        .children_with_tokens()
+        .map(|child| child.into_token().unwrap())
+        .collect();
+    assert_eq!(tokens[1].text_range(), TextRange::new(2.into(), 5.into()));
+    // tokens with pre-interned text are deduplicated with other tokens
+    assert!(tokens[1].green().ptr_eq(tokens[2].green()));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "length")]
+fn pre_interned_token_with_wrong_length() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    let key = builder.interner_mut().get_or_intern("let");
+    builder.start_node(SyntaxKind(0));
+    builder.token_interned(SyntaxKind(1), key, 2.into());
+}
+
+#[test]
+fn trimmed_range() {
+    const WHITESPACE: SyntaxKind = SyntaxKind(1);
+    let is_trivia = |kind| kind == WHITESPACE;
+
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    builder.token(WHITESPACE, "  ");
+    builder.start_node(SyntaxKind(2));
+    builder.token(WHITESPACE, " ");
+    builder.token(SyntaxKind(3), "a");
+    builder.token(WHITESPACE, " ");
+    builder.token(SyntaxKind(3), "b");
+    builder.finish_node();
+    builder.token(WHITESPACE, "\n");
+    builder.start_node(SyntaxKind(4));
+    builder.token(WHITESPACE, " ");
+    builder.finish_node();
+    builder.finish_node();
+    let root: SyntaxNode = SyntaxNode::new_root(builder.finish().0);
+
+    // trivia inside of child nodes is trimmed as well, but not trivia between other tokens
+    assert_eq!(root.trimmed_range(is_trivia), TextRange::new(3.into(), 6.into()));
+    let inner = root.first_child().unwrap();
+    assert_eq!(inner.text_range(), TextRange::new(2.into(), 6.into()));
+    assert_eq!(inner.trimmed_range(is_trivia), TextRange::new(3.into(), 6.into()));
+    let only_trivia = root.last_child().unwrap();
+    assert_eq!(only_trivia.trimmed_range(is_trivia), TextRange::empty(7.into()));
+    assert_eq!(root.trimmed_range(|_| false), root.text_range());
+}
+
+#[test]
+fn smallest_node_covering() {
+    let (tree, _) = build_tree::<()>(&two_level_tree());
+
+    // a range within a single token yields the token's parent
+    let node = tree
+        .smallest_node_covering(TextRange::new(12.into(), 15.into()))
+        .unwrap();
+    assert_eq!(node.kind(), SyntaxKind(6));
+    assert_eq!(node.text_range(), TextRange::new(9.into(), 18.into()));
+    assert_eq!(
+        tree.covering_element(TextRange::new(12.into(), 15.into())).kind(),
+        SyntaxKind(8)
+    );
+
+    // a range spanning several children is covered by their common parent
+    let node = tree
+        .smallest_node_covering(TextRange::new(5.into(), 10.into()))
+        .unwrap();
+    assert_eq!(node, &tree);
+    assert_eq!(tree.smallest_node_covering(TextRange::new(10.into(), 20.into())), None);
+}
+
+#[test]
+fn children_size_hints() {
+    let (tree, _) = build_tree::<()>(&two_level_tree());
+
+    let mut children = tree.children_with_tokens();
+    assert_eq!(children.len(), 3);
+    children.next();
+    assert_eq!(children.len(), 2);
+
+    // collecting allocates exactly once (small vectors always reserve space for at least 4 elements)
+    let (wide, _) = build_tree::<()>(&Element::Node((0..10).map(|_| Element::Token("x")).collect()));
+    let collected: Vec<_> = wide.children_with_tokens().collect();
+    assert_eq!(collected.capacity(), 10);
+
+    // only child nodes are counted, not tokens
+    let first = tree.first_child().unwrap();
+    assert_eq!(first.children().len(), 0);
+    assert_eq!(first.children().count(), 0);
+    assert_eq!(first.children_with_tokens().len(), 2);
+    let mut children = tree.children();
+    assert_eq!(children.len(), 3);
+    children.next();
+    assert_eq!(children.size_hint(), (2, Some(2)));
+    let (mixed, _) = build_tree::<()>(&Element::Node(vec![
+        Element::Token("a"),
+        Element::Node(vec![Element::Token("b")]),
+        Element::Token("c"),
+    ]));
+    assert_eq!(mixed.children().len(), 1);
+}
+
+#[test]
+fn node_len_hints() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node_with_len_hint(SyntaxKind(0), 5.into());
+    builder.start_node(SyntaxKind(1));
+    builder.token(SyntaxKind(2), "ab");
+    builder.finish_node();
+    builder.start_node_with_len_hint(SyntaxKind(1), 3.into());
+    builder.token(SyntaxKind(2), "cde");
+    builder.finish_node();
+    builder.finish_node();
+    let (tree, _) = builder.finish();
+    assert_eq!(tree.text_len(), 5.into());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "length hint")]
+fn wrong_node_len_hint() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node_with_len_hint(SyntaxKind(0), 2.into());
+    builder.token(SyntaxKind(1), "abc");
+    builder.finish_node();
+}
+
+#[test]
+fn registered_classifier() {
+    const WHITESPACE: SyntaxKind = SyntaxKind(1);
+
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    builder.token(WHITESPACE, "  ");
+    builder.start_node(SyntaxKind(2));
+    builder.token(SyntaxKind(3), "a");
+    builder.token(WHITESPACE, " ");
+    builder.finish_node();
+    builder.finish_node();
+    let root: SyntaxNode = SyntaxNode::new_root(builder.finish().0);
+    let inner = root.first_child().unwrap().clone();
+    assert!(root.classifier().is_none());
+    assert_eq!(root.trimmed_range_by_classifier(), root.text_range());
+
+    // the classifier applies to all nodes of the tree, including ones that were created before
+    let root = root.with_classifier(|kind| kind == WHITESPACE);
+    assert_eq!(root.trimmed_range_by_classifier(), TextRange::new(2.into(), 3.into()));
+    assert_eq!(inner.trimmed_range_by_classifier(), TextRange::new(2.into(), 3.into()));
+    let kinds: Vec<_> = root.non_trivia_tokens().map(|token| token.kind()).collect();
+    assert_eq!(kinds, [SyntaxKind(3)]);
+    assert!(root.first_token().unwrap().is_trivia());
+    assert!(!inner.first_token().unwrap().is_trivia());
+}
+
+#[test]
+fn elements_between() {
+    let (tree, _) = build_tree::<()>(&two_level_tree());
+    let children: Vec<_> = tree.children_with_tokens().collect();
+
+    let between: Vec<_> = tree
+        .elements_between(children[0], children[2])
+        .map(|el| el.kind())
+        .collect();
+    assert_eq!(between, [SyntaxKind(4)]);
+    assert_eq!(tree.elements_between(children[0], children[1]).count(), 0);
+
+    // elements out of order or of another parent yield nothing
+    assert_eq!(tree.elements_between(children[2], children[0]).count(), 0);
+    let nested = tree.first_child().unwrap();
+    let tokens: Vec<_> = nested.children_with_tokens().collect();
+    assert_eq!(tree.elements_between(tokens[0], children[2]).count(), 0);
+    assert_eq!(nested.elements_between(tokens[0], tokens[1]).count(), 0);
+}
+
+#[test]
+fn file_id() {
+    let (tree, resolver) = build_tree::<()>(&two_level_tree());
+    let deep = tree.last_child().unwrap().last_token().unwrap().clone();
+    assert_eq!(deep.file_id(), None);
+
+    let tree = tree.with_file_id(FileId(3));
+    assert_eq!(tree.file_id(), Some(FileId(3)));
+    assert_eq!(deep.file_id(), Some(FileId(3)));
+    assert_eq!(tree.children().nth(1).unwrap().file_id(), Some(FileId(3)));
+
+    // other trees of the same green tree are not affected
+    let other: SyntaxNode = SyntaxNode::new_root(tree.green().clone());
+    assert_eq!(other.file_id(), None);
+    let resolved =
+        SyntaxNode::<SyntaxKind>::new_root_with_resolver(tree.green().clone(), resolver).with_file_id(FileId(4));
+    assert_eq!(resolved.first_child().unwrap().file_id(), Some(FileId(4)));
+}
+
+#[test]
+fn text_chunks() {
+    let (tree, resolver) = build_tree::<()>(&two_level_tree());
+    let chunks: Vec<_> = tree.text_chunks(&resolver).collect();
+    assert_eq!(chunks, ["0.0", "0.1", "1.0", "2.0", "2.1", "2.2"]);
+    assert_eq!(tree.resolve_text(&resolver), chunks.concat().as_str());
+
+    let node2 = tree.last_child().unwrap();
+    assert_eq!(node2.text_chunks(&resolver).collect::<String>(), "2.02.12.2");
+}
+
+#[test]
+fn map_kinds() {
+    let mut cache = NodeCache::new();
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let mapped = green.map_kinds(|kind| RawSyntaxKind(kind.0 + 100), &mut cache);
+    let tree: SyntaxNode = SyntaxNode::new_root(green);
+    let mapped: SyntaxNode = SyntaxNode::new_root(mapped);
+
+    let interner = cache.interner();
+    assert_eq!(mapped.resolve_text(interner), tree.resolve_text(interner));
+    assert_eq!(mapped.descendants_with_tokens().count(), 10);
+    for (old, new) in tree.descendants_with_tokens().zip(mapped.descendants_with_tokens()) {
+        assert_eq!(new.kind(), SyntaxKind(old.kind().0 + 100));
+        assert_eq!(new.text_range(), old.text_range());
+    }
+}
+
+#[test]
+fn ancestor_of_kind() {
+    let (tree, _) = build_tree::<()>(&two_level_tree());
+    let leaf1_0 = tree.children().nth(1).unwrap().first_token().unwrap();
+    let parent = leaf1_0.parent();
+    assert_eq!(parent.ancestor_of_kind(SyntaxKind(0)), Some(&tree));
+    assert_eq!(parent.ancestor_of_kind(SyntaxKind(4)), None);
+    assert_eq!(parent.self_or_ancestor_of_kind(SyntaxKind(4)), Some(parent));
+    assert_eq!(tree.ancestor_of_kind(SyntaxKind(0)), None);
+    assert_eq!(tree.self_or_ancestor_of_kind(SyntaxKind(0)), Some(&tree));
+}
+
+#[cfg(feature = "lasso_compat")]
+#[test]
+fn small_interner_keys() {
+    use cstree::interning::lasso::{MiniSpur, Rodeo};
+
+    let mut interner = Rodeo::<MiniSpur>::new();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let tree: SyntaxNode = SyntaxNode::new_root(green);
+    assert_eq!(tree.resolve_text(&interner), "0.00.11.02.02.12.2");
+
+    let mut full = Rodeo::<MiniSpur>::new();
+    for i in 0..u16::MAX {
+        full.try_get_or_intern(i.to_string()).unwrap();
+    }
+    assert!(Interner::try_get_or_intern(&mut full, "one too many").is_err());
+}
+
+#[test]
+fn children_with_lookahead() {
+    let (tree, _) = build_tree::<()>(&two_level_tree());
+    let lookahead: Vec<_> = tree
+        .children_with_tokens()
+        .with_lookahead(1)
+        .map(|(child, ahead)| (child.kind(), ahead))
+        .collect();
+    assert_eq!(
+        lookahead,
+        [
+            (SyntaxKind(1), vec![Some(SyntaxKind(4))]),
+            (SyntaxKind(4), vec![Some(SyntaxKind(6))]),
+            (SyntaxKind(6), vec![None]),
+        ]
+    );
+    let (child, ahead) = tree.children_with_tokens().with_lookahead(0).next().unwrap();
+    assert_eq!(child.kind(), SyntaxKind(1));
+    assert!(ahead.is_empty());
+}
+
+#[test]
+fn reuse_builder() {
+    let mut cache = NodeCache::new();
+    let mut builder: GreenNodeBuilder<SyntaxKind, _> = GreenNodeBuilder::with_cache(&mut cache);
+    // an unfinished tree is discarded
+    builder.start_node(SyntaxKind(0));
+    builder.token(SyntaxKind(1), "discarded");
+    builder.reset();
+
+    build_recursive(&two_level_tree(), &mut builder, 0);
+    let first = builder.finish_and_reset();
+    build_recursive(&tree_with_eq_tokens(), &mut builder, 0);
+    let second = builder.finish_and_reset();
+    builder.reset();
+    build_recursive(&two_level_tree(), &mut builder, 0);
+    let third = builder.finish_node_tree();
+
+    let interner = cache.interner();
+    let first: SyntaxNode = SyntaxNode::new_root(first);
+    let second: SyntaxNode = SyntaxNode::new_root(second);
+    assert_eq!(first.resolve_text(interner), "0.00.11.02.02.12.2");
+    assert_eq!(second.resolve_text(interner), "abcabc");
+    // the cache is kept between trees
+    assert!(first.green().ptr_eq(&third));
+}
+
+#[test]
+fn descendants_with_data() {
+    let (tree, _) = build_tree::<String>(&two_level_tree());
+    assert_eq!(tree.descendants_with_data().count(), 0);
+    let first = tree.children().next().unwrap();
+    let last = tree.last_child().unwrap();
+    last.set_data("last".into());
+    first.set_data("first".into());
+
+    let annotated: Vec<_> = tree
+        .descendants_with_data()
+        .map(|(node, data)| (node.clone(), data.to_string()))
+        .collect();
+    assert_eq!(
+        annotated,
+        [(first.clone(), "first".to_string()), (last.clone(), "last".to_string())]
+    );
+    last.clear_data();
+    assert_eq!(tree.descendants_with_data().count(), 1);
+}
+
+#[test]
+fn match_pattern() {
+    use cstree::syntax::Pattern::{self, *};
+
+    let (tree, _) = build_tree::<()>(&two_level_tree());
+    let three_nodes = Node(SyntaxKind(0), vec![Any, Any, Any]);
+    assert!(tree.matches(&three_nodes));
+    let exact = Node(
+        SyntaxKind(0),
+        vec![
+            Node(SyntaxKind(1), vec![Kind(SyntaxKind(2)), Kind(SyntaxKind(3))]),
+            Kind(SyntaxKind(4)),
+            Node(SyntaxKind(6), vec![Repeat(Box::new(Any))]),
+        ],
+    );
+    assert!(tree.matches(&exact));
+    let repeated: Pattern<SyntaxKind> = Node(SyntaxKind(0), vec![Repeat(Box::new(Seq(vec![Any, Any]))), Any]);
+    assert!(tree.matches(&repeated));
+
+    assert!(!tree.matches(&Node(SyntaxKind(0), vec![Any, Any])));
+    assert!(!tree.matches(&Node(SyntaxKind(1), vec![Any, Any, Any])));
+    assert!(!tree.matches(&Node(SyntaxKind(0), vec![Repeat(Box::new(Kind(SyntaxKind(1))))])));
+    // empty repetitions terminate
+    assert!(tree.matches(&Node(SyntaxKind(0), vec![Repeat(Box::new(Repeat(Box::new(Any))))])));
+}
+
+#[test]
+fn to_json() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(6));
+    builder.token(SyntaxKind(7), "2.0");
+    builder.finish_node();
+    let (tree, cache) = builder.finish();
+    let resolver = cache.unwrap().into_interner().unwrap();
+    let node: SyntaxNode = SyntaxNode::new_root(tree);
+    assert_eq!(
+        node.to_json(&resolver),
+        r#"{"kind":6,"range":[0,3],"children":[{"kind":7,"range":[0,3],"text":"2.0"}]}"#
+    );
+
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    builder.start_node(SyntaxKind(1));
+    builder.finish_node();
+    builder.token(SyntaxKind(2), "\"\\\n\u{1}");
+    builder.finish_node();
+    let (tree, cache) = builder.finish();
+    let resolver = cache.unwrap().into_interner().unwrap();
+    let node: SyntaxNode = SyntaxNode::new_root(tree);
+    assert_eq!(
+        node.to_json(&resolver),
+        r#"{"kind":0,"range":[0,4],"children":[{"kind":1,"range":[0,0],"children":[]},{"kind":2,"range":[0,4],"text":"\"\\\n\u0001"}]}"#
+    );
+    let _: serde_json::Value = serde_json::from_str(&node.to_json(&resolver)).unwrap();
+}
+
+#[test]
+fn resolver_is_shared() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let tree = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let tree: ResolvedNode = SyntaxNode::new_root_with_resolver(tree, interner);
+    let resolver = std::sync::Arc::clone(tree.resolver());
+    let strong_count = std::sync::Arc::strong_count(&resolver);
+
+    let leaf = tree.last_child().unwrap().first_token().unwrap();
+    let clones: Vec<_> = (0..100).map(|_| leaf.clone()).collect();
+    for clone in &clones {
+        assert!(std::sync::Arc::ptr_eq(clone.resolver(), &resolver));
+        assert_eq!(clone.text(), "2.0");
+    }
+    let node_clones: Vec<_> = (0..100).map(|_| leaf.parent().clone()).collect();
+    assert!(std::sync::Arc::ptr_eq(node_clones[99].resolver(), &resolver));
+    // the resolver is neither duplicated nor referenced separately by every clone
+    assert_eq!(std::sync::Arc::strong_count(&resolver), strong_count);
+}
+
+#[test]
+fn text_reader() {
+    use std::io::Read;
+
+    let (tree, resolver) = build_tree::<()>(&two_level_tree());
+    let mut reader = tree.text_reader(&resolver);
+    let mut text = Vec::new();
+    let mut buf = [0; 4];
+    loop {
+        let len = reader.read(&mut buf).unwrap();
+        if len == 0 {
+            break;
+        }
+        assert!(len <= 4);
+        text.extend_from_slice(&buf[..len]);
+    }
+    assert_eq!(text, tree.resolve_text(&resolver).to_string().as_bytes());
+    assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+    let node2 = tree.last_child().unwrap();
+    let mut text = String::new();
+    node2.text_reader(&resolver).read_to_string(&mut text).unwrap();
+    assert_eq!(text, "2.02.12.2");
+}
+
+#[test]
+fn green_child_at_path() {
+    let mut cache = NodeCache::new();
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let token = green.child_at_path(&[2, 1]).unwrap().into_token().unwrap();
+    assert_eq!(token.kind(), RawSyntaxKind(8));
+    assert_eq!(cache.interner().resolve(token.text_key().unwrap()), "2.1");
+    let node = green.child_at_path(&[1]).unwrap().into_node().unwrap();
+    assert_eq!(node.kind(), RawSyntaxKind(4));
+    assert!(green.child_at_path(&[]).unwrap().into_node().unwrap().ptr_eq(&green));
+
+    assert!(green.child_at_path(&[3]).is_none());
+    assert!(green.child_at_path(&[1, 1]).is_none());
+    assert!(green.child_at_path(&[2, 1, 0]).is_none());
+}
+
+#[test]
+fn build_stats() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    build_recursive(&two_level_tree(), &mut builder, 0);
+    assert_eq!(builder.stats().token_count, 6);
+    let (tree, _, stats) = builder.finish_with_stats();
+    assert_eq!(
+        stats,
+        BuildStats {
+            node_count:      4,
+            token_count:     6,
+            interned_tokens: 6,
+        }
+    );
+    let tree: SyntaxNode = SyntaxNode::new_root(tree);
+    assert_eq!(tree.descendants().count(), stats.node_count);
+    assert_eq!(tree.tokens().count(), stats.token_count);
+
+    // the elements of a continued tree are counted as well
+    let mut cache = NodeCache::new();
+    let mut builder = GreenNodeBuilder::from_green(tree.green().clone(), &mut cache);
+    builder.start_node(SyntaxKind(0));
+    builder.token(SyntaxKind(1), "x");
+    builder.finish_node();
+    builder.finish_node();
+    let (continued, _, stats) = builder.finish_with_stats();
+    let continued: SyntaxNode = SyntaxNode::new_root(continued);
+    assert_eq!(stats.node_count, 5);
+    assert_eq!(continued.descendants().count(), stats.node_count);
+    assert_eq!(continued.tokens().count(), stats.token_count);
+    assert_eq!(stats.interned_tokens, 7);
+
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    build_recursive(&two_level_tree(), &mut builder, 0);
+    builder.finish_and_reset();
+    assert_eq!(builder.stats(), BuildStats::default());
+}
+
+#[test]
+fn element_parent() {
+    let (tree, _) = build_tree::<()>(&two_level_tree());
+    let node = tree.children_with_tokens().next().unwrap();
+    assert_eq!(node.parent(), Some(&tree));
+    let node = node.into_node().unwrap();
+    let token = node.children_with_tokens().next().unwrap();
+    assert!(token.as_token().is_some());
+    assert_eq!(token.parent(), Some(node));
+    let root: SyntaxElementRef<'_> = (&tree).into();
+    assert_eq!(root.parent(), None);
+}
+
+#[test]
+fn deep_copy() {
+    let mut cache = NodeCache::new();
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let interner = cache.into_interner().unwrap();
+    let tree: SyntaxNode = SyntaxNode::new_root(green);
+    let node2 = tree.children().nth(2).unwrap();
+
+    let copy = node2.deep_copy(&mut NodeCache::new());
+    assert!(!copy.ptr_eq(node2.green()));
+    assert_eq!(&copy, node2.green());
+    let copy: SyntaxNode = SyntaxNode::new_root(copy);
+    assert_eq!(copy.resolve_text(&interner), node2.resolve_text(&interner));
+    assert_eq!(copy.resolve_text(&interner), "2.02.12.2");
+}
+
+#[test]
+fn intern_through_cache() {
+    let mut cache = NodeCache::new();
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let extra = cache.interner_mut().get_or_intern("diagnostic");
+    let existing = cache.interner_mut().get_or_intern("1.0");
+    let interner = cache.into_interner().unwrap();
+    assert_eq!(interner.resolve(extra), "diagnostic");
+
+    let tree: SyntaxNode = SyntaxNode::new_root(green);
+    let token = tree.children().nth(1).unwrap().first_token().unwrap();
+    assert_eq!(token.text_key(), Some(existing));
+    assert_eq!(token.resolve_text(&interner), "1.0");
+}
+
+#[test]
+fn empty_nodes() {
+    let (tree, _) = build_tree::<()>(&two_level_tree());
+    assert!(!tree.is_empty());
+    assert!(tree.has_children());
+    assert_eq!(tree.child_count(), 3);
+    let leaf_parent = tree.children().nth(2).unwrap();
+    assert_eq!(leaf_parent.child_count(), 3);
+    assert_eq!(leaf_parent.arity(), 0);
+    assert!(!leaf_parent.is_empty());
+    // tokens count as children
+    assert!(leaf_parent.has_children());
+
+    let (empty, _) = build_tree::<()>(&Element::Node(Vec::new()));
+    assert!(empty.is_empty());
+    assert!(!empty.has_children());
+    assert_eq!(empty.child_count(), 0);
+}
+
+#[test]
+fn kind_range() {
+    let (tree, _) = build_tree::<()>(&two_level_tree());
+    let range = KindRange::from(SyntaxKind(3)..SyntaxKind(8));
+    assert!(range.contains(SyntaxKind(3)));
+    assert!(!range.contains(SyntaxKind(8)));
+    assert!(!range.is_empty());
+    assert!(KindRange::new(SyntaxKind(8), SyntaxKind(3)).is_empty());
+
+    let in_range: Vec<_> = tree
+        .children_with_tokens()
+        .in_range(range)
+        .map(|child| child.kind())
+        .collect();
+    assert_eq!(in_range, [SyntaxKind(4), SyntaxKind(6)]);
+    let tokens: Vec<_> = tree
+        .tokens()
+        .map(|token| token.kind())
+        .filter(|&kind| range.contains(kind))
+        .collect();
+    assert_eq!(tokens, [SyntaxKind(3), SyntaxKind(5), SyntaxKind(7)]);
+}
+
+#[test]
+fn tokens_with_offsets() {
+    let (tree, _) = build_tree::<()>(&two_level_tree());
+    let offsets: Vec<_> = tree
+        .tokens_with_offsets()
+        .map(|(_, offset)| u32::from(offset))
+        .collect();
+    assert_eq!(offsets, [0, 3, 6, 9, 12, 15]);
+    for (token, offset) in tree.tokens_with_offsets() {
+        assert_eq!(offset, token.text_range().start());
+    }
+    let node = tree.children().nth(2).unwrap();
+    let offsets: Vec<_> = node
+        .tokens_with_offsets()
+        .map(|(_, offset)| u32::from(offset))
+        .collect();
+    assert_eq!(offsets, [9, 12, 15]);
+}
+
+#[test]
+fn builder_from_green() {
+    let mut cache = NodeCache::new();
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::from_green(green.clone(), &mut cache);
+    assert_eq!(builder.current_depth(), 1);
+    builder.finish_node();
+    let (unchanged, _) = builder.finish();
+    assert_eq!(unchanged, green);
+
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::from_green(green, &mut cache);
+    builder.token(SyntaxKind(10), "3.0");
+    builder.finish_node();
+    let (appended, _) = builder.finish();
+    let tree: SyntaxNode = SyntaxNode::new_root(appended);
+    assert_eq!(tree.arity_with_tokens(), 4);
+    let token = tree.last_child_or_token().unwrap().into_token().unwrap();
+    assert_eq!(token.kind(), SyntaxKind(10));
+    assert_eq!(token.text_range(), TextRange::new(18.into(), 21.into()));
+    assert_eq!(tree.resolve_text(cache.interner()), "0.00.11.02.02.12.23.0");
+}
+
+#[test]
+fn char_len() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    builder.token(SyntaxKind(1), "λx");
+    builder.token(SyntaxKind(2), ".");
+    builder.token(SyntaxKind(1), "😀");
+    builder.finish_node();
+    let (green, cache) = builder.finish();
+    let resolver = cache.unwrap().into_interner().unwrap();
+    let tree: SyntaxNode = SyntaxNode::new_root(green.clone());
+
+    let lambda = tree.first_token().unwrap();
+    assert_eq!(lambda.char_len(&resolver), 2);
+    assert_eq!(lambda.text_range().len(), 3.into());
+    assert_eq!(tree.char_len(&resolver), 4);
+    assert!(tree.char_len(&resolver) < usize::from(tree.text_range().len()));
+
+    let tree = SyntaxNode::<SyntaxKind>::new_root_with_resolver(green, resolver);
+    let chars: Vec<_> = tree.tokens().map(|token| token.char_len()).collect();
+    assert_eq!(chars, [2, 1, 1]);
+    assert_eq!(tree.char_len(), 4);
+}
+
+#[test]
+fn tokens_rev() {
+    let (tree, resolver) = build_tree::<()>(&two_level_tree());
+    let texts: Vec<_> = tree.tokens_rev().map(|token| token.resolve_text(&resolver)).collect();
+    assert_eq!(texts, ["2.2", "2.1", "2.0", "1.0", "0.1", "0.0"]);
+    let node = tree.children().nth(1).unwrap();
+    let texts: Vec<_> = node.tokens_rev().map(|token| token.resolve_text(&resolver)).collect();
+    assert_eq!(texts, ["1.0"]);
+
+    use Element::*;
+    let (tree, resolver) = build_tree::<()>(&Node(vec![
+        Token("a"),
+        Node(vec![]),
+        Node(vec![Node(vec![Token("b")]), Node(vec![])]),
+        Node(vec![]),
+    ]));
+    let texts: Vec<_> = tree.tokens_rev().map(|token| token.resolve_text(&resolver)).collect();
+    assert_eq!(texts, ["b", "a"]);
+    let (empty, _) = build_tree::<()>(&Node(vec![]));
+    assert_eq!(empty.tokens_rev().count(), 0);
+}
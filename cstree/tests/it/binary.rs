@@ -0,0 +1,359 @@
+use super::*;
+use cstree::{
+    build::NodeCache,
+    green::{DecodeError, GreenNode, MmapElement, MmapNode, MmapTree, ValidationError, DEFAULT_MAX_DEPTH},
+    interning::{new_interner, Interner},
+    util::NodeOrToken,
+};
+
+fn two_level_tree() -> Element<'static> {
+    use Element::*;
+    Node(vec![
+        Node(vec![Token("0.0"), Token("0.1")]),
+        Node(vec![Token("1.0")]),
+        Node(vec![Token("2.0"), Token("2.1"), Token("2.2")]),
+    ])
+}
+
+#[test]
+fn streaming_matches_in_memory() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+
+    let mut written = Vec::new();
+    green.serialize_to(&mut written, &interner).unwrap();
+    assert_eq!(written, green.to_bytes(&interner));
+}
+
+#[test]
+fn roundtrip() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let bytes = green.to_bytes(&interner);
+
+    // decoding with the original interner yields a structurally equal tree
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let decoded = GreenNode::deserialize_from::<SyntaxKind, _, _>(&mut bytes.as_slice(), &mut cache).unwrap();
+    assert_eq!(decoded, green);
+
+    let mut cache = NodeCache::new();
+    let decoded = GreenNode::from_bytes::<SyntaxKind, _>(&bytes, &mut cache).unwrap();
+    let tree: SyntaxNode = SyntaxNode::new_root(decoded);
+    let original: SyntaxNode = SyntaxNode::new_root(green);
+    assert_eq!(tree.resolve_text(cache.interner()), "0.00.11.02.02.12.2");
+    assert_eq!(
+        tree.display_structure().to_string(),
+        original.display_structure().to_string()
+    );
+}
+
+#[test]
+fn invalid_input() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let mut bytes = green.to_bytes(&interner);
+
+    let mut cache = NodeCache::new();
+    let truncated = &bytes[..bytes.len() - 1];
+    assert!(matches!(
+        GreenNode::from_bytes::<SyntaxKind, _>(truncated, &mut cache),
+        Err(DecodeError::Io(_))
+    ));
+    assert!(matches!(
+        GreenNode::from_bytes::<SyntaxKind, _>(b"CSTR\x07", &mut cache),
+        Err(DecodeError::UnsupportedVersion(7))
+    ));
+    assert!(matches!(
+        GreenNode::from_bytes::<SyntaxKind, _>(b"TREE\x01", &mut cache),
+        Err(DecodeError::InvalidMagic)
+    ));
+
+    // streaming reads stop at the end of the tree, but decoding a byte slice expects nothing more
+    bytes.push(0);
+    let mut reader = bytes.as_slice();
+    assert!(GreenNode::deserialize_from::<SyntaxKind, _, _>(&mut reader, &mut cache).is_ok());
+    assert_eq!(reader, [0]);
+    assert!(matches!(
+        GreenNode::from_bytes::<SyntaxKind, _>(&bytes, &mut cache),
+        Err(DecodeError::TrailingData)
+    ));
+}
+
+#[test]
+fn too_deep() {
+    // a chain of nested nodes, each with a single child, ending in a token
+    fn nested(depth: usize) -> Vec<u8> {
+        let mut bytes = b"CSTR\x02".to_vec();
+        for _ in 0..depth {
+            bytes.push(0);
+            bytes.extend_from_slice(&0_u32.to_le_bytes());
+            bytes.extend_from_slice(&1_u32.to_le_bytes());
+            bytes.push(0);
+        }
+        bytes.push(1);
+        bytes.extend_from_slice(&0_u32.to_le_bytes());
+        bytes.extend_from_slice(&1_u32.to_le_bytes());
+        bytes.push(1);
+        bytes.push(b'a');
+        bytes
+    }
+
+    let mut cache = NodeCache::new();
+    let ok = GreenNode::from_bytes::<SyntaxKind, _>(&nested(DEFAULT_MAX_DEPTH), &mut cache).unwrap();
+    assert_eq!(ok.text_len(), 1.into());
+    assert!(matches!(
+        GreenNode::from_bytes::<SyntaxKind, _>(&nested(DEFAULT_MAX_DEPTH + 1), &mut cache),
+        Err(DecodeError::TooDeep)
+    ));
+    let bytes = nested(3000);
+    assert!(matches!(
+        GreenNode::from_bytes::<SyntaxKind, _>(&bytes, &mut cache),
+        Err(DecodeError::TooDeep)
+    ));
+    assert!(GreenNode::deserialize_with_max_depth::<SyntaxKind, _, _>(&mut bytes.as_slice(), &mut cache, 3000).is_ok());
+    assert!(matches!(
+        GreenNode::deserialize_with_max_depth::<SyntaxKind, _, _>(&mut nested(2).as_slice(), &mut cache, 1),
+        Err(DecodeError::TooDeep)
+    ));
+}
+
+fn view_structure(node: MmapNode<'_>, out: &mut Vec<String>) {
+    out.push(format!("{:?}@{:?}", node.kind(), node.text_range()));
+    for child in node.children() {
+        match child {
+            MmapElement::Node(node) => view_structure(node, out),
+            MmapElement::Token(token) => out.push(format!(
+                "{:?}@{:?} {:?}",
+                token.kind(),
+                token.text_range(),
+                token.text().unwrap()
+            )),
+        }
+    }
+}
+
+#[test]
+fn zero_copy_view() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let bytes = green.to_bytes(&interner);
+
+    let view = MmapTree::new(&bytes).unwrap();
+    let mut viewed = Vec::new();
+    view_structure(view.root(), &mut viewed);
+
+    let tree: SyntaxNode = SyntaxNode::new_root(green);
+    let expected: Vec<_> = tree
+        .descendants_with_tokens()
+        .map(|element| match element {
+            NodeOrToken::Node(node) => format!("{:?}@{:?}", node.syntax_kind(), node.text_range()),
+            NodeOrToken::Token(token) => format!(
+                "{:?}@{:?} {:?}",
+                token.syntax_kind(),
+                token.text_range(),
+                token.resolve_text(&interner)
+            ),
+        })
+        .collect();
+    assert_eq!(viewed, expected);
+    assert_eq!(view.root().children().len(), 3);
+
+    // the view validates its input like decoding does
+    assert!(matches!(
+        MmapTree::new(&bytes[..bytes.len() - 1]),
+        Err(DecodeError::Io(_))
+    ));
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(matches!(MmapTree::new(&trailing), Err(DecodeError::TrailingData)));
+}
+
+#[test]
+fn zero_copy_view_text_too_long() {
+    // the view does not know which kinds have static text, so text-less tokens may claim any length
+    let mut bytes = b"CSTR\x02".to_vec();
+    bytes.push(0);
+    bytes.extend_from_slice(&0_u32.to_le_bytes());
+    bytes.extend_from_slice(&2_u32.to_le_bytes());
+    bytes.push(0);
+    for _ in 0..2 {
+        bytes.push(1);
+        bytes.extend_from_slice(&1_u32.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.push(0);
+    }
+    assert!(matches!(MmapTree::new(&bytes), Err(DecodeError::TextTooLong)));
+    // without the second token, the text still fits
+    bytes[10..14].copy_from_slice(&1_u32.to_le_bytes());
+    bytes.truncate(bytes.len() - 10);
+    assert!(MmapTree::new(&bytes).is_ok());
+}
+
+#[test]
+fn validate_decoded_tree() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let bytes = green.to_bytes(&interner);
+
+    let mut cache = NodeCache::new();
+    let decoded = GreenNode::from_bytes::<SyntaxKind, _>(&bytes, &mut cache).unwrap();
+    assert_eq!(decoded.validate::<SyntaxKind, _>(cache.interner()), Ok(()));
+
+    // resolving the tokens with an unrelated interner yields text of the wrong length or no text at all
+    let mut unrelated = new_interner();
+    unrelated.get_or_intern("x");
+    assert_eq!(
+        decoded.validate::<SyntaxKind, _>(&unrelated),
+        Err(ValidationError::TokenLength {
+            path:   vec![0, 0],
+            stored: 3.into(),
+            text:   1.into(),
+        })
+    );
+    let mut unrelated = new_interner();
+    for text in ["0.0", "0.1", "1.0", "2.0", "x"] {
+        unrelated.get_or_intern(text);
+    }
+    assert_eq!(
+        decoded.validate::<SyntaxKind, _>(&unrelated).unwrap_err().path(),
+        [2, 1]
+    );
+    let error = decoded.validate::<SyntaxKind, _>(&new_interner()).unwrap_err();
+    assert_eq!(error, ValidationError::UnresolvedText { path: vec![0, 0] });
+    assert_eq!(error.path(), [0, 0]);
+}
+
+#[test]
+fn malicious_tokens() {
+    fn token(len: u32, has_text: u8, text: &[u8]) -> Vec<u8> {
+        let mut bytes = b"CSTR\x02".to_vec();
+        bytes.push(0);
+        bytes.extend_from_slice(&0_u32.to_le_bytes());
+        bytes.extend_from_slice(&1_u32.to_le_bytes());
+        bytes.push(0);
+        bytes.push(1);
+        bytes.extend_from_slice(&7_u32.to_le_bytes());
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.push(has_text);
+        bytes.extend_from_slice(text);
+        bytes
+    }
+
+    let mut cache = NodeCache::new();
+    // a huge length must not be trusted to allocate a buffer
+    assert!(matches!(
+        GreenNode::from_bytes::<SyntaxKind, _>(&token(u32::MAX, 1, b"abc"), &mut cache),
+        Err(DecodeError::Io(_))
+    ));
+    // `SyntaxKind` has no static text, so every token needs text of its own
+    assert!(matches!(
+        GreenNode::from_bytes::<SyntaxKind, _>(&token(1, 0, b""), &mut cache),
+        Err(DecodeError::MissingText(RawSyntaxKind(7)))
+    ));
+    assert!(GreenNode::from_bytes::<SyntaxKind, _>(&token(3, 1, b"abc"), &mut cache).is_ok());
+}
+
+#[test]
+fn malformed_static_tokens() {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct StaticKind(u32);
+
+    impl Syntax for StaticKind {
+        fn from_raw(raw: RawSyntaxKind) -> Self {
+            assert!(raw.0 <= 1, "invalid kind {}", raw.0);
+            Self(raw.0)
+        }
+
+        fn try_from_raw(raw: RawSyntaxKind) -> Option<Self> {
+            (raw.0 <= 1).then_some(Self(raw.0))
+        }
+
+        fn into_raw(self) -> RawSyntaxKind {
+            RawSyntaxKind(self.0)
+        }
+
+        fn static_text(self) -> Option<&'static str> {
+            (self.0 == 1).then_some("+")
+        }
+    }
+
+    fn static_tokens(tokens: &[(u32, u32)]) -> Vec<u8> {
+        let mut bytes = b"CSTR\x02".to_vec();
+        bytes.push(0);
+        bytes.extend_from_slice(&0_u32.to_le_bytes());
+        bytes.extend_from_slice(&(tokens.len() as u32).to_le_bytes());
+        bytes.push(0);
+        for &(kind, len) in tokens {
+            bytes.push(1);
+            bytes.extend_from_slice(&kind.to_le_bytes());
+            bytes.extend_from_slice(&len.to_le_bytes());
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    let mut cache = NodeCache::new();
+    // unknown kinds are rejected before they reach `Syntax::from_raw`
+    assert!(matches!(
+        GreenNode::from_bytes::<StaticKind, _>(&static_tokens(&[(9999, 1)]), &mut cache),
+        Err(DecodeError::InvalidKind(RawSyntaxKind(9999)))
+    ));
+    // the length of a static token must match its static text
+    assert!(matches!(
+        GreenNode::from_bytes::<StaticKind, _>(&static_tokens(&[(1, 1000)]), &mut cache),
+        Err(DecodeError::StaticTextLenMismatch(RawSyntaxKind(1)))
+    ));
+    assert!(matches!(
+        GreenNode::from_bytes::<StaticKind, _>(&static_tokens(&[(1, u32::MAX), (1, u32::MAX)]), &mut cache),
+        Err(DecodeError::StaticTextLenMismatch(RawSyntaxKind(1)))
+    ));
+    let decoded = GreenNode::from_bytes::<StaticKind, _>(&static_tokens(&[(1, 1), (1, 1)]), &mut cache).unwrap();
+    assert_eq!(u32::from(decoded.text_len()), 2);
+}
+
+#[test]
+fn text_too_long() {
+    static LONG_TEXT: [u8; 1 << 16] = [b'a'; 1 << 16];
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct LongKind(u32);
+
+    impl Syntax for LongKind {
+        fn from_raw(raw: RawSyntaxKind) -> Self {
+            Self(raw.0)
+        }
+
+        fn into_raw(self) -> RawSyntaxKind {
+            RawSyntaxKind(self.0)
+        }
+
+        fn static_text(self) -> Option<&'static str> {
+            (self.0 == 1).then(|| std::str::from_utf8(&LONG_TEXT).unwrap())
+        }
+    }
+
+    // each token is valid, but their text together is longer than `u32::MAX` bytes
+    let n_tokens = (1_u32 << 16) + 1;
+    let mut bytes = b"CSTR\x02".to_vec();
+    bytes.push(0);
+    bytes.extend_from_slice(&0_u32.to_le_bytes());
+    bytes.extend_from_slice(&n_tokens.to_le_bytes());
+    bytes.push(0);
+    for _ in 0..n_tokens {
+        bytes.push(1);
+        bytes.extend_from_slice(&1_u32.to_le_bytes());
+        bytes.extend_from_slice(&(LONG_TEXT.len() as u32).to_le_bytes());
+        bytes.push(0);
+    }
+
+    let mut cache = NodeCache::new();
+    assert!(matches!(
+        GreenNode::from_bytes::<LongKind, _>(&bytes, &mut cache),
+        Err(DecodeError::TextTooLong)
+    ));
+}
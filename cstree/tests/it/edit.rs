@@ -0,0 +1,304 @@
+use super::*;
+use cstree::{
+    build::NodeCache,
+    interning::new_interner,
+    syntax::EditError,
+    text::{TextRange, TextSize},
+};
+
+fn range(start: u32, end: u32) -> TextRange {
+    TextRange::new(TextSize::from(start), TextSize::from(end))
+}
+
+fn two_level_tree() -> Element<'static> {
+    use Element::*;
+    Node(vec![
+        Node(vec![Token("0.0"), Token("0.1")]),
+        Node(vec![Token("1.0")]),
+        Node(vec![Token("2.0"), Token("2.1"), Token("2.2")]),
+    ])
+}
+
+#[test]
+fn apply_disjoint_edits() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let tree: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&two_level_tree(), &mut cache));
+
+    // edits are given out of order and one of them spans a token boundary
+    let edits = [(range(10, 13), "xyz"), (range(0, 1), "abc"), (range(18, 18), "!")];
+    let edited: SyntaxNode = SyntaxNode::new_root(tree.apply_edits(&edits, &mut cache).unwrap());
+    assert_eq!(edited.resolve_text(cache.interner()), "abc.00.11.02xyz.12.2!");
+
+    // untouched subtrees are shared with the original tree
+    assert_eq!(
+        edited.children().nth(1).unwrap().green(),
+        tree.children().nth(1).unwrap().green()
+    );
+    let node2 = edited.children().nth(2).unwrap();
+    let texts: Vec<_> = node2
+        .children_with_tokens()
+        .map(|token| token.as_token().unwrap().resolve_text(cache.interner()))
+        .collect();
+    assert_eq!(texts, ["2xyz", ".1", "2.2!"]);
+}
+
+#[test]
+fn apply_edits_to_subtree() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let tree: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&two_level_tree(), &mut cache));
+
+    let node1 = tree.children().nth(1).unwrap();
+    let edited: SyntaxNode = SyntaxNode::new_root(node1.apply_edits(&[(range(7, 8), "")], &mut cache).unwrap());
+    assert_eq!(edited.resolve_text(cache.interner()), "0.00.1102.02.12.2");
+    assert_eq!(
+        node1.apply_edits(&[(range(3, 7), "")], &mut cache),
+        Err(EditError::OutOfBounds(range(3, 7)))
+    );
+}
+
+#[test]
+fn replace_element_at_offset() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let tree: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&two_level_tree(), &mut cache));
+
+    let token = cache.token(SyntaxKind(5), "one");
+    let replacement = cache.node(SyntaxKind(4), vec![token.into()]);
+    let edited = tree
+        .replace_element_at(6.into(), replacement.into(), &mut cache)
+        .unwrap();
+    let edited: SyntaxNode = SyntaxNode::new_root(edited);
+    assert_eq!(edited.resolve_text(cache.interner()), "0.00.1one2.02.12.2");
+    assert_eq!(edited.children().nth(2).unwrap().text_range(), range(9, 18));
+
+    // replacing children of a subtree
+    let node2 = tree.children().nth(2).unwrap();
+    let token = cache.token(SyntaxKind(8), "!");
+    let edited = node2.replace_element_at(12.into(), token.into(), &mut cache).unwrap();
+    let edited: SyntaxNode = SyntaxNode::new_root(edited);
+    assert_eq!(edited.resolve_text(cache.interner()), "0.00.11.02.0!2.2");
+
+    // no child starts inside of another child
+    let token = cache.token(SyntaxKind(1), "x");
+    assert!(tree
+        .replace_element_at(7.into(), token.clone().into(), &mut cache)
+        .is_none());
+    assert!(tree.replace_element_at(18.into(), token.into(), &mut cache).is_none());
+}
+
+#[test]
+fn change_kind() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let tree: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&two_level_tree(), &mut cache));
+
+    let node2 = tree.children().nth(2).unwrap();
+    let edited: SyntaxNode = SyntaxNode::new_root(node2.with_kind(SyntaxKind(42), &mut cache));
+    assert_eq!(
+        edited.resolve_text(cache.interner()),
+        tree.resolve_text(cache.interner())
+    );
+    let changed = edited.children().nth(2).unwrap();
+    assert_eq!(changed.kind(), SyntaxKind(42));
+    assert_eq!(changed.text_range(), node2.text_range());
+    // the children are shared with the original node
+    assert!(changed
+        .green()
+        .children()
+        .zip(node2.green().children())
+        .all(|(new, old)| new.into_token().unwrap().ptr_eq(old.into_token().unwrap())));
+    assert_eq!(
+        edited.first_child().unwrap().green(),
+        tree.first_child().unwrap().green()
+    );
+
+    let root = tree.with_kind(SyntaxKind(43), &mut cache);
+    assert_eq!(root.kind(), SyntaxKind(43).into_raw());
+}
+
+#[test]
+fn change_token_kind() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let tree: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&two_level_tree(), &mut cache));
+
+    let leaf1_0 = tree.children().nth(1).unwrap().first_token().unwrap();
+    let edited: SyntaxNode = SyntaxNode::new_root(leaf1_0.with_kind(SyntaxKind(42), &mut cache));
+    assert_eq!(
+        edited.resolve_text(cache.interner()),
+        tree.resolve_text(cache.interner())
+    );
+    let changed = edited.children().nth(1).unwrap().first_token().unwrap();
+    assert_eq!(changed.kind(), SyntaxKind(42));
+    assert_ne!(changed.kind(), leaf1_0.kind());
+    assert_eq!(changed.text_range(), leaf1_0.text_range());
+    assert_eq!(changed.text_key(), leaf1_0.text_key());
+    assert_eq!(
+        edited.first_child().unwrap().green(),
+        tree.first_child().unwrap().green()
+    );
+}
+
+#[test]
+fn insertion_at_start_of_replacement() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let tree: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&two_level_tree(), &mut cache));
+
+    // the result does not depend on the order of the edits
+    let edits = [(range(6, 9), "a"), (range(6, 6), "b")];
+    let edited: SyntaxNode = SyntaxNode::new_root(tree.apply_edits(&edits, &mut cache).unwrap());
+    assert_eq!(edited.resolve_text(cache.interner()), "0.00.1ba2.02.12.2");
+    let edits = [(range(6, 6), "b"), (range(6, 9), "a")];
+    let reversed: SyntaxNode = SyntaxNode::new_root(tree.apply_edits(&edits, &mut cache).unwrap());
+    assert_eq!(reversed.green(), edited.green());
+}
+
+#[test]
+fn overlapping_edits() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let tree: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&two_level_tree(), &mut cache));
+
+    let edits = [(range(4, 8), "a"), (range(2, 5), "b")];
+    assert_eq!(
+        tree.apply_edits(&edits, &mut cache),
+        Err(EditError::Overlap {
+            first:  range(2, 5),
+            second: range(4, 8),
+        })
+    );
+}
+
+#[test]
+fn transfer_data_to_edited_tree() {
+    let mut interner = new_interner();
+    let mut cache = NodeCache::with_interner(&mut interner);
+    let tree: SyntaxNode<String> = SyntaxNode::new_root(build_tree_with_cache(&two_level_tree(), &mut cache));
+    tree.set_data("root".to_string());
+    tree.children().nth(1).unwrap().set_data("unedited".to_string());
+    tree.children().nth(2).unwrap().set_data("edited".to_string());
+
+    let edits = [(range(12, 15), "x")];
+    let edited: SyntaxNode<String> = SyntaxNode::new_root(tree.apply_edits(&edits, &mut cache).unwrap());
+    // by default, data is not carried over
+    assert!(edited.children().nth(1).unwrap().get_data().is_none());
+
+    tree.transfer_data(&edited, |old, data| match old.kind() {
+        SyntaxKind(0) => Some(data),
+        _ => None,
+    });
+    assert_eq!(*edited.get_data().unwrap(), "root");
+    assert_eq!(*edited.children().nth(1).unwrap().get_data().unwrap(), "unedited");
+    assert!(edited.children().nth(2).unwrap().get_data().is_none());
+}
+
+#[test]
+fn text_edits_between_trees() {
+    use Element::*;
+    let mut cache = NodeCache::new();
+    let old: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&two_level_tree(), &mut cache));
+    let changed = Node(vec![
+        Node(vec![Token("0.0"), Token("0.1")]),
+        Node(vec![Token("1.0")]),
+        Node(vec![Token("2.0"), Token("xyz"), Token("2.2")]),
+    ]);
+    let changed: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&changed, &mut cache));
+    let shorter = Node(vec![Node(vec![Token("0.0"), Token("0.1")]), Node(vec![Token("1.0")])]);
+    let shorter: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&shorter, &mut cache));
+
+    // a single changed token results in a single edit of that token
+    let edits = old.text_edits(&changed, cache.interner());
+    assert_eq!(edits, [(range(12, 15), "xyz".to_string())]);
+    assert!(old.text_edits(&old, cache.interner()).is_empty());
+
+    // changing the number of children replaces the changed children as a whole
+    let edits = old.text_edits(&shorter, cache.interner());
+    assert_eq!(edits, [(range(9, 18), String::new())]);
+
+    // the edits turn the text of the old tree into the text of the new one
+    for new in [&changed, &shorter] {
+        let edits = old.text_edits(new, cache.interner());
+        let edits: Vec<_> = edits.iter().map(|(range, text)| (*range, text.as_str())).collect();
+        let edited: SyntaxNode = SyntaxNode::new_root(old.apply_edits(&edits, &mut cache).unwrap());
+        assert_eq!(
+            edited.resolve_text(cache.interner()),
+            new.resolve_text(cache.interner())
+        );
+    }
+}
+
+#[test]
+fn replace_range_with_tree() {
+    let mut cache = NodeCache::new();
+    let green = build_tree_with_cache(&two_level_tree(), &mut cache);
+    let tree: SyntaxNode = SyntaxNode::new_root(green);
+
+    let replacement = {
+        let token = cache.token(SyntaxKind(11), "new");
+        let inner = cache.node(SyntaxKind(10), vec![token.into()]);
+        let token = cache.token(SyntaxKind(12), "er");
+        cache.node(SyntaxKind(10), vec![inner.into(), token.into()])
+    };
+    let edited = tree
+        .replace_range(range(6, 9), replacement.clone(), &mut cache)
+        .unwrap();
+    let edited: SyntaxNode = SyntaxNode::new_root(edited);
+    assert_eq!(edited.resolve_text(cache.interner()), "0.00.1newer2.02.12.2");
+    let kinds: Vec<_> = edited.children().map(|child| child.kind()).collect();
+    assert_eq!(kinds, [SyntaxKind(1), SyntaxKind(10), SyntaxKind(6)]);
+    assert_eq!(edited.children().nth(1).unwrap().text_range(), range(6, 11));
+    // later elements are shifted by the length difference
+    assert_eq!(edited.last_child().unwrap().text_range(), range(11, 20));
+
+    // ranges within a single child replace the children of that child
+    let edited = tree
+        .replace_range(range(12, 18), replacement.clone(), &mut cache)
+        .unwrap();
+    let edited: SyntaxNode = SyntaxNode::new_root(edited);
+    assert_eq!(edited.resolve_text(cache.interner()), "0.00.11.02.0newer");
+    assert_eq!(edited.last_child().unwrap().children_with_tokens().count(), 2);
+    // empty ranges insert
+    let edited = tree
+        .replace_range(range(9, 9), replacement.clone(), &mut cache)
+        .unwrap();
+    let edited: SyntaxNode = SyntaxNode::new_root(edited);
+    assert_eq!(edited.resolve_text(cache.interner()), "0.00.11.0newer2.02.12.2");
+    assert_eq!(edited.children().count(), 4);
+
+    assert_eq!(
+        tree.replace_range(range(7, 9), replacement.clone(), &mut cache),
+        Err(EditError::Misaligned(range(7, 9)))
+    );
+    assert_eq!(
+        tree.replace_range(range(3, 9), replacement.clone(), &mut cache),
+        Err(EditError::Misaligned(range(3, 9)))
+    );
+    let node1 = tree.children().nth(1).unwrap();
+    assert_eq!(
+        node1.replace_range(range(3, 9), replacement, &mut cache),
+        Err(EditError::OutOfBounds(range(3, 9)))
+    );
+}
+
+#[test]
+fn invalid_token_edits() {
+    use Element::*;
+    let mut cache = NodeCache::new();
+    let tree: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&Node(vec![Token("aλb")]), &mut cache));
+    // `λ` occupies bytes 1..3
+    assert_eq!(
+        tree.apply_edits(&[(range(2, 3), "x")], &mut cache),
+        Err(EditError::NotCharBoundary(range(2, 3)))
+    );
+    assert!(tree.apply_edits(&[(range(1, 3), "x")], &mut cache).is_ok());
+
+    // the new cache does not know the text of the tree's tokens
+    let mut other = NodeCache::new();
+    assert_eq!(
+        tree.apply_edits(&[(range(0, 1), "x")], &mut other),
+        Err(EditError::UnresolvedText(range(0, 1)))
+    );
+}
@@ -0,0 +1,69 @@
+use super::*;
+use cstree::{syntax::Highlighter, text::TextRange};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    First,
+    Second,
+    Third,
+}
+
+/// Classifies tokens by the digit after the `.` in their text.
+struct DigitHighlighter<'r, R>(&'r R);
+
+impl<R: Resolver> Highlighter<SyntaxKind> for DigitHighlighter<'_, R> {
+    type Class = Class;
+
+    fn classify(&self, token: &SyntaxToken) -> Class {
+        match &token.resolve_text(self.0)[2..] {
+            "0" => Class::First,
+            "1" => Class::Second,
+            _ => Class::Third,
+        }
+    }
+}
+
+fn two_level_tree() -> Element<'static> {
+    use Element::*;
+    Node(vec![
+        Node(vec![Token("0.0"), Token("0.1")]),
+        Node(vec![Token("1.0")]),
+        Node(vec![Token("2.0"), Token("2.1"), Token("2.2")]),
+    ])
+}
+
+fn range(start: u32, end: u32) -> TextRange {
+    TextRange::new(start.into(), end.into())
+}
+
+#[test]
+fn highlight_tokens() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    build_recursive(&two_level_tree(), &mut builder, 0);
+    let (green, cache) = builder.finish();
+    let resolver = cache.unwrap().into_interner().unwrap();
+    let root: SyntaxNode = SyntaxNode::new_root(green);
+
+    let highlighter = DigitHighlighter(&resolver);
+    assert_eq!(
+        root.highlight(&highlighter),
+        [
+            (range(0, 3), Class::First),
+            (range(3, 6), Class::Second),
+            (range(6, 9), Class::First),
+            (range(9, 12), Class::First),
+            (range(12, 15), Class::Second),
+            (range(15, 18), Class::Third),
+        ]
+    );
+    assert_eq!(
+        root.highlight_merged(&highlighter),
+        [
+            (range(0, 3), Class::First),
+            (range(3, 6), Class::Second),
+            (range(6, 12), Class::First),
+            (range(12, 15), Class::Second),
+            (range(15, 18), Class::Third),
+        ]
+    );
+}
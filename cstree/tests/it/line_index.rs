@@ -0,0 +1,165 @@
+use super::*;
+use cstree::{
+    build::GreenNodeBuilder,
+    text::{text_in_range, LineCol, LineIndex, TextRange, TextSize},
+};
+
+const TEXT: &str = "fn main() {\n    let x = 1;\r\n\n}\n";
+
+#[test]
+fn line_col_roundtrip() {
+    let index = LineIndex::new(TEXT);
+    assert_eq!(index.len(), TextSize::of(TEXT));
+    assert_eq!(index.line_count(), 5);
+
+    let mut line = 0;
+    let mut col = 0;
+    for (offset, byte) in TEXT.bytes().enumerate() {
+        let offset = TextSize::from(offset as u32);
+        let position = LineCol { line, col };
+        assert_eq!(index.line_col(offset), position);
+        assert_eq!(index.offset(position), Some(offset));
+        if byte == b'\n' {
+            line += 1;
+            col = 0;
+        } else {
+            col += 1;
+        }
+    }
+    assert_eq!(index.line_col(index.len()), LineCol { line: 4, col: 0 });
+
+    assert_eq!(index.offset(LineCol { line: 0, col: 12 }), None);
+    assert_eq!(index.offset(LineCol { line: 5, col: 0 }), None);
+    assert_eq!(index.offset(LineCol { line: 1, col: u32::MAX }), None);
+    assert_eq!(index.line_range(1), Some(TextRange::new(12.into(), 27.into())));
+    assert_eq!(index.line_range(2), Some(TextRange::new(28.into(), 28.into())));
+}
+
+#[test]
+fn from_chunks_agrees_with_str() {
+    let index = LineIndex::new(TEXT);
+    for chunk_size in 1..TEXT.len() {
+        let chunks: Vec<_> = TEXT
+            .as_bytes()
+            .chunks(chunk_size)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap())
+            .collect();
+        let chunked = LineIndex::from_chunks(chunks.iter().copied());
+        assert_eq!(chunked, index);
+        for offset in 0..=TEXT.len() {
+            let offset = TextSize::from(offset as u32);
+            assert_eq!(chunked.line_col(offset), index.line_col(offset));
+        }
+    }
+}
+
+#[test]
+fn text_in_range_agrees_with_str() {
+    for chunk_size in 1..TEXT.len() {
+        let chunks: Vec<_> = TEXT
+            .as_bytes()
+            .chunks(chunk_size)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap())
+            .collect();
+        for start in 0..=TEXT.len() {
+            for end in start..=TEXT.len() {
+                let range = TextRange::new((start as u32).into(), (end as u32).into());
+                assert_eq!(text_in_range(chunks.iter().copied(), range).as_deref(), Some(&TEXT[start..end]));
+            }
+        }
+        let past_end = TextRange::new(0.into(), TextSize::of(TEXT) + TextSize::from(1));
+        assert_eq!(text_in_range(chunks.iter().copied(), past_end), None);
+    }
+    // not a `char` boundary
+    assert_eq!(text_in_range(["aλb"], TextRange::new(0.into(), 2.into())), None);
+}
+
+#[test]
+fn empty_text() {
+    let index = LineIndex::new("");
+    assert!(index.is_empty());
+    assert_eq!(index.line_count(), 1);
+    assert_eq!(index.line_col(0.into()), LineCol { line: 0, col: 0 });
+    assert_eq!(index.line_range(0), Some(TextRange::default()));
+}
+
+#[test]
+fn element_positions() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    builder.token(SyntaxKind(1), "let");
+    builder.token(SyntaxKind(2), "\n  ");
+    builder.start_node(SyntaxKind(3));
+    builder.token(SyntaxKind(1), "x");
+    builder.token(SyntaxKind(2), "\n");
+    builder.token(SyntaxKind(1), "y");
+    builder.finish_node();
+    builder.finish_node();
+    let (tree, cache) = builder.finish();
+    let resolver = cache.unwrap().into_interner().unwrap();
+    let root: SyntaxNode = SyntaxNode::new_root(tree);
+    let index = LineIndex::new(&root.resolve_text(&resolver).to_string());
+
+    for token in root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+    {
+        assert_eq!(token.position(&index), index.line_col(token.text_range().start()));
+    }
+    let node = root.first_child().unwrap();
+    assert_eq!(node.position(&index), LineCol { line: 1, col: 2 });
+    assert_eq!(node.last_token().unwrap().position(&index), LineCol { line: 2, col: 0 });
+    assert_eq!(root.position(&index), LineCol { line: 0, col: 0 });
+}
+
+#[test]
+fn token_columns() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    builder.token(SyntaxKind(1), "fn");
+    builder.token(SyntaxKind(2), "\n\t");
+    builder.token(SyntaxKind(1), "λ");
+    builder.token(SyntaxKind(2), " ");
+    builder.token(SyntaxKind(1), "=");
+    builder.finish_node();
+    let (tree, cache) = builder.finish();
+    let resolver = cache.unwrap().into_interner().unwrap();
+    let root: SyntaxNode = SyntaxNode::new_root(tree);
+    let index = LineIndex::new(&root.resolve_text(&resolver).to_string());
+
+    let columns: Vec<_> = root
+        .children_with_tokens()
+        .map(|element| element.into_token().unwrap().columns(&resolver, &index))
+        .collect();
+    // the tab is a single byte and `char`, but the lambda has two bytes
+    assert_eq!(columns, [(0, 0), (2, 2), (1, 1), (3, 2), (4, 3)]);
+}
+
+#[test]
+fn node_line_ranges() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    builder.token(SyntaxKind(1), "ab");
+    builder.start_node(SyntaxKind(3));
+    builder.token(SyntaxKind(1), "cd\n");
+    builder.token(SyntaxKind(1), "efg\nh");
+    builder.finish_node();
+    builder.start_node(SyntaxKind(3));
+    builder.token(SyntaxKind(1), "ij");
+    builder.finish_node();
+    builder.token(SyntaxKind(2), "\n");
+    builder.finish_node();
+    let (tree, cache) = builder.finish();
+    let resolver = cache.unwrap().into_interner().unwrap();
+    let root: SyntaxNode = SyntaxNode::new_root(tree);
+    let index = LineIndex::new(&root.resolve_text(&resolver).to_string());
+    let range = |start: u32, end: u32| TextRange::new(start.into(), end.into());
+
+    let node = root.first_child().unwrap();
+    let lines: Vec<_> = node.line_ranges(&index).collect();
+    assert_eq!(lines, [range(2, 4), range(5, 8), range(9, 10)]);
+    let single = node.next_sibling().unwrap();
+    assert_eq!(single.line_ranges(&index).collect::<Vec<_>>(), [single.text_range()]);
+    // the trailing line break does not extend the root to the empty last line
+    assert_eq!(root.line_ranges(&index).count(), 3);
+}
@@ -1,13 +1,23 @@
+mod arena;
 mod basic;
+mod binary;
+mod edit;
+mod highlight;
+mod line_index;
+#[cfg(feature = "rayon")]
+mod parallel;
 mod regressions;
 mod sendsync;
 #[cfg(feature = "serialize")]
 mod serde;
+#[cfg(feature = "testing")]
+mod test_utils;
+mod visit;
 
 use cstree::{
     build::{GreenNodeBuilder, NodeCache},
     green::GreenNode,
-    interning::Interner,
+    interning::{Interner, Resolver},
     RawSyntaxKind, Syntax,
 };
 
@@ -27,7 +37,7 @@ pub enum Element<'s> {
     Token(&'s str),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct SyntaxKind(u32);
 
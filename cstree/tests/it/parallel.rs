@@ -0,0 +1,32 @@
+use super::*;
+use cstree::build::NodeCache;
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+fn large_tree() -> Element<'static> {
+    use Element::*;
+    Node(
+        (0..200)
+            .map(|i| {
+                Node(vec![
+                    Token("a"),
+                    Node((0..i % 7).map(|_| Node(vec![Token("b"), Token("c")])).collect()),
+                ])
+            })
+            .collect(),
+    )
+}
+
+#[test]
+fn par_descendants_visits_all_nodes() {
+    let mut cache = NodeCache::new();
+    let tree: SyntaxNode = SyntaxNode::new_root(build_tree_with_cache(&large_tree(), &mut cache));
+
+    assert_eq!(tree.par_descendants().count(), tree.descendants().count());
+    let parallel: HashSet<_> = tree.par_descendants().map(|node| node.text_range()).collect();
+    let sequential: HashSet<_> = tree.descendants().map(|node| node.text_range()).collect();
+    assert_eq!(parallel, sequential);
+
+    let node = tree.children().nth(13).unwrap();
+    assert_eq!(node.par_descendants().count(), 2 + 13 % 7);
+}
@@ -287,3 +287,59 @@ fn serialize_tree_without_data() {
 
     serde_test::assert_tokens(&tree, three_level_tree_tokens().as_slice());
 }
+
+#[test]
+fn deserialize_malformed_tree() {
+    const ENTER: &str = r#"{"t":"EnterNode","c":[0,false]}"#;
+    const LEAVE: &str = r#"{"t":"LeaveNode"}"#;
+    let tree = |events: &[&str]| format!("[[{}],[]]", events.join(","));
+
+    let unbalanced = tree(&[ENTER, LEAVE, LEAVE]);
+    let err = serde_json::from_str::<TestNode>(&unbalanced).unwrap_err();
+    assert!(err.to_string().contains("invalid serialized tree"));
+    let two_roots = tree(&[ENTER, LEAVE, ENTER, LEAVE]);
+    assert!(serde_json::from_str::<TestNode>(&two_roots).is_err());
+    let unfinished = tree(&[ENTER, ENTER, LEAVE]);
+    assert!(serde_json::from_str::<TestNode>(&unfinished).is_err());
+
+    let mut too_deep = vec![ENTER; cstree::green::DEFAULT_MAX_DEPTH + 1];
+    too_deep.extend(vec![LEAVE; cstree::green::DEFAULT_MAX_DEPTH + 1]);
+    let err = serde_json::from_str::<TestNode>(&tree(&too_deep)).unwrap_err();
+    assert!(err.to_string().contains("nested too deeply"));
+}
+
+#[test]
+fn deserialize_with_max_depth() {
+    let deep = build_tree(three_level_tree());
+    let depth = deep.descendants().map(|node| node.ancestors().count()).max().unwrap();
+    let serialized = serde_json::to_string(&deep).unwrap();
+    let deserialize = |max_depth| {
+        let mut deserializer = serde_json::Deserializer::from_str(&serialized);
+        ResolvedNode::<String>::deserialize_with_max_depth(&mut deserializer, max_depth)
+    };
+
+    let err = deserialize(depth - 1).unwrap_err();
+    assert!(err.to_string().contains("nested too deeply"));
+    let deserialized = deserialize(depth).unwrap();
+    assert_eq!(TestNode::new(deserialized), TestNode::new(deep));
+}
+
+#[test]
+fn serialize_flags() {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    builder.start_node(SyntaxKind(0));
+    builder.start_node(SyntaxKind(1));
+    builder.set_flags(0b10);
+    builder.token(SyntaxKind(2), "a");
+    builder.finish_node();
+    builder.finish_node();
+    let (node, cache) = builder.finish();
+    let tree: ResolvedNode<String> = SyntaxNode::new_root_with_resolver(node, cache.unwrap().into_interner().unwrap());
+
+    let serialized = serde_json::to_string(&tree).unwrap();
+    assert!(serialized.contains(r#"{"t":"EnterNode","c":[0,false]}"#));
+    assert!(serialized.contains(r#"{"t":"EnterFlaggedNode","c":[1,false,2]}"#));
+    let deserialized: ResolvedNode<String> = serde_json::from_str(&serialized).unwrap();
+    let flags: Vec<_> = deserialized.descendants().map(|node| node.flags()).collect();
+    assert_eq!(flags, [0b10, 0b10]);
+}
@@ -0,0 +1,54 @@
+use super::*;
+use cstree::{
+    test_utils::{assert_kind_at, assert_range_text, assert_tree_text},
+    text::TextRange,
+};
+
+fn two_level_tree() -> Element<'static> {
+    use Element::*;
+    Node(vec![
+        Node(vec![Token("0.0"), Token("0.1")]),
+        Node(vec![Token("1.0")]),
+        Node(vec![Token("2.0"), Token("2.1"), Token("2.2")]),
+    ])
+}
+
+fn build_tree() -> (SyntaxNode, impl Resolver) {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    build_recursive(&two_level_tree(), &mut builder, 0);
+    let (node, cache) = builder.finish();
+    (SyntaxNode::new_root(node), cache.unwrap().into_interner().unwrap())
+}
+
+#[test]
+fn tree_text() {
+    let (tree, resolver) = build_tree();
+    let tokens: String = tree.tokens().map(|token| token.resolve_text(&resolver)).collect();
+    assert_tree_text(&tree, &resolver, &tokens);
+    assert_range_text(&tree, &resolver, TextRange::new(6.into(), 12.into()), "1.02.0");
+}
+
+#[test]
+#[should_panic(expected = "tree text does not match")]
+fn tree_text_mismatch() {
+    let (tree, resolver) = build_tree();
+    assert_tree_text(&tree, &resolver, "0.00.1");
+}
+
+#[test]
+fn kind_at() {
+    let (tree, _) = build_tree();
+    assert_kind_at(&tree, 7.into(), SyntaxKind(5));
+    assert_kind_at(&tree, 7.into(), SyntaxKind(4));
+    assert_kind_at(&tree, 7.into(), SyntaxKind(0));
+    // between two tokens
+    assert_kind_at(&tree, 9.into(), SyntaxKind(4));
+    assert_kind_at(&tree, 9.into(), SyntaxKind(7));
+}
+
+#[test]
+#[should_panic(expected = "no element of kind")]
+fn kind_at_mismatch() {
+    let (tree, _) = build_tree();
+    assert_kind_at(&tree, 7.into(), SyntaxKind(6));
+}
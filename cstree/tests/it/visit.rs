@@ -0,0 +1,80 @@
+use super::*;
+use cstree::{syntax::Visitor, traversal::VisitControl};
+
+fn two_level_tree() -> Element<'static> {
+    use Element::*;
+    Node(vec![
+        Node(vec![Token("0.0"), Token("0.1")]),
+        Node(vec![Token("1.0")]),
+        Node(vec![Token("2.0"), Token("2.1"), Token("2.2")]),
+    ])
+}
+
+fn build_tree(root: &Element<'_>) -> SyntaxNode {
+    let mut builder: GreenNodeBuilder<SyntaxKind> = GreenNodeBuilder::new();
+    build_recursive(root, &mut builder, 0);
+    SyntaxNode::new_root(builder.finish().0)
+}
+
+#[derive(Default)]
+struct Counter {
+    enter:    usize,
+    leave:    usize,
+    tokens:   usize,
+    /// The kind of node at which to skip the children.
+    skip_at:  Option<SyntaxKind>,
+    /// The kind of node at which to stop the traversal.
+    break_at: Option<SyntaxKind>,
+}
+
+impl Visitor<SyntaxKind> for Counter {
+    fn visit_node_enter(&mut self, node: &SyntaxNode) -> VisitControl {
+        self.enter += 1;
+        if Some(node.kind()) == self.break_at {
+            VisitControl::Break
+        } else if Some(node.kind()) == self.skip_at {
+            VisitControl::SkipChildren
+        } else {
+            VisitControl::Continue
+        }
+    }
+
+    fn visit_node_leave(&mut self, _node: &SyntaxNode) {
+        self.leave += 1;
+    }
+
+    fn visit_token(&mut self, _token: &SyntaxToken) {
+        self.tokens += 1;
+    }
+}
+
+#[test]
+fn count_elements() {
+    let tree = build_tree(&two_level_tree());
+    let mut counter = Counter::default();
+    assert_eq!(tree.accept(&mut counter), VisitControl::Continue);
+    assert_eq!((counter.enter, counter.leave, counter.tokens), (4, 4, 6));
+}
+
+#[test]
+fn skip_subtree() {
+    let tree = build_tree(&two_level_tree());
+    let mut counter = Counter {
+        skip_at: Some(SyntaxKind(6)),
+        ..Default::default()
+    };
+    assert_eq!(tree.accept(&mut counter), VisitControl::Continue);
+    assert_eq!((counter.enter, counter.leave, counter.tokens), (4, 4, 3));
+}
+
+#[test]
+fn stop_early() {
+    let tree = build_tree(&two_level_tree());
+    let mut counter = Counter {
+        break_at: Some(SyntaxKind(4)),
+        ..Default::default()
+    };
+    assert_eq!(tree.accept(&mut counter), VisitControl::Break);
+    // the root and the first node were left, the second node was entered, but nothing after it
+    assert_eq!((counter.enter, counter.leave, counter.tokens), (3, 1, 2));
+}
@@ -17,6 +17,9 @@ fn basic() {
     assert_eq!(MySyntax::from_raw(RawSyntaxKind(0)), SyntaxKind::A);
     assert_eq!(MySyntax::from_raw(RawSyntaxKind(1)), SyntaxKind::B);
 
+    assert_eq!(MySyntax::try_from_raw(RawSyntaxKind(1)), Some(SyntaxKind::B));
+    assert_eq!(MySyntax::try_from_raw(RawSyntaxKind(2)), None);
+
     assert!(MySyntax::static_text(SyntaxKind::A).is_none());
     assert_eq!(MySyntax::static_text(SyntaxKind::B), Some("b"));
 }